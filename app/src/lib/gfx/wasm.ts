@@ -6,13 +6,15 @@ export async function initWasm() {
   const init = pkg.default;
   const wasm = await init();
 
-  const { Engine } = pkg;
+  const { Engine, SpawnField, StreamKind } = pkg;
   memory = wasm.memory;
 
   engine = new Engine();
 
   return {
     engine,
-    memory
+    memory,
+    SpawnField,
+    StreamKind
   };
 }
@@ -1,17 +1,47 @@
 import { initWasm } from '$lib/gfx/wasm';
-import { mat4, vec4, vec3 } from "gl-matrix";
 
 let device: GPUDevice;
 let context: GPUCanvasContext;
 let format: GPUTextureFormat;
 
 let pipeline: GPURenderPipeline;
+// Alpha blended, without depth writes; draws the transparent ranges.
+let transparentPipeline: GPURenderPipeline;
 
-let modelStorageBuffer: GPUBuffer;
 let cameraUniformBuffer: GPUBuffer;
 
-let modelBindGroup: GPUBindGroup;
 let cameraBindGroup: GPUBindGroup;
+let cameraLayout: GPUBindGroupLayout;
+
+// The engine's packed light buffer (see light::LIGHT_STRIDE).
+let lightBuffer: GPUBuffer;
+
+// GPU side of one engine instance stream (see StreamKind): the persistent
+// instance slots plus the list of slots to draw this frame.
+type GpuStream = {
+  kind: number;
+  instances: GPUBuffer;
+  visible: GPUBuffer;
+  bindGroup: GPUBindGroup;
+};
+
+let modelLayout: GPUBindGroupLayout;
+let streams: GpuStream[] = [];
+
+// Geometry per mesh id (the engine's Mesh component), copied from the
+// engine's mesh assets. Interleaved position + normal + uv, 8 floats per
+// vertex, drawn indexed.
+type GpuMesh = {
+  vertices: GPUBuffer;
+  indices: GPUBuffer;
+  indexCount: number;
+};
+
+const meshes = new Map<number, GpuMesh>();
+const VERTEX_STRIDE = 8;
+
+// Instance layout the shader's Model struct is written for.
+const INSTANCE_LAYOUT_VERSION = 2;
 
 let depthTexture: GPUTexture;
 
@@ -20,7 +50,8 @@ export let engineAPI: any = null
 let memory: WebAssembly.Memory;
 
 let animationFrameId: number | null = null;
-let activeEntity = -1;
+const eventListeners = new Set<(events: Uint32Array) => void>();
+let activeEntity: number | undefined;
 let lastX = 0;
 let lastY = 0;
 let mouseDownX = 0;
@@ -49,98 +80,209 @@ function createDepthTexture() {
   });
 }
 
-function createCubeVertexBuffer(device: GPUDevice): GPUBuffer {
-
-  const vertices = new Float32Array([
-    // position          // normal
-
-    // Front
-    -0.5, -0.5, 0.5,   /**/ 0, 0, 1,
-    0.5, -0.5, 0.5,   /**/ 0, 0, 1,
-    0.5, 0.5, 0.5,   /**/ 0, 0, 1,
-    -0.5, -0.5, 0.5,   /**/ 0, 0, 1,
-    0.5, 0.5, 0.5,   /**/ 0, 0, 1,
-    -0.5, 0.5, 0.5,   /**/ 0, 0, 1,
-
-    // Back
-    -0.5, -0.5, -0.5,   /**/ 0, 0, -1,
-    0.5, 0.5, -0.5,   /**/ 0, 0, -1,
-    0.5, -0.5, -0.5,   /**/ 0, 0, -1,
-    -0.5, -0.5, -0.5,   /**/ 0, 0, -1,
-    -0.5, 0.5, -0.5,   /**/ 0, 0, -1,
-    0.5, 0.5, -0.5,   /**/ 0, 0, -1,
-
-    // Left
-    -0.5, -0.5, -0.5,   /**/ -1, 0, 0,
-    -0.5, -0.5, 0.5,   /**/ -1, 0, 0,
-    -0.5, 0.5, 0.5,   /**/ -1, 0, 0,
-    -0.5, -0.5, -0.5,   /**/ -1, 0, 0,
-    -0.5, 0.5, 0.5,   /**/ -1, 0, 0,
-    -0.5, 0.5, -0.5,   /**/ -1, 0, 0,
-
-    // Right
-    0.5, -0.5, -0.5,   /**/ 1, 0, 0,
-    0.5, 0.5, 0.5,   /**/ 1, 0, 0,
-    0.5, -0.5, 0.5,   /**/ 1, 0, 0,
-    0.5, -0.5, -0.5,   /**/ 1, 0, 0,
-    0.5, 0.5, -0.5,   /**/ 1, 0, 0,
-    0.5, 0.5, 0.5,   /**/ 1, 0, 0,
-
-    // Top
-    -0.5, 0.5, -0.5,   /**/ 0, 1, 0,
-    -0.5, 0.5, 0.5,   /**/ 0, 1, 0,
-    0.5, 0.5, 0.5,   /**/ 0, 1, 0,
-    -0.5, 0.5, -0.5,   /**/ 0, 1, 0,
-    0.5, 0.5, 0.5,   /**/ 0, 1, 0,
-    0.5, 0.5, -0.5,   /**/ 0, 1, 0,
-
-    // Bottom
-    -0.5, -0.5, -0.5,   /**/ 0, -1, 0,
-    0.5, -0.5, 0.5,   /**/ 0, -1, 0,
-    -0.5, -0.5, 0.5,   /**/ 0, -1, 0,
-    -0.5, -0.5, -0.5,   /**/ 0, -1, 0,
-    0.5, -0.5, -0.5,   /**/ 0, -1, 0,
-    0.5, -0.5, 0.5,   /**/ 0, -1, 0,
-  ]);
-
-  const buffer = device.createBuffer({
-    size: vertices.byteLength,
+function uploadMesh(id: number): GpuMesh {
+  const vertices = new Float32Array(
+    memory.buffer,
+    engine.mesh_vertices_ptr(id),
+    engine.mesh_vertices_len(id)
+  );
+  const indices = new Uint32Array(
+    memory.buffer,
+    engine.mesh_indices_ptr(id),
+    engine.mesh_indices_len(id)
+  );
+
+  const vertexBuffer = device.createBuffer({
+    size: Math.max(4, vertices.byteLength),
     usage: GPUBufferUsage.VERTEX | GPUBufferUsage.COPY_DST
   });
+  const indexBuffer = device.createBuffer({
+    size: Math.max(4, indices.byteLength),
+    usage: GPUBufferUsage.INDEX | GPUBufferUsage.COPY_DST
+  });
+
+  device.queue.writeBuffer(vertexBuffer, 0, vertices);
+  device.queue.writeBuffer(indexBuffer, 0, indices);
+
+  return { vertices: vertexBuffer, indices: indexBuffer, indexCount: indices.length };
+}
+
+// Mesh assets are only ever added, so upload the ids not seen yet.
+function syncMeshes() {
+  const count = engine.mesh_count();
+
+  for (let id = meshes.size; id < count; id++) {
+    meshes.set(id, uploadMesh(id));
+  }
+}
+
+function createStorageBuffer(size: number): GPUBuffer {
+  return device.createBuffer({
+    // Bindings can't be empty; keep a floor and round up to 4 bytes.
+    size: Math.max(256, Math.ceil(size / 4) * 4),
+    usage: GPUBufferUsage.STORAGE | GPUBufferUsage.COPY_DST
+  });
+}
+
+function createStream(kind: number): GpuStream {
+  const instances = createStorageBuffer(1024 * 64);
+  const visible = createStorageBuffer(1024 * 4);
+
+  return { kind, instances, visible, bindGroup: createStreamBindGroup(instances, visible) };
+}
+
+function createStreamBindGroup(instances: GPUBuffer, visible: GPUBuffer): GPUBindGroup {
+  return device.createBindGroup({
+    layout: modelLayout,
+    entries: [
+      { binding: 0, resource: { buffer: instances } },
+      { binding: 1, resource: { buffer: visible } }
+    ]
+  });
+}
+
+// Uploads what changed in one stream since last frame. Buffers that are
+// too small are replaced (at least doubled) and re-uploaded whole.
+function uploadStream(stream: GpuStream) {
+  const kind = stream.kind;
+
+  const len = engine.instance_len(kind);
+  const ranges: Uint32Array = engine.take_dirty_ranges(kind);
+  const data = new Float32Array(memory.buffer, engine.instance_ptr(kind), len);
+
+  if (data.byteLength > stream.instances.size) {
+    stream.instances.destroy();
+    stream.instances = createStorageBuffer(Math.max(data.byteLength, stream.instances.size * 2));
+    stream.bindGroup = createStreamBindGroup(stream.instances, stream.visible);
+
+    device.queue.writeBuffer(stream.instances, 0, data.buffer, data.byteOffset, data.byteLength);
+  } else {
+    for (let i = 0; i < ranges.length; i += 2) {
+      device.queue.writeBuffer(
+        stream.instances,
+        ranges[i],
+        data.buffer,
+        data.byteOffset + ranges[i],
+        ranges[i + 1]
+      );
+    }
+  }
+
+  if (engine.take_visible_dirty(kind)) {
+    const count = engine.visible_len(kind);
+    const visible = new Uint32Array(memory.buffer, engine.visible_ptr(kind), count);
+
+    if (visible.byteLength > stream.visible.size) {
+      stream.visible.destroy();
+      stream.visible = createStorageBuffer(Math.max(visible.byteLength, stream.visible.size * 2));
+      stream.bindGroup = createStreamBindGroup(stream.instances, stream.visible);
+    }
+    device.queue.writeBuffer(stream.visible, 0, visible.buffer, visible.byteOffset, visible.byteLength);
+  }
+}
+
+function createCameraBindGroup(): GPUBindGroup {
+  return device.createBindGroup({
+    layout: cameraLayout,
+    entries: [
+      { binding: 0, resource: { buffer: cameraUniformBuffer } },
+      { binding: 1, resource: { buffer: lightBuffer } }
+    ]
+  });
+}
 
-  device.queue.writeBuffer(buffer, 0, vertices);
-  return buffer;
+function uploadLights() {
+  if (!engine.take_lights_dirty()) return;
+
+  const data = new Float32Array(memory.buffer, engine.light_ptr(), engine.light_len());
+
+  if (data.byteLength > lightBuffer.size) {
+    lightBuffer.destroy();
+    lightBuffer = createStorageBuffer(Math.max(data.byteLength, lightBuffer.size * 2));
+    cameraBindGroup = createCameraBindGroup();
+  }
+  device.queue.writeBuffer(lightBuffer, 0, data.buffer, data.byteOffset, data.byteLength);
 }
 
 function createPipeline(
   modelLayout: GPUBindGroupLayout,
-  cameraLayout: GPUBindGroupLayout
+  cameraLayout: GPUBindGroupLayout,
+  transparent = false
 ): GPURenderPipeline {
 
   const shader = device.createShaderModule({
     code: `
+    // Instance layout version 2 (see INSTANCE_LAYOUT_VERSION).
     struct Model {
       model: mat4x4<f32>,
-      color: vec4<f32>,
+      base_color: vec4<f32>,
+      // rgb, strength
+      emissive: vec4<f32>,
+      metallic: f32,
+      roughness: f32,
+      flags: u32,
+      _pad: f32,
     };
+    const FLAG_UNLIT: u32 = 1u;
+    // One record of the engine's light buffer.
     struct Light {
+      position: vec3<f32>,
+      kind: u32,
+      // Direction the light travels in.
       direction: vec3<f32>,
-      padding: f32
+      range: f32,
+      color: vec3<f32>,
+      intensity: f32,
+      cos_inner: f32,
+      cos_outer: f32,
+      _pad: vec2<f32>,
+    };
+    struct Lights {
+      count: u32,
+      _pad0: u32,
+      _pad1: u32,
+      _pad2: u32,
+      items: array<Light>,
     };
+    const LIGHT_DIRECTIONAL: u32 = 0u;
+    const LIGHT_SPOT: u32 = 2u;
     @group(0) @binding(0)
     var<storage, read> models: array<Model>;
 
+    // Slots of models to draw, indexed by instance.
+    @group(0) @binding(1)
+    var<storage, read> visible: array<u32>;
+
+    // Engine sky block (see sky::SKY_LEN).
+    struct Sky {
+      // Direction sunlight travels in.
+      light_direction: vec3<f32>,
+      ambient: f32,
+      light_color: vec3<f32>,
+      light_intensity: f32,
+      zenith: vec3<f32>,
+      horizon: vec3<f32>,
+    };
+
+    struct Camera {
+      view_proj: mat4x4<f32>,
+      // xyz; w unused
+      eye: vec4<f32>,
+      sky: Sky,
+    };
+
     @group(1) @binding(0)
-    var<uniform> view_proj: mat4x4<f32>;
+    var<uniform> camera: Camera;
 
     @group(1) @binding(1)
-    var<uniform> light: Light;
+    var<storage, read> lights: Lights;
 
     struct VSOut {
       @builtin(position) pos: vec4<f32>,
       @location(0) normal: vec3<f32>,
       @location(1) color: vec4<f32>,
       @location(2) world_pos: vec3<f32>,
+      @location(3) @interpolate(flat) slot: u32,
     };
 
     @vertex
@@ -152,11 +294,13 @@ function createPipeline(
 
       var out: VSOut;
 
-      let modelMatrix = models[instance].model;
-      out.color = models[instance].color;
+      let slot = visible[instance];
+      let modelMatrix = models[slot].model;
+      out.color = models[slot].base_color;
+      out.slot = slot;
 
       let world = modelMatrix * vec4<f32>(in_pos, 1.0);
-      out.pos = view_proj * world;
+      out.pos = camera.view_proj * world;
       out.world_pos = world.xyz;
 
       let normalWorld = (modelMatrix * vec4<f32>(in_normal, 0.0)).xyz;
@@ -169,22 +313,67 @@ function createPipeline(
     fn fs_main(
       @location(0) normal: vec3<f32>,
       @location(1) color: vec4<f32>,
-      @location(2) world_pos: vec3<f32>
+      @location(2) world_pos: vec3<f32>,
+      @location(3) @interpolate(flat) slot: u32
     ) -> @location(0) vec4<f32> {
 
+      let material = models[slot];
+      let emission = material.emissive.rgb * material.emissive.w;
+
+      if ((material.flags & FLAG_UNLIT) != 0u) {
+        return vec4<f32>(color.rgb + emission, color.a);
+      }
+
       let N = normalize(normal);
-      let lightDir = normalize(light.direction);
+      let V = normalize(camera.eye.xyz - world_pos);
 
       // ---------- lighting ----------
 
-      let ambient = 0.35;
-      let diffuse = max(dot(N, -lightDir), 0.0);
+      let ambient = camera.sky.ambient;
 
       let rim = pow(1.0 - max(dot(N, vec3<f32>(0.0,1.0,0.0)),0.0),2.0) * 0.15;
 
-      let lighting = ambient + diffuse * 0.7 + rim;
+      // Metals have no diffuse term and tint their highlight.
+      let albedo = color.rgb * (1.0 - material.metallic);
+      let specColor = mix(vec3<f32>(0.04), color.rgb, material.metallic);
+
+      // Blinn-Phong highlight, sharper for smoother surfaces.
+      let shininess = 2.0 / max(pow(material.roughness, 4.0), 1e-4) - 2.0;
 
-      var finalColor = color.rgb * lighting;
+      var diffuse = vec3<f32>(0.0);
+      var specular = vec3<f32>(0.0);
+
+      for (var i = 0u; i < lights.count; i++) {
+        let light = lights.items[i];
+
+        var L = -light.direction;
+        var attenuation = 1.0;
+
+        if (light.kind != LIGHT_DIRECTIONAL) {
+          let toLight = light.position - world_pos;
+          let d = length(toLight);
+          L = toLight / max(d, 1e-4);
+
+          // Inverse square, smoothly cut off at range.
+          let cutoff = clamp(1.0 - pow(d / max(light.range, 1e-4), 4.0), 0.0, 1.0);
+          attenuation = cutoff * cutoff / (1.0 + d * d);
+
+          if (light.kind == LIGHT_SPOT) {
+            attenuation *= smoothstep(light.cos_outer, light.cos_inner, dot(-L, light.direction));
+          }
+        }
+
+        let radiance = light.color * light.intensity * attenuation;
+        let NdotL = max(dot(N, L), 0.0);
+        let H = normalize(V + L);
+
+        diffuse += radiance * NdotL;
+        specular += radiance * pow(max(dot(N, H), 0.0), shininess) * NdotL;
+      }
+
+      var finalColor = albedo * (ambient + diffuse + rim)
+        + specColor * (ambient * material.metallic + specular)
+        + emission;
 
       // ---------- ground grid ----------
 //WIP
@@ -222,11 +411,9 @@ function createPipeline(
 
       let fog = clamp((distance - fogStart) / (fogEnd - fogStart), 0.0, 1.0);
 
-      let skyColor = vec3<f32>(0.70, 0.82, 0.92);
+      finalColor = mix(finalColor, camera.sky.horizon, fog);
 
-      finalColor = mix(finalColor, skyColor, fog);
-
-      return vec4<f32>(finalColor, 1.0);
+      return vec4<f32>(finalColor, color.a);
     }
     `
   });
@@ -241,17 +428,24 @@ function createPipeline(
       module: shader,
       entryPoint: 'vs_main',
       buffers: [{
-        arrayStride: 6 * 4,
+        arrayStride: VERTEX_STRIDE * 4,
         attributes: [
           { shaderLocation: 0, offset: 0, format: 'float32x3' },
-          { shaderLocation: 1, offset: 12, format: 'float32x3' }
+          { shaderLocation: 1, offset: 12, format: 'float32x3' },
+          { shaderLocation: 2, offset: 24, format: 'float32x2' }
         ]
       }]
     },
     fragment: {
       module: shader,
       entryPoint: 'fs_main',
-      targets: [{ format }]
+      targets: [{
+        format,
+        blend: transparent ? {
+          color: { srcFactor: 'src-alpha', dstFactor: 'one-minus-src-alpha', operation: 'add' },
+          alpha: { srcFactor: 'one', dstFactor: 'one-minus-src-alpha', operation: 'add' }
+        } : undefined
+      }]
     },
     primitive: {
       topology: 'triangle-list',
@@ -259,7 +453,9 @@ function createPipeline(
     },
     depthStencil: {
       format: 'depth24plus',
-      depthWriteEnabled: true,
+      // Transparent instances are sorted instead; they still test
+      // against the opaque depth.
+      depthWriteEnabled: !transparent,
       depthCompare: 'less'
     }
   });
@@ -288,6 +484,14 @@ export async function startWebGPU(canvas: HTMLCanvasElement) {
   const wasm = await initWasm();
   engine = wasm.engine;
   memory = wasm.memory;
+  const { SpawnField, StreamKind } = wasm;
+
+  if (engine.instance_layout_version() !== INSTANCE_LAYOUT_VERSION) {
+    throw new Error(
+      `engine instance layout v${engine.instance_layout_version()}, ` +
+      `shader expects v${INSTANCE_LAYOUT_VERSION}`
+    );
+  }
 
   engineAPI = {
     setColor: (r: number, g: number, b: number) => engine.set_selected_color(r, g, b),
@@ -295,49 +499,81 @@ export async function startWebGPU(canvas: HTMLCanvasElement) {
     orbit: (dx: number, dy: number) => {
       engine.camera_orbit(dx, dy)
     },
+    frameScene: () => engine.frame_scene(),
+    // Hour of the day in 0..24 and how many hours pass per second.
+    setTimeOfDay: (hour: number) => engine.set_time_of_day(hour),
+    getTimeOfDay: () => engine.time_of_day(),
+    setDaySpeed: (hoursPerSecond: number) => engine.set_day_speed(hoursPerSecond),
+    // Appearance of one entity. Omitted fields keep their current value
+    // (or the Material defaults).
+    setMaterial: (handle: number, m: {
+      color?: [number, number, number, number],
+      metallic?: number,
+      roughness?: number,
+      emissive?: [number, number, number, number],
+      unlit?: boolean
+    }) => {
+      if (m.color || m.metallic !== undefined || m.roughness !== undefined) {
+        const current = engine.get_material(handle);
+        if (!current) return;
+        const [r, g, b, a] = m.color ?? current.slice(0, 4);
+        engine.set_material(
+          handle, r, g, b, a,
+          m.metallic ?? current[4],
+          m.roughness ?? current[5]
+        );
+      }
+      if (m.emissive) engine.set_emissive(handle, ...m.emissive);
+      if (m.unlit !== undefined) engine.set_unlit(handle, m.unlit);
+    },
     getObjectCount: () => engine.entity_count(),
-    getSelectedCount: () => engine.selected_count()
-  }
-
-  function buildRay(clientX: number, clientY: number) {
-
-    const rect = canvas.getBoundingClientRect();
-
-    const ndcX = ((clientX - rect.left) / rect.width) * 2 - 1;
-    const ndcY = -(((clientY - rect.top) / rect.height) * 2 - 1);
-
-    const vpPtr = engine.view_proj_ptr();
-    const vp = new Float32Array(memory.buffer, vpPtr, 16);
-
-    const invVP = mat4.create();
-    mat4.invert(invVP, vp);
-
-    const nearPoint = vec4.fromValues(ndcX, ndcY, -1, 1);
-    const farPoint = vec4.fromValues(ndcX, ndcY, 1, 1);
-
-    vec4.transformMat4(nearPoint, nearPoint, invVP);
-    vec4.transformMat4(farPoint, farPoint, invVP);
+    getSelectedCount: () => engine.selected_count(),
+    // Instances drawn / skipped by frustum culling in the last rebuild.
+    getVisibleCount: () => engine.visible_count(),
+    getCulledCount: () => engine.culled_count(),
+    // [x, y, depth, state] in canvas pixels, or undefined. state is a
+    // ScreenState: 0 on screen, 1 off screen, 2 behind the camera.
+    projectEntity: (handle: number) => engine.project_entity(handle),
+    // Projects every entity at once. The views alias wasm memory, so read
+    // them before calling back into the engine.
+    projectAll: () => {
+      const count = engine.project_all();
+      return {
+        handles: new Uint32Array(memory.buffer, engine.projection_handles_ptr(), count),
+        points: new Float32Array(memory.buffer, engine.projection_ptr(), count * 4)
+      };
+    },
+    // Loads a model and returns the handle of the entity holding it: the
+    // root of the node hierarchy for glb, a single entity for obj. Throws
+    // with the engine's message on malformed files.
+    importModel: (bytes: Uint8Array, format: 'glb' | 'obj') => {
+      let handle: number;
+
+      if (format === 'glb') {
+        handle = engine.import_glb(bytes);
+      } else {
+        const mesh = engine.import_obj(bytes);
+        handle = engine.create_entity();
+        engine.add_transform(handle, 0, 0, 0, 0, 0, 0);
+        engine.set_mesh(handle, mesh);
+      }
 
-    for (let i = 0; i < 3; i++) {
-      nearPoint[i] /= nearPoint[3];
-      farPoint[i] /= farPoint[3];
+      syncMeshes();
+      return handle;
+    },
+    // Called once per frame with a flat Uint32Array of [kind, a, b]
+    // records (see EventKind in the engine). Returns an unsubscribe fn.
+    onEvents: (listener: (events: Uint32Array) => void) => {
+      eventListeners.add(listener);
+      return () => eventListeners.delete(listener);
     }
+  }
 
-    const rayOrigin = vec3.fromValues(
-      nearPoint[0],
-      nearPoint[1],
-      nearPoint[2]
-    );
-
-    const rayDir = vec3.create();
-    vec3.subtract(rayDir,
-      vec3.fromValues(farPoint[0], farPoint[1], farPoint[2]),
-      rayOrigin
-    );
-
-    vec3.normalize(rayDir, rayDir);
-
-    return { rayOrigin, rayDir };
+  // Pointer position relative to the canvas, in the units given to
+  // engine.set_viewport.
+  function canvasPoint(clientX: number, clientY: number) {
+    const rect = canvas.getBoundingClientRect();
+    return { x: clientX - rect.left, y: clientY - rect.top };
   }
   canvas.addEventListener("pointerdown", (e) => {
 
@@ -348,21 +584,16 @@ export async function startWebGPU(canvas: HTMLCanvasElement) {
         canvas.requestPointerLock();
       }
     }
-    const { rayOrigin, rayDir } = buildRay(e.clientX, e.clientY);
+    const p = canvasPoint(e.clientX, e.clientY);
 
-    const hit = engine.pick(
-      rayOrigin[0], rayOrigin[1], rayOrigin[2],
-      rayDir[0], rayDir[1], rayDir[2],
-      e.shiftKey,
-      e.ctrlKey
-    );
+    const hit = engine.pick_at(p.x, p.y, e.shiftKey, e.ctrlKey);
 
     mouseDownX = e.clientX;
     mouseDownY = e.clientY;
     lastX = e.clientX;
     lastY = e.clientY;
 
-    if (hit >= 0) {
+    if (hit !== undefined) {
       mode = "potential-drag";
       activeEntity = hit;
     } else {
@@ -389,12 +620,9 @@ export async function startWebGPU(canvas: HTMLCanvasElement) {
 
         mode = "drag";
 
-        const { rayOrigin, rayDir } = buildRay(e.clientX, e.clientY);
+        const p = canvasPoint(e.clientX, e.clientY);
 
-        engine.begin_drag(
-          rayOrigin[0], rayOrigin[1], rayOrigin[2],
-          rayDir[0], rayDir[1], rayDir[2]
-        );
+        engine.begin_drag_at(p.x, p.y);
       }
     }
 
@@ -402,15 +630,12 @@ export async function startWebGPU(canvas: HTMLCanvasElement) {
 
       engine.update(0);
 
-      const { rayOrigin, rayDir } = buildRay(e.clientX, e.clientY);
+      const p = canvasPoint(e.clientX, e.clientY);
       let lastDragUpdate = 0;
 
       if (performance.now() - lastDragUpdate > 16) {
         lastDragUpdate = performance.now();
-        engine.update_drag_ray(
-          rayOrigin[0], rayOrigin[1], rayOrigin[2],
-          rayDir[0], rayDir[1], rayDir[2]
-        );
+        engine.update_drag_at(p.x, p.y);
       }
     }
   });
@@ -441,86 +666,86 @@ export async function startWebGPU(canvas: HTMLCanvasElement) {
 
   engine.add_color(ground, 0.35, 0.37, 0.40)
   engine.set_static(ground)
+  // The engine's time of day moves the sun and drives its light.
   const sun = engine.create_entity();
-  const sunDistance = 12;
-  const lightData = new Float32Array(4);
+  engine.add_transform(sun, 0, 0, 0, 0, 0, 0);
   engine.set_scale(sun, 0.8, 0.8, 0.8);
   engine.add_color(sun, 1.0, 0.9, 0.3);
-  engine.set_static(sun);
+  engine.set_emissive(sun, 1.0, 0.85, 0.4, 1.5);
+  engine.set_unlit(sun, true);
+  engine.set_sun(sun);
 
   const gridSize = 10;
   const spacing = 1.2;
 
+  // position, color, velocity: 9 floats per cube
+  const layout = SpawnField.Position | SpawnField.Color | SpawnField.Velocity;
+  const records = new Float32Array(gridSize * gridSize * 9);
+
   for (let x = 0; x < gridSize; x++) {
     for (let z = 0; z < gridSize; z++) {
 
-      const e = engine.create_entity();
+      const o = (x * gridSize + z) * 9;
+
+      records[o + 0] = (x - gridSize / 2) * spacing;
+      records[o + 1] = 0.6;
+      records[o + 2] = (z - gridSize / 2) * spacing;
 
-      const px = (x - gridSize / 2) * spacing;
-      const pz = (z - gridSize / 2) * spacing;
+      records[o + 3] = 0.3 + Math.random() * 0.2;
+      records[o + 4] = 0.8 + Math.random() * 0.1;
+      records[o + 5] = 0.3 + Math.random() * 0.2;
 
-      engine.add_transform(e, px, 0.6, pz, 0.0, 0.0, 0.0);
-      engine.add_color(
-        e,
-        0.3 + Math.random() * 0.2,
-        0.8 + Math.random() * 0.1,
-        0.3 + Math.random() * 0.2
-      )
       // optional: slight movement or none
-      engine.add_velocity(e, 0.0, 0.0, 0.0);
+      records[o + 6] = 0.0;
+      records[o + 7] = 0.0;
+      records[o + 8] = 0.0;
     }
   }
 
-  modelStorageBuffer = device.createBuffer({
-    size: 1024 * 64,
-    usage: GPUBufferUsage.STORAGE | GPUBufferUsage.COPY_DST
-  });
-  const lightBuffer = device.createBuffer({
-    size: 16,
-    usage: GPUBufferUsage.UNIFORM | GPUBufferUsage.COPY_DST
-  });
+  engine.spawn_batch(records, layout);
+
+  lightBuffer = createStorageBuffer(engine.light_len() * 4);
+  // view_proj, eye, then the sky block
   cameraUniformBuffer = device.createBuffer({
-    size: 64,
+    size: 144,
     usage: GPUBufferUsage.UNIFORM | GPUBufferUsage.COPY_DST
   });
 
-  const modelLayout = device.createBindGroupLayout({
+  modelLayout = device.createBindGroupLayout({
     entries: [{
       binding: 0,
+      // The fragment stage reads the material fields.
+      visibility: GPUShaderStage.VERTEX | GPUShaderStage.FRAGMENT,
+      buffer: { type: 'read-only-storage' }
+    },
+    {
+      binding: 1,
       visibility: GPUShaderStage.VERTEX,
       buffer: { type: 'read-only-storage' }
     }]
   });
 
-  const cameraLayout = device.createBindGroupLayout({
+  cameraLayout = device.createBindGroupLayout({
     entries: [{
       binding: 0,
-      visibility: GPUShaderStage.VERTEX,
+      visibility: GPUShaderStage.VERTEX | GPUShaderStage.FRAGMENT,
       buffer: { type: 'uniform' }
     },
     {
       binding: 1,
       visibility: GPUShaderStage.FRAGMENT,
-      buffer: { type: 'uniform' }
+      buffer: { type: 'read-only-storage' }
     }]
   });
 
-  modelBindGroup = device.createBindGroup({
-    layout: modelLayout,
-    entries: [{ binding: 0, resource: { buffer: modelStorageBuffer } }]
-  });
+  streams = [createStream(StreamKind.Static), createStream(StreamKind.Dynamic)];
 
-  cameraBindGroup = device.createBindGroup({
-    layout: cameraLayout,
-    entries: [
-      { binding: 0, resource: { buffer: cameraUniformBuffer } },
-      { binding: 1, resource: { buffer: lightBuffer } }
-    ]
-  });
+  cameraBindGroup = createCameraBindGroup();
 
   pipeline = createPipeline(modelLayout, cameraLayout);
+  transparentPipeline = createPipeline(modelLayout, cameraLayout, true);
 
-  const vertexBuffer = createCubeVertexBuffer(device);
+  syncMeshes();
 
   let lastTime = performance.now();
   engine.set_camera(
@@ -532,63 +757,31 @@ export async function startWebGPU(canvas: HTMLCanvasElement) {
     0.1,
     100
   );
+  engine.set_viewport(canvas.clientWidth, canvas.clientHeight);
 
   function frame(now: number) {
 
     if (resizeCanvas(canvas)) {
       context.configure({ device, format, alphaMode: 'opaque' });
       createDepthTexture();
+      engine.set_viewport(canvas.clientWidth, canvas.clientHeight);
     }
 
-    const t = now * 0.0003;
-
-    const lightDir = [
-      Math.sin(t),
-      -1.8,
-      Math.cos(t)
-    ];
-
-    const sun_dist = Math.hypot(...lightDir);
-    const dir = lightDir.map(v => v / sun_dist);
-    const sunPos = [
-      -dir[0] * sunDistance,
-      -dir[1] * sunDistance,
-      -dir[2] * sunDistance
-    ];
-    engine.add_transform(
-      sun,
-      sunPos[0],
-      sunPos[1],
-      sunPos[2],
-      0, 0, 0
-    );
-    lightData[0] = dir[0];
-    lightData[1] = dir[1];
-    lightData[2] = dir[2];
-    lightData[3] = 0;
-    device.queue.writeBuffer(
-      lightBuffer,
-      0,
-      lightData
-    );
     engine.camera_orbit(0.0003, 0);
     const delta = (now - lastTime) * 0.001;
     lastTime = now;
 
 
-    const ptr = engine.render_buffer_ptr();
-    const len = engine.render_buffer_len();
+    engine.update(delta);
 
-    const renderData = new Float32Array(memory.buffer, ptr, len);
+    const events: Uint32Array = engine.drain_events();
+    if (events.length > 0) {
+      eventListeners.forEach((listener) => listener(events));
+    }
+
+    streams.forEach(uploadStream);
+    uploadLights();
 
-    engine.update(delta);
-    device.queue.writeBuffer(
-      modelStorageBuffer,
-      0,
-      renderData.buffer,
-      renderData.byteOffset,
-      renderData.byteLength
-    );
     const vpPtr = engine.view_proj_ptr();
     const vpData = new Float32Array(memory.buffer, vpPtr, 16);
 
@@ -599,15 +792,20 @@ export async function startWebGPU(canvas: HTMLCanvasElement) {
       vpData.byteOffset,
       vpData.byteLength
     );
+    device.queue.writeBuffer(cameraUniformBuffer, 64, new Float32Array(engine.camera_eye()));
+
+    const sky = new Float32Array(engine.sky());
+    device.queue.writeBuffer(cameraUniformBuffer, 80, sky);
+    // Horizon color, matching the fog.
+    const [skyR, skyG, skyB] = sky.subarray(12, 15);
 
-    const instanceCount = len / 20;
     const encoder = device.createCommandEncoder();
     const pass = encoder.beginRenderPass({
       colorAttachments: [{
         view: context.getCurrentTexture().createView(),
         loadOp: 'clear',
         storeOp: 'store',
-        clearValue: { r: 0.70, g: 0.82, b: 0.92, a: 1 }
+        clearValue: { r: skyR, g: skyG, b: skyB, a: 1 }
       }],
       depthStencilAttachment: {
         view: depthTexture.createView(),
@@ -617,12 +815,41 @@ export async function startWebGPU(canvas: HTMLCanvasElement) {
       }
     });
 
-    pass.setPipeline(pipeline);
-    pass.setBindGroup(0, modelBindGroup);
+    // Draws the stream's batches lying in its [first, count] range of
+    // the visible list.
+    function drawRange(stream: GpuStream, range: Uint32Array) {
+      if (range[1] === 0) return;
+
+      // [mesh, firstInstance, instanceCount] per batch
+      const batches: Uint32Array = engine.batches(stream.kind);
+      const end = range[0] + range[1];
+
+      pass.setBindGroup(0, stream.bindGroup);
+
+      for (let i = 0; i < batches.length; i += 3) {
+        if (batches[i + 1] < range[0] || batches[i + 1] >= end) continue;
+
+        const mesh = meshes.get(batches[i]);
+        if (!mesh) continue;
+
+        pass.setVertexBuffer(0, mesh.vertices);
+        pass.setIndexBuffer(mesh.indices, 'uint32');
+        pass.drawIndexed(mesh.indexCount, batches[i + 2], 0, 0, batches[i + 1]);
+      }
+    }
+
     pass.setBindGroup(1, cameraBindGroup);
-    pass.setVertexBuffer(0, vertexBuffer);
 
-    pass.draw(36, instanceCount);
+    pass.setPipeline(pipeline);
+    for (const stream of streams) {
+      drawRange(stream, engine.opaque_range(stream.kind));
+    }
+
+    // Back to front, after everything opaque.
+    pass.setPipeline(transparentPipeline);
+    for (const stream of streams) {
+      drawRange(stream, engine.transparent_range(stream.kind));
+    }
 
     pass.end();
     device.queue.submit([encoder.finish()]);
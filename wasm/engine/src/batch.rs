@@ -0,0 +1,213 @@
+use std::fmt;
+
+use wasm_bindgen::prelude::*;
+
+use crate::components::{Color, Transform, Velocity};
+use crate::math::{Quat, Vec3};
+use crate::world::{Entity, EntityLimit, World};
+
+/// Fields that can appear in a `spawn_batch` record. Combine them as a
+/// bitmask; each record holds the selected fields in this order, three
+/// floats each. Rotation is Euler angles, as in `Engine::add_transform`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SpawnField {
+    Position = 1,
+    Rotation = 2,
+    Scale = 4,
+    Color = 8,
+    Velocity = 16,
+}
+
+impl SpawnField {
+    pub const ALL: [SpawnField; 5] = [
+        SpawnField::Position,
+        SpawnField::Rotation,
+        SpawnField::Scale,
+        SpawnField::Color,
+        SpawnField::Velocity,
+    ];
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BatchError {
+    /// The layout mask has bits that don't name a field, or names none.
+    InvalidLayout(u32),
+    /// The data length is not a whole number of records.
+    Truncated { len: usize, stride: usize },
+    /// The world has room for fewer entities than there are records;
+    /// nothing was spawned.
+    EntityLimit,
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::InvalidLayout(mask) => write!(f, "invalid spawn layout mask {mask:#x}"),
+            BatchError::Truncated { len, stride } => write!(
+                f,
+                "batch data length {len} is not a multiple of the record size {stride}"
+            ),
+            BatchError::EntityLimit => write!(f, "{EntityLimit}"),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+/// Floats per record for a layout mask.
+pub fn stride(layout: u32) -> Result<usize, BatchError> {
+    let known = SpawnField::ALL.iter().fold(0, |mask, &f| mask | f as u32);
+
+    if layout == 0 || layout & !known != 0 {
+        return Err(BatchError::InvalidLayout(layout));
+    }
+
+    Ok(layout.count_ones() as usize * 3)
+}
+
+/// Spawns one entity per record in `data`.
+///
+/// Any of position/rotation/scale gives the entity a `Transform`; missing
+/// parts default to the origin, no rotation and a 0.5 scale, matching
+/// `Engine::add_transform`.
+pub fn spawn_batch(
+    world: &mut World,
+    data: &[f32],
+    layout: u32,
+) -> Result<Vec<Entity>, BatchError> {
+    let stride = stride(layout)?;
+
+    if !data.len().is_multiple_of(stride) {
+        return Err(BatchError::Truncated {
+            len: data.len(),
+            stride,
+        });
+    }
+
+    let count = data.len() / stride;
+    if count > world.spawn_capacity() {
+        return Err(BatchError::EntityLimit);
+    }
+
+    let has = |field: SpawnField| layout & field as u32 != 0;
+    let mut entities = Vec::with_capacity(count);
+
+    for record in data.chunks_exact(stride) {
+        let mut fields = record.chunks_exact(3).map(|v| [v[0], v[1], v[2]]);
+        let mut next = |field: SpawnField| if has(field) { fields.next() } else { None };
+
+        let position = next(SpawnField::Position);
+        let rotation = next(SpawnField::Rotation);
+        let scale = next(SpawnField::Scale);
+        let color = next(SpawnField::Color);
+        let velocity = next(SpawnField::Velocity);
+
+        let entity = world.spawn().expect("capacity checked above");
+
+        if position.is_some() || rotation.is_some() || scale.is_some() {
+            world.insert(
+                entity,
+                Transform {
+                    position: position.map_or(Vec3::ZERO, Vec3::from),
+                    rotation: rotation
+                        .map_or(Quat::IDENTITY, |[x, y, z]| Quat::from_euler(x, y, z)),
+                    scale: scale.map_or(Vec3::splat(0.5), Vec3::from),
+                },
+            );
+        }
+        if let Some(rgb) = color {
+            world.insert(entity, Color { rgb });
+        }
+        if let Some(linear) = velocity {
+            world.insert(
+                entity,
+                Velocity {
+                    linear: linear.into(),
+                },
+            );
+        }
+
+        entities.push(entity);
+    }
+
+    Ok(entities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POSITION_COLOR: u32 = SpawnField::Position as u32 | SpawnField::Color as u32;
+
+    #[test]
+    fn layout_masks_are_validated() {
+        assert_eq!(stride(SpawnField::Position as u32), Ok(3));
+        assert_eq!(stride(POSITION_COLOR), Ok(6));
+        assert_eq!(stride(31), Ok(15));
+        assert_eq!(stride(0), Err(BatchError::InvalidLayout(0)));
+        assert_eq!(stride(32 | 1), Err(BatchError::InvalidLayout(33)));
+    }
+
+    #[test]
+    fn records_fill_in_missing_transform_parts() {
+        let mut world = World::new();
+        let data = [1.0, 2.0, 3.0, 0.1, 0.2, 0.3, -1.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+
+        let entities = spawn_batch(&mut world, &data, POSITION_COLOR).unwrap();
+
+        assert_eq!(entities.len(), 2);
+
+        let transform = *world.get::<Transform>(entities[0]).unwrap();
+        assert_eq!(transform.position, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(transform.rotation, Quat::IDENTITY);
+        assert_eq!(transform.scale, Vec3::splat(0.5));
+        assert_eq!(world.get::<Color>(entities[1]).unwrap().rgb, [1.0; 3]);
+        assert!(!world.has::<Velocity>(entities[0]));
+
+        // Only a colour: no transform at all.
+        let bare = spawn_batch(&mut world, &[0.5; 3], SpawnField::Color as u32).unwrap()[0];
+        assert!(!world.has::<Transform>(bare));
+    }
+
+    #[test]
+    fn bad_input_spawns_nothing() {
+        let mut world = World::new();
+
+        assert_eq!(
+            spawn_batch(&mut world, &[0.0; 7], POSITION_COLOR),
+            Err(BatchError::Truncated { len: 7, stride: 6 })
+        );
+        assert_eq!(
+            spawn_batch(&mut world, &[0.0; 3], 64),
+            Err(BatchError::InvalidLayout(64))
+        );
+        assert_eq!(spawn_batch(&mut world, &[], POSITION_COLOR), Ok(Vec::new()));
+        assert!(world.is_empty());
+    }
+
+    #[test]
+    fn batches_larger_than_the_free_space_spawn_nothing() {
+        let mut world = World::new();
+        while world.spawn().is_ok() {}
+        world.despawn(world.entity(0).unwrap());
+        world.despawn(world.entity(1).unwrap());
+        world.drain_lifecycle().for_each(drop);
+        let live = world.len();
+
+        let data = [0.0; 9];
+        let layout = SpawnField::Position as u32;
+
+        assert_eq!(
+            spawn_batch(&mut world, &data, layout),
+            Err(BatchError::EntityLimit)
+        );
+        assert_eq!(world.len(), live);
+        assert_eq!(world.drain_lifecycle().count(), 0);
+
+        assert_eq!(
+            spawn_batch(&mut world, &data[..6], layout).map(|e| e.len()),
+            Ok(2)
+        );
+    }
+}
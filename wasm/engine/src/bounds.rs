@@ -0,0 +1,347 @@
+use crate::components::Hidden;
+use crate::hierarchy::GlobalTransform;
+use crate::math::{Mat4, Vec3};
+use crate::query::{With, Without};
+use crate::world::World;
+
+/// Axis-aligned box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// The cube every mesh is modelled on: ±0.5 on each axis.
+    pub const UNIT_CUBE: Aabb = Aabb {
+        min: Vec3::splat(-0.5),
+        max: Vec3::splat(0.5),
+    };
+
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb {
+            min: min.min(max),
+            max: min.max(max),
+        }
+    }
+    pub fn from_center_half(center: Vec3, half: Vec3) -> Self {
+        Aabb {
+            min: center - half,
+            max: center + half,
+        }
+    }
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+    /// Overlap test; boxes that only touch do not overlap.
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        (0..3).all(|i| self.min[i] < other.max[i] && other.min[i] < self.max[i])
+    }
+    /// Smallest axis-aligned box containing this box transformed by `m`.
+    pub fn transformed(&self, m: &Mat4) -> Aabb {
+        let [x, y, z] = [0, 1, 2].map(|i| m.col(i).truncate().abs());
+        let half = self.half_extents();
+
+        Aabb::from_center_half(
+            m.transform_point3(self.center()),
+            x * half.x + y * half.y + z * half.z,
+        )
+    }
+    /// Distance along the ray to the first hit, 0 if `origin` is inside.
+    /// `dir` need not be normalized; the result is in units of `dir`.
+    pub fn ray_hit(&self, origin: Vec3, dir: Vec3) -> Option<f32> {
+        let mut tmin = f32::NEG_INFINITY;
+        let mut tmax = f32::INFINITY;
+
+        for i in 0..3 {
+            if dir[i].abs() < 1e-6 {
+                if origin[i] < self.min[i] || origin[i] > self.max[i] {
+                    return None;
+                }
+            } else {
+                let inv = 1.0 / dir[i];
+                let mut t1 = (self.min[i] - origin[i]) * inv;
+                let mut t2 = (self.max[i] - origin[i]) * inv;
+
+                if t1 > t2 {
+                    std::mem::swap(&mut t1, &mut t2);
+                }
+
+                tmin = tmin.max(t1);
+                tmax = tmax.min(t2);
+
+                if tmin > tmax {
+                    return None;
+                }
+            }
+        }
+
+        if tmax < 0.0 {
+            None
+        } else {
+            Some(tmin.max(0.0))
+        }
+    }
+}
+
+/// Local extent of an entity, before its `Transform`. Entities without
+/// one are treated as [`Aabb::UNIT_CUBE`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub aabb: Aabb,
+    /// Bounding sphere around `aabb`'s center.
+    pub radius: f32,
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Bounds::from_aabb(Aabb::UNIT_CUBE)
+    }
+}
+
+impl Bounds {
+    pub fn from_aabb(aabb: Aabb) -> Self {
+        Bounds {
+            aabb,
+            radius: aabb.half_extents().length(),
+        }
+    }
+}
+
+/// World-space bounds from `Bounds` and `GlobalTransform`, written by
+/// [`update_world_bounds`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldBounds {
+    pub aabb: Aabb,
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl WorldBounds {
+    pub fn compute(bounds: &Bounds, matrix: &Mat4) -> Self {
+        let max_scale = (0..3)
+            .map(|i| matrix.col(i).truncate().length())
+            .fold(0.0, f32::max);
+
+        WorldBounds {
+            aabb: bounds.aabb.transformed(matrix),
+            center: matrix.transform_point3(bounds.aabb.center()),
+            radius: bounds.radius * max_scale,
+        }
+    }
+}
+
+/// Keeps a `WorldBounds` on every entity with a `GlobalTransform` that
+/// is not `Hidden`, and removes it from `Hidden` ones and ones that
+/// lost their `GlobalTransform`. Run
+/// after [`crate::hierarchy::propagate_transforms`].
+///
+/// Only entities whose transform or bounds changed this frame are
+/// recomputed, unless some entity lost its `Bounds`.
+pub fn update_world_bounds(world: &World) {
+    let since = world.change_tick();
+    let globals = world.storage::<GlobalTransform>();
+    let local = world.storage::<Bounds>();
+    let mut world_bounds = world.storage_mut::<WorldBounds>();
+
+    let refresh_all = local.removed_since(since);
+
+    if globals.removed_since(since) {
+        let stale: Vec<u32> = world_bounds
+            .entities()
+            .iter()
+            .copied()
+            .filter(|&index| !globals.contains_index(index))
+            .collect();
+        for index in stale {
+            world_bounds.remove_entity(world.entity_at(index));
+        }
+    }
+
+    world
+        .query_filtered::<&GlobalTransform, With<Hidden>>()
+        .for_each(|entity, _| {
+            world_bounds.remove_entity(entity);
+        });
+
+    world
+        .query_filtered::<(&GlobalTransform, Option<&Bounds>), Without<Hidden>>()
+        .for_each(|entity, (global, bounds)| {
+            let stale = refresh_all
+                || !world_bounds.contains(entity)
+                || globals.is_changed_since(entity.index(), since)
+                || local.is_changed_since(entity.index(), since);
+
+            if stale {
+                let bounds = bounds.copied().unwrap_or_default();
+                world_bounds.insert(entity, WorldBounds::compute(&bounds, &global.matrix));
+            }
+        });
+}
+
+/// Union of every entity's world bounds, or `None` for an empty scene.
+pub fn scene_bounds(world: &World) -> Option<Aabb> {
+    let mut scene: Option<Aabb> = None;
+
+    world.query::<&WorldBounds>().for_each(|_, bounds| {
+        scene = Some(match scene {
+            Some(aabb) => aabb.union(&bounds.aabb),
+            None => bounds.aabb,
+        });
+    });
+
+    scene
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Transform;
+    use crate::hierarchy::{Children, Parent, propagate_transforms};
+    use crate::math::Quat;
+    use crate::world::Entity;
+
+    const EPS: f32 = 1e-5;
+
+    fn assert_vec3(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < EPS, "{a:?} != {b:?}");
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register::<Transform>();
+        world.register::<Parent>();
+        world.register::<Children>();
+        world.register::<GlobalTransform>();
+        world.register::<Bounds>();
+        world.register::<WorldBounds>();
+        world.register::<Hidden>();
+        world
+    }
+
+    fn spawn_at(world: &mut World, position: Vec3) -> Entity {
+        let entity = world.spawn().unwrap();
+        world.insert(
+            entity,
+            Transform {
+                position,
+                ..Transform::IDENTITY
+            },
+        );
+        entity
+    }
+
+    fn update(world: &mut World) {
+        propagate_transforms(world);
+        update_world_bounds(world);
+        world.advance_tick();
+    }
+
+    #[test]
+    fn touching_boxes_do_not_overlap() {
+        let a = Aabb::new(Vec3::ZERO, Vec3::ONE);
+
+        assert!(a.overlaps(&Aabb::new(Vec3::splat(0.5), Vec3::splat(2.0))));
+        assert!(a.overlaps(&Aabb::new(Vec3::splat(0.25), Vec3::splat(0.75))));
+        assert!(!a.overlaps(&Aabb::new(
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(2.0, 1.0, 1.0)
+        )));
+        assert!(!a.overlaps(&Aabb::new(
+            Vec3::new(0.0, 2.0, 0.0),
+            Vec3::new(1.0, 3.0, 1.0)
+        )));
+    }
+
+    #[test]
+    fn transformed_boxes_contain_every_corner() {
+        let aabb = Aabb::new(Vec3::new(-1.0, -0.5, -0.25), Vec3::new(1.0, 0.5, 0.25));
+
+        // A quarter turn about Y swaps the X and Z extents.
+        let m = Mat4::from_scale_rotation_translation(
+            Vec3::splat(2.0),
+            Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+            Vec3::new(10.0, 0.0, 0.0),
+        );
+        let out = aabb.transformed(&m);
+        assert_vec3(out.min, Vec3::new(9.5, -1.0, -2.0));
+        assert_vec3(out.max, Vec3::new(10.5, 1.0, 2.0));
+
+        // An eighth turn grows the box to hold the rotated corners.
+        let m = Mat4::from_quat(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4));
+        let out = Aabb::UNIT_CUBE.transformed(&m);
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        assert_vec3(out.min, Vec3::new(-half, -half, -0.5));
+        assert_vec3(out.max, Vec3::new(half, half, 0.5));
+    }
+
+    #[test]
+    fn ray_hits_report_the_entry_distance() {
+        let aabb = Aabb::UNIT_CUBE;
+
+        let hit = aabb.ray_hit(Vec3::new(0.0, 0.0, 5.0), -Vec3::Z);
+        assert!((hit.unwrap() - 4.5).abs() < EPS);
+
+        // Distances are in units of `dir`.
+        let hit = aabb.ray_hit(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -2.0));
+        assert!((hit.unwrap() - 2.25).abs() < EPS);
+
+        assert_eq!(aabb.ray_hit(Vec3::ZERO, Vec3::X), Some(0.0));
+        assert_eq!(aabb.ray_hit(Vec3::new(0.0, 0.0, 5.0), Vec3::Z), None);
+        assert_eq!(aabb.ray_hit(Vec3::new(2.0, 0.0, 5.0), -Vec3::Z), None);
+        assert_eq!(
+            aabb.ray_hit(Vec3::new(0.0, 2.0, 5.0), Vec3::new(0.0, -0.1, -1.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn scene_bounds_is_the_union_of_world_bounds() {
+        let mut world = world();
+        assert_eq!(scene_bounds(&world), None);
+
+        spawn_at(&mut world, Vec3::new(-2.0, 0.0, 0.0));
+        let far = spawn_at(&mut world, Vec3::new(3.0, 1.0, 0.0));
+        world.insert(far, Bounds::from_aabb(Aabb::new(Vec3::ZERO, Vec3::ONE)));
+        update(&mut world);
+
+        let scene = scene_bounds(&world).unwrap();
+        assert_vec3(scene.min, Vec3::new(-2.5, -0.5, -0.5));
+        assert_vec3(scene.max, Vec3::new(4.0, 2.0, 1.0));
+
+        world.insert(far, Hidden);
+        update(&mut world);
+
+        let scene = scene_bounds(&world).unwrap();
+        assert_vec3(scene.min, Vec3::new(-2.5, -0.5, -0.5));
+        assert_vec3(scene.max, Vec3::new(-1.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn removing_the_transform_drops_world_state() {
+        let mut world = world();
+        let kept = spawn_at(&mut world, Vec3::ZERO);
+        let moved = spawn_at(&mut world, Vec3::new(5.0, 0.0, 0.0));
+        update(&mut world);
+        assert!(world.get::<WorldBounds>(moved).is_some());
+
+        world.remove::<Transform>(moved);
+        update(&mut world);
+
+        assert!(world.get::<GlobalTransform>(moved).is_none());
+        assert!(world.get::<WorldBounds>(moved).is_none());
+        assert!(world.get::<WorldBounds>(kept).is_some());
+
+        let scene = scene_bounds(&world).unwrap();
+        assert_vec3(scene.max, Vec3::splat(0.5));
+    }
+}
@@ -0,0 +1,291 @@
+use wasm_bindgen::prelude::*;
+
+use crate::math::{Mat4, Vec3, Vec4};
+
+/// Orbit camera around `target`. Inserted as a resource by
+/// `Engine::set_camera`.
+#[derive(Clone, Copy)]
+pub struct Camera {
+    pub target: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+    pub aspect: f32,
+}
+
+impl Camera {
+    /// World position of the eye on its orbit.
+    pub fn eye(&self) -> Vec3 {
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+
+        self.target + Vec3::new(cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw) * self.distance
+    }
+    /// Projection * view, column-major.
+    pub fn view_proj(&self) -> Mat4 {
+        let proj = Mat4::perspective(self.fov, self.aspect, self.near, self.far);
+        let view = Mat4::look_at(self.eye(), self.target, Vec3::Y);
+
+        proj * view
+    }
+}
+
+/// Size of the canvas in the same pixel units JS passes to the `*_at`
+/// entry points, origin top-left.
+#[derive(Clone, Copy, Default)]
+pub struct Viewport {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0.0 || self.height <= 0.0
+    }
+    /// Pixel position to normalized device coordinates, y up.
+    pub fn to_ndc(&self, px: f32, py: f32) -> (f32, f32) {
+        (px / self.width * 2.0 - 1.0, 1.0 - py / self.height * 2.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin: Vec3,
+    /// Unit length.
+    pub dir: Vec3,
+}
+
+impl Ray {
+    /// World-space ray through pixel (`px`, `py`), starting on the near
+    /// plane. `None` for an empty viewport or a degenerate projection.
+    pub fn from_screen(view_proj: &Mat4, viewport: &Viewport, px: f32, py: f32) -> Option<Ray> {
+        if viewport.is_empty() {
+            return None;
+        }
+
+        let (x, y) = viewport.to_ndc(px, py);
+        let inv = view_proj.inverse()?;
+
+        let near = inv.project_point3(Vec3::new(x, y, -1.0));
+        let far = inv.project_point3(Vec3::new(x, y, 1.0));
+
+        Some(Ray {
+            origin: near,
+            dir: (far - near).normalize(),
+        })
+    }
+}
+
+/// Where a projected point landed, as reported to JS.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScreenState {
+    /// In front of the camera and inside the viewport and depth range.
+    OnScreen = 0,
+    /// In front of the camera but outside the viewport or depth range.
+    OffScreen = 1,
+    /// Behind the eye; `x`/`y` are mirrored and should not be used.
+    Behind = 2,
+}
+
+/// A world point projected into the viewport.
+#[derive(Clone, Copy, Debug)]
+pub struct ScreenPoint {
+    /// Pixels from the viewport's top-left corner.
+    pub x: f32,
+    pub y: f32,
+    /// Distance in front of the eye along the view direction.
+    pub depth: f32,
+    pub state: ScreenState,
+}
+
+impl ScreenPoint {
+    /// Floats per point in the buffers handed to JS.
+    pub const STRIDE: usize = 4;
+
+    pub fn project(view_proj: &Mat4, viewport: &Viewport, p: Vec3) -> ScreenPoint {
+        let clip = *view_proj * p.extend(1.0);
+        let ndc = clip.truncate() / clip.w;
+
+        let inside = (-1.0..=1.0).contains(&ndc.x)
+            && (-1.0..=1.0).contains(&ndc.y)
+            && (-1.0..=1.0).contains(&ndc.z);
+
+        let state = if clip.w <= 0.0 {
+            ScreenState::Behind
+        } else if inside {
+            ScreenState::OnScreen
+        } else {
+            ScreenState::OffScreen
+        };
+
+        ScreenPoint {
+            x: (ndc.x + 1.0) * 0.5 * viewport.width,
+            y: (1.0 - ndc.y) * 0.5 * viewport.height,
+            depth: clip.w,
+            state,
+        }
+    }
+    pub fn to_array(self) -> [f32; Self::STRIDE] {
+        [self.x, self.y, self.depth, self.state as u32 as f32]
+    }
+}
+
+/// The six clip planes of a view-projection matrix, normals pointing
+/// inwards: `xyz` is the normal and `w` the offset, so a point `p` is on
+/// the inside of a plane when `n.dot(p) + w >= 0`.
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts left, right, bottom, top, near, far from the rows of
+    /// `view_proj` (Gribb/Hartmann), for the -1..1 clip depth of
+    /// `Mat4::perspective`.
+    pub fn from_view_proj(view_proj: &Mat4) -> Frustum {
+        let [r0, r1, r2, r3] = [0, 1, 2, 3].map(|i| view_proj.row(i));
+
+        let planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r3 + r2, r3 - r2].map(|p| {
+            let len = p.truncate().length();
+            if len > 0.0 { p / len } else { p }
+        });
+
+        Frustum { planes }
+    }
+    /// Conservative box test: `false` only if the box is entirely outside
+    /// one plane. Boxes near a frustum corner can pass while not visible.
+    pub fn intersects_aabb(&self, center: Vec3, half: Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            normal.dot(center) + plane.w + normal.abs().dot(half) >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPS: f32 = 1e-3;
+
+    /// Eye at +10 Z looking at the origin.
+    fn camera() -> Camera {
+        Camera {
+            target: Vec3::ZERO,
+            yaw: 0.0,
+            pitch: 0.0,
+            distance: 10.0,
+            fov: 1.0,
+            near: 0.1,
+            far: 100.0,
+            aspect: 800.0 / 600.0,
+        }
+    }
+
+    const VIEWPORT: Viewport = Viewport {
+        width: 800.0,
+        height: 600.0,
+    };
+
+    #[test]
+    fn screen_rays_pass_back_through_their_pixel() {
+        let view_proj = camera().view_proj();
+
+        let center = Ray::from_screen(&view_proj, &VIEWPORT, 400.0, 300.0).unwrap();
+        assert!((center.dir - -Vec3::Z).length() < EPS, "{:?}", center.dir);
+        assert!((center.origin - Vec3::new(0.0, 0.0, 9.9)).length() < EPS);
+
+        for (px, py) in [
+            (400.0, 300.0),
+            (0.0, 0.0),
+            (800.0, 0.0),
+            (0.0, 600.0),
+            (800.0, 600.0),
+        ] {
+            let ray = Ray::from_screen(&view_proj, &VIEWPORT, px, py).unwrap();
+            assert!((ray.dir.length() - 1.0).abs() < EPS);
+
+            // The origin sits on the near plane, and every point along
+            // the ray lands on the pixel it was cast through.
+            assert!((view_proj.project_point3(ray.origin).z + 1.0).abs() < EPS);
+            for t in [0.0, 5.0, 50.0] {
+                let ndc = view_proj.project_point3(ray.origin + ray.dir * t);
+                let x = (ndc.x + 1.0) * 0.5 * VIEWPORT.width;
+                let y = (1.0 - ndc.y) * 0.5 * VIEWPORT.height;
+                assert!(
+                    (x - px).abs() < 0.01 && (y - py).abs() < 0.01,
+                    "({x}, {y}) for ({px}, {py})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn empty_viewports_cast_no_ray() {
+        let view_proj = camera().view_proj();
+
+        assert!(Ray::from_screen(&view_proj, &Viewport::default(), 0.0, 0.0).is_none());
+        assert!(Ray::from_screen(&Mat4::from_scale(Vec3::ZERO), &VIEWPORT, 0.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn projected_points_report_where_they_landed() {
+        let view_proj = camera().view_proj();
+
+        let origin = ScreenPoint::project(&view_proj, &VIEWPORT, Vec3::ZERO);
+        assert_eq!(origin.state, ScreenState::OnScreen);
+        assert!((origin.x - 400.0).abs() < EPS && (origin.y - 300.0).abs() < EPS);
+        assert!((origin.depth - 10.0).abs() < EPS);
+
+        // Up in the world is up on screen, which is a smaller y.
+        let above = ScreenPoint::project(&view_proj, &VIEWPORT, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(above.state, ScreenState::OnScreen);
+        assert!(above.y < 300.0);
+
+        for p in [
+            Vec3::new(100.0, 0.0, 0.0),
+            Vec3::new(0.0, -100.0, 0.0),
+            Vec3::new(0.0, 0.0, -200.0),
+        ] {
+            let point = ScreenPoint::project(&view_proj, &VIEWPORT, p);
+            assert_eq!(point.state, ScreenState::OffScreen, "{p:?}");
+            assert!(point.depth > 0.0);
+        }
+
+        let behind = ScreenPoint::project(&view_proj, &VIEWPORT, Vec3::new(1.0, 1.0, 20.0));
+        assert_eq!(behind.state, ScreenState::Behind);
+        assert!(behind.depth < 0.0);
+        assert_eq!(behind.to_array()[3], ScreenState::Behind as u32 as f32);
+    }
+
+    #[test]
+    fn boxes_outside_any_plane_are_culled() {
+        let frustum = Frustum::from_view_proj(&camera().view_proj());
+        let half = Vec3::splat(0.5);
+
+        for plane in frustum.planes {
+            assert!((plane.truncate().length() - 1.0).abs() < EPS);
+        }
+
+        assert!(frustum.intersects_aabb(Vec3::ZERO, half));
+        // Straddling the near plane and the left edge both count.
+        assert!(frustum.intersects_aabb(Vec3::new(0.0, 0.0, 9.9), half));
+        assert!(frustum.intersects_aabb(Vec3::new(-7.5, 0.0, 0.0), Vec3::splat(1.0)));
+
+        for center in [
+            Vec3::new(0.0, 0.0, 20.0),
+            Vec3::new(0.0, 0.0, -200.0),
+            Vec3::new(50.0, 0.0, 0.0),
+            Vec3::new(-50.0, 0.0, 0.0),
+            Vec3::new(0.0, 50.0, 0.0),
+            Vec3::new(0.0, -50.0, 0.0),
+        ] {
+            assert!(!frustum.intersects_aabb(center, half), "{center:?}");
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::hierarchy;
+use crate::world::{Component, Entity, EntityLimit, World};
+
+type EntityCommand = Box<dyn FnOnce(&mut World, Entity)>;
+
+enum Command {
+    Despawn(Entity),
+    Entity(Entity, EntityCommand),
+}
+
+/// Structural changes recorded while the world is borrowed (typically from
+/// inside a query) and applied later with [`Commands::apply`].
+#[derive(Default)]
+pub struct Commands {
+    queue: Vec<Command>,
+}
+
+impl Commands {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Reserves a new entity, which later commands can target through
+    /// [`SpawnCommands::id`]. It is spawned when the queue is applied;
+    /// components added through the returned builder are inserted then.
+    pub fn spawn(&mut self, world: &World) -> Result<SpawnCommands<'_>, EntityLimit> {
+        Ok(SpawnCommands {
+            entity: world.reserve()?,
+            commands: self,
+        })
+    }
+    /// Queues `entity` and all of its descendants for despawning; it is
+    /// detached from its parent's `Children` when applied.
+    pub fn despawn(&mut self, entity: Entity) {
+        self.queue.push(Command::Despawn(entity));
+    }
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) {
+        self.queue.push(Command::Entity(
+            entity,
+            Box::new(move |world, entity| {
+                world.insert(entity, component);
+            }),
+        ));
+    }
+    pub fn remove<T: Component>(&mut self, entity: Entity) {
+        self.queue.push(Command::Entity(
+            entity,
+            Box::new(|world, entity| {
+                world.remove::<T>(entity);
+            }),
+        ));
+    }
+
+    /// Applies queued commands in the order they were recorded. Commands
+    /// aimed at entities that have since been despawned are dropped.
+    pub fn apply(&mut self, world: &mut World) {
+        world.flush_reserved();
+
+        for command in self.queue.drain(..) {
+            match command {
+                Command::Despawn(entity) => {
+                    hierarchy::despawn_recursive(world, entity);
+                }
+                Command::Entity(entity, command) => {
+                    if world.is_alive(entity) {
+                        command(world, entity);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub struct SpawnCommands<'a> {
+    commands: &'a mut Commands,
+    entity: Entity,
+}
+
+impl SpawnCommands<'_> {
+    /// The reserved entity, alive once the queue is applied.
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+    pub fn insert<T: Component>(self, component: T) -> Self {
+        self.commands.insert(self.entity, component);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Color(u32);
+    struct Static;
+
+    fn color(world: &World, entity: Entity) -> Option<u32> {
+        world.get::<Color>(entity).map(|c| c.0)
+    }
+
+    #[test]
+    fn spawned_entities_can_be_targeted_before_apply() {
+        let mut world = World::new();
+        let mut commands = Commands::new();
+
+        let entity = commands.spawn(&world).unwrap().insert(Color(1)).id();
+        let child = commands.spawn(&world).unwrap().id();
+        commands.insert(child, Color(2));
+        commands.insert(entity, Static);
+
+        assert!(!world.is_alive(entity));
+        assert_eq!(commands.len(), 3);
+
+        commands.apply(&mut world);
+
+        assert!(commands.is_empty());
+        assert_eq!(color(&world, entity), Some(1));
+        assert_eq!(color(&world, child), Some(2));
+        assert!(world.has::<Static>(entity));
+    }
+
+    #[test]
+    fn recycled_index_does_not_inherit_components() {
+        let mut world = World::new();
+        let mut commands = Commands::new();
+
+        let old = commands
+            .spawn(&world)
+            .unwrap()
+            .insert(Color(7))
+            .insert(Static)
+            .id();
+        commands.apply(&mut world);
+        commands.despawn(old);
+        commands.apply(&mut world);
+
+        let queued = commands.spawn(&world).unwrap().id();
+        commands.apply(&mut world);
+        let direct = world.spawn().unwrap();
+
+        assert_eq!(queued.index(), old.index());
+        assert_ne!(queued, old);
+        assert_eq!(color(&world, queued), None);
+        assert!(!world.has::<Static>(queued));
+        assert_eq!(color(&world, direct), None);
+    }
+
+    #[test]
+    fn commands_apply_in_recorded_order() {
+        let mut world = World::new();
+        let entity = world.spawn().unwrap();
+        let doomed = world.spawn().unwrap();
+        let mut commands = Commands::new();
+
+        commands.insert(entity, Color(1));
+        commands.insert(entity, Static);
+        commands.insert(entity, Color(2));
+        commands.remove::<Static>(entity);
+
+        // Commands aimed at the entity after its despawn are dropped.
+        commands.insert(doomed, Color(3));
+        commands.despawn(doomed);
+        commands.insert(doomed, Color(4));
+
+        commands.apply(&mut world);
+
+        assert_eq!(color(&world, entity), Some(2));
+        assert!(!world.has::<Static>(entity));
+        assert!(!world.is_alive(doomed));
+
+        let reused = world.spawn().unwrap();
+        assert_eq!(reused.index(), doomed.index());
+        assert_eq!(color(&world, reused), None);
+    }
+}
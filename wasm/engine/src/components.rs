@@ -0,0 +1,110 @@
+use crate::math::{Mat4, Quat, Vec3};
+
+#[derive(Clone, Copy)]
+pub struct Color {
+    pub rgb: [f32; 3],
+}
+
+/// Surface appearance, drawn instead of `Color` when both are present.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Material {
+    /// RGBA; alpha below 1 lets what is behind show through.
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+    /// Multiplier on `emissive`; above 1 for glow.
+    pub emissive_strength: f32,
+    /// Skips lighting: drawn at base color plus emission.
+    pub unlit: bool,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            base_color: [1.0; 4],
+            metallic: 0.0,
+            roughness: 0.5,
+            emissive: [0.0; 3],
+            emissive_strength: 1.0,
+            unlit: false,
+        }
+    }
+}
+
+impl Material {
+    /// Opaque dielectric of the given color, as `Color` is drawn.
+    pub fn from_color(rgb: [f32; 3]) -> Self {
+        Material {
+            base_color: [rgb[0], rgb[1], rgb[2], 1.0],
+            ..Material::default()
+        }
+    }
+}
+
+/// Local to the entity's `Parent`, or world space for roots.
+#[derive(Clone, Copy)]
+pub struct Transform {
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        position: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    /// Local-to-parent matrix: scale, then rotate, then translate.
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position)
+    }
+    /// Inverse of [`Transform::matrix`]. Shear, which a non-uniformly
+    /// scaled parent can introduce, is dropped.
+    pub fn from_matrix(m: Mat4) -> Self {
+        let (scale, rotation, position) = m.to_scale_rotation_translation();
+
+        Transform {
+            position,
+            rotation,
+            scale,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Velocity {
+    pub linear: Vec3,
+}
+
+/// Mesh asset to draw the entity with, by id into the
+/// [`crate::mesh::Meshes`] resource, which owns the vertex data; entities
+/// without a `Mesh` are drawn as [`Mesh::CUBE`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Mesh(pub u32);
+
+impl Mesh {
+    pub const CUBE: Mesh = Mesh(0);
+}
+
+/// Marker for entities that never move (ground, sun).
+#[derive(Clone, Copy)]
+pub struct Static;
+
+/// Marker for entities that are neither drawn nor picked and have no
+/// `WorldBounds`, such as the grouping nodes of an imported model. Their
+/// children are unaffected.
+#[derive(Clone, Copy)]
+pub struct Hidden;
+
+/// Marker for entities in the current selection.
+#[derive(Clone, Copy)]
+pub struct Selected;
+
+/// Offset from the drag plane hit point, stored while an entity is dragged.
+#[derive(Clone, Copy)]
+pub struct Dragging {
+    pub offset: Vec3,
+}
@@ -0,0 +1,121 @@
+use wasm_bindgen::prelude::*;
+
+use crate::world::Entity;
+
+/// Event kinds as they appear in the buffer returned by
+/// `Engine::drain_events`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EventKind {
+    EntitySpawned = 0,
+    EntityDespawned = 1,
+    SelectionChanged = 2,
+    DragStarted = 3,
+    DragEnded = 4,
+    Collision = 5,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EngineEvent {
+    EntitySpawned(Entity),
+    EntityDespawned(Entity),
+    /// Carries the new number of selected entities.
+    SelectionChanged(u32),
+    /// Carries the number of entities being dragged.
+    DragStarted(u32),
+    DragEnded,
+    /// A dragged entity started overlapping another one.
+    Collision(Entity, Entity),
+}
+
+/// Number of `u32`s per event in the encoded buffer: kind, a, b.
+pub const EVENT_STRIDE: usize = 3;
+
+impl EngineEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            EngineEvent::EntitySpawned(_) => EventKind::EntitySpawned,
+            EngineEvent::EntityDespawned(_) => EventKind::EntityDespawned,
+            EngineEvent::SelectionChanged(_) => EventKind::SelectionChanged,
+            EngineEvent::DragStarted(_) => EventKind::DragStarted,
+            EngineEvent::DragEnded => EventKind::DragEnded,
+            EngineEvent::Collision(..) => EventKind::Collision,
+        }
+    }
+    /// `[kind, a, b]`, with entities written as their packed handles.
+    pub fn encode(&self) -> [u32; EVENT_STRIDE] {
+        let (a, b) = match *self {
+            EngineEvent::EntitySpawned(e) | EngineEvent::EntityDespawned(e) => (e.to_bits(), 0),
+            EngineEvent::SelectionChanged(count) | EngineEvent::DragStarted(count) => (count, 0),
+            EngineEvent::DragEnded => (0, 0),
+            EngineEvent::Collision(a, b) => (a.to_bits(), b.to_bits()),
+        };
+
+        [self.kind() as u32, a, b]
+    }
+}
+
+/// FIFO of engine events, drained by JS once per frame.
+#[derive(Default)]
+pub struct Events {
+    queue: Vec<EngineEvent>,
+}
+
+impl Events {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn push(&mut self, event: EngineEvent) {
+        self.queue.push(event);
+    }
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+    pub fn drain(&mut self) -> impl Iterator<Item = EngineEvent> + '_ {
+        self.queue.drain(..)
+    }
+    /// Drains every queued event into a flat `[kind, a, b, ...]` buffer.
+    pub fn drain_encoded(&mut self) -> Vec<u32> {
+        let mut buffer = Vec::with_capacity(self.queue.len() * EVENT_STRIDE);
+
+        for event in self.queue.drain(..) {
+            buffer.extend_from_slice(&event.encode());
+        }
+
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_encode_as_kind_and_two_words() {
+        let a = Entity::from_bits(3 | (2 << 20));
+        let b = Entity::from_bits(9);
+
+        assert_eq!(EngineEvent::EntitySpawned(a).encode(), [0, a.to_bits(), 0]);
+        assert_eq!(EngineEvent::EntityDespawned(b).encode(), [1, 9, 0]);
+        assert_eq!(EngineEvent::SelectionChanged(4).encode(), [2, 4, 0]);
+        assert_eq!(EngineEvent::DragStarted(2).encode(), [3, 2, 0]);
+        assert_eq!(EngineEvent::DragEnded.encode(), [4, 0, 0]);
+        assert_eq!(EngineEvent::Collision(a, b).encode(), [5, a.to_bits(), 9]);
+    }
+
+    #[test]
+    fn drain_keeps_order_and_empties_the_queue() {
+        let mut events = Events::new();
+        events.push(EngineEvent::DragStarted(1));
+        events.push(EngineEvent::SelectionChanged(0));
+        events.push(EngineEvent::DragEnded);
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events.drain_encoded(), [3, 1, 0, 2, 0, 0, 4, 0, 0]);
+        assert!(events.is_empty());
+        assert!(events.drain_encoded().is_empty());
+    }
+}
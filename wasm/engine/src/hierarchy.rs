@@ -0,0 +1,403 @@
+use crate::components::Transform;
+use crate::math::{Mat4, Vec3};
+use crate::query::{With, Without};
+use crate::storage::Storage;
+use crate::world::{Entity, World};
+
+/// The entity this one is attached to. Its `Transform` is then relative
+/// to the parent's world matrix.
+#[derive(Clone, Copy)]
+pub struct Parent(pub Entity);
+
+/// Entities attached to this one, kept in sync with their `Parent`.
+#[derive(Clone, Default)]
+pub struct Children(pub Vec<Entity>);
+
+/// World matrix computed from the local `Transform` chain by
+/// [`propagate_transforms`]. Column-major, like the render buffer.
+#[derive(Clone, Copy)]
+pub struct GlobalTransform {
+    pub matrix: Mat4,
+}
+
+impl GlobalTransform {
+    pub fn translation(&self) -> Vec3 {
+        self.matrix.translation()
+    }
+}
+
+/// Live parent of `entity`, ignoring a `Parent` that points at a despawned
+/// entity.
+pub fn parent_of(world: &World, entity: Entity) -> Option<Entity> {
+    let parent = world.get::<Parent>(entity)?.0;
+    world.is_alive(parent).then_some(parent)
+}
+
+/// World matrix of `entity`, computed by walking up its parents. Unlike
+/// `GlobalTransform` this is never a frame behind.
+pub fn world_matrix(world: &World, entity: Entity) -> Mat4 {
+    let local = |entity| {
+        world
+            .get::<Transform>(entity)
+            .map_or(Mat4::IDENTITY, |t| t.matrix())
+    };
+
+    let mut matrix = local(entity);
+    let mut current = entity;
+
+    while let Some(parent) = parent_of(world, current) {
+        matrix = local(parent) * matrix;
+        current = parent;
+    }
+
+    matrix
+}
+
+/// Attaches `child` to `parent` (or detaches it when `parent` is `None`)
+/// while keeping its current world position.
+///
+/// Returns `false` if either entity is dead or the change would make
+/// `child` its own ancestor.
+pub fn set_parent(world: &mut World, child: Entity, parent: Option<Entity>) -> bool {
+    if !world.is_alive(child) {
+        return false;
+    }
+    if let Some(parent) = parent {
+        if !world.is_alive(parent) {
+            return false;
+        }
+
+        let mut ancestor = Some(parent);
+        while let Some(current) = ancestor {
+            if current == child {
+                return false;
+            }
+            ancestor = parent_of(world, current);
+        }
+    }
+
+    let child_world = world_matrix(world, child);
+
+    if let Some(old) = parent_of(world, child)
+        && let Some(mut children) = world.get_mut::<Children>(old)
+    {
+        children.0.retain(|&c| c != child);
+    }
+
+    let local = match parent {
+        Some(parent) => {
+            let parent_inv = world_matrix(world, parent)
+                .inverse()
+                .unwrap_or(Mat4::IDENTITY);
+
+            link(world, child, parent);
+
+            parent_inv * child_world
+        }
+        None => {
+            world.remove::<Parent>(child);
+            child_world
+        }
+    };
+
+    if let Some(mut transform) = world.get_mut::<Transform>(child) {
+        *transform = Transform::from_matrix(local);
+    }
+
+    true
+}
+
+/// Records `child` under `parent` on both sides, leaving its `Transform`
+/// alone. The caller makes sure `child` has no parent yet.
+pub(crate) fn link(world: &mut World, child: Entity, parent: Entity) {
+    world.insert(child, Parent(parent));
+
+    if !world.has::<Children>(parent) {
+        world.insert(parent, Children::default());
+    }
+    if let Some(mut children) = world.get_mut::<Children>(parent) {
+        children.0.push(child);
+    }
+}
+
+/// Despawns `entity` together with all of its descendants and detaches it
+/// from its parent.
+pub fn despawn_recursive(world: &mut World, entity: Entity) -> bool {
+    if !world.is_alive(entity) {
+        return false;
+    }
+
+    if let Some(parent) = parent_of(world, entity)
+        && let Some(mut children) = world.get_mut::<Children>(parent)
+    {
+        children.0.retain(|&c| c != entity);
+    }
+
+    let mut stack = vec![entity];
+
+    while let Some(current) = stack.pop() {
+        if let Some(children) = world.remove::<Children>(current) {
+            stack.extend(children.0);
+        }
+        world.despawn(current);
+    }
+
+    true
+}
+
+/// Writes a `GlobalTransform` for every entity with a `Transform`, parents
+/// before children. Entities without one are not written but still pass
+/// their parent's matrix on, and lose any `GlobalTransform` they had.
+/// Run before render extraction.
+///
+/// Matrices that come out unchanged are not written back, so
+/// `Changed<GlobalTransform>` only fires for entities that really moved.
+pub fn propagate_transforms(world: &World) {
+    let transforms = world.storage::<Transform>();
+    let children = world.storage::<Children>();
+    let mut globals = world.storage_mut::<GlobalTransform>();
+
+    // An entity that lost its `Transform` has no place in the world any
+    // more; dropping the matrix stops it being drawn and picked.
+    if transforms.removed_since(world.change_tick()) {
+        let stale: Vec<u32> = globals
+            .entities()
+            .iter()
+            .copied()
+            .filter(|&index| !transforms.contains_index(index))
+            .collect();
+        for index in stale {
+            globals.remove_entity(world.entity_at(index));
+        }
+    }
+
+    let mut stack = Vec::new();
+
+    world
+        .query::<(&Transform, Option<&Parent>)>()
+        .for_each(|entity, (transform, parent)| {
+            if parent.is_some_and(|p| world.is_alive(p.0)) {
+                return;
+            }
+
+            let matrix = transform.matrix();
+            write_global(&mut globals, entity, matrix);
+            stack.push((entity, matrix));
+        });
+
+    // A parent without a `Transform` (a bare pivot) places its children
+    // as if it were at the origin, like `world_matrix` does.
+    world
+        .query_filtered::<Option<&Parent>, (With<Children>, Without<Transform>)>()
+        .for_each(|entity, parent| {
+            if !parent.is_some_and(|p| world.is_alive(p.0)) {
+                stack.push((entity, Mat4::IDENTITY));
+            }
+        });
+
+    while let Some((entity, parent_matrix)) = stack.pop() {
+        let Some(list) = children.get(entity) else {
+            continue;
+        };
+
+        for &child in &list.0 {
+            if !world.is_alive(child) {
+                continue;
+            }
+
+            let matrix = match transforms.get(child) {
+                Some(t) => {
+                    let matrix = parent_matrix * t.matrix();
+                    write_global(&mut globals, child, matrix);
+                    matrix
+                }
+                None => parent_matrix,
+            };
+
+            stack.push((child, matrix));
+        }
+    }
+}
+
+fn write_global(globals: &mut Storage<GlobalTransform>, entity: Entity, matrix: Mat4) {
+    if globals.get(entity).is_none_or(|g| g.matrix != matrix) {
+        globals.insert(entity, GlobalTransform { matrix });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Commands;
+    use crate::math::Quat;
+
+    const EPS: f32 = 1e-5;
+
+    fn spawn_at(world: &mut World, position: Vec3) -> Entity {
+        let entity = world.spawn().unwrap();
+        world.insert(
+            entity,
+            Transform {
+                position,
+                ..Transform::IDENTITY
+            },
+        );
+        entity
+    }
+
+    fn children(world: &World, entity: Entity) -> Vec<Entity> {
+        world
+            .get::<Children>(entity)
+            .map_or(Vec::new(), |c| c.0.clone())
+    }
+
+    #[test]
+    fn set_parent_keeps_world_position() {
+        let mut world = World::new();
+        let parent = world.spawn().unwrap();
+        world.insert(
+            parent,
+            Transform {
+                position: Vec3::new(1.0, 2.0, 3.0),
+                rotation: Quat::from_axis_angle(Vec3::Y, 1.0),
+                scale: Vec3::splat(2.0),
+            },
+        );
+        let child = spawn_at(&mut world, Vec3::new(-4.0, 0.5, 6.0));
+
+        assert!(set_parent(&mut world, child, Some(parent)));
+        assert_eq!(parent_of(&world, child), Some(parent));
+        assert_eq!(children(&world, parent), [child]);
+
+        let local = world.get::<Transform>(child).unwrap().position;
+        assert!((local - Vec3::new(-4.0, 0.5, 6.0)).length() > 1.0);
+
+        let position = world_matrix(&world, child).translation();
+        assert!((position - Vec3::new(-4.0, 0.5, 6.0)).length() < EPS);
+
+        // Detaching writes the world position back as the local one.
+        assert!(set_parent(&mut world, child, None));
+        assert_eq!(parent_of(&world, child), None);
+        assert!(children(&world, parent).is_empty());
+
+        let local = world.get::<Transform>(child).unwrap().position;
+        assert!((local - Vec3::new(-4.0, 0.5, 6.0)).length() < EPS);
+    }
+
+    #[test]
+    fn reparenting_moves_the_child_between_lists() {
+        let mut world = World::new();
+        let a = spawn_at(&mut world, Vec3::X);
+        let b = spawn_at(&mut world, Vec3::Y);
+        let child = spawn_at(&mut world, Vec3::Z);
+
+        set_parent(&mut world, child, Some(a));
+        set_parent(&mut world, child, Some(b));
+
+        assert!(children(&world, a).is_empty());
+        assert_eq!(children(&world, b), [child]);
+    }
+
+    #[test]
+    fn cycles_and_dead_entities_are_rejected() {
+        let mut world = World::new();
+        let root = spawn_at(&mut world, Vec3::ZERO);
+        let middle = spawn_at(&mut world, Vec3::X);
+        let leaf = spawn_at(&mut world, Vec3::Y);
+
+        assert!(set_parent(&mut world, middle, Some(root)));
+        assert!(set_parent(&mut world, leaf, Some(middle)));
+
+        assert!(!set_parent(&mut world, root, Some(root)));
+        assert!(!set_parent(&mut world, root, Some(leaf)));
+        assert!(!set_parent(&mut world, middle, Some(leaf)));
+        assert_eq!(parent_of(&world, root), None);
+        assert_eq!(parent_of(&world, middle), Some(root));
+
+        let dead = world.spawn().unwrap();
+        world.despawn(dead);
+        assert!(!set_parent(&mut world, leaf, Some(dead)));
+        assert!(!set_parent(&mut world, dead, Some(root)));
+        assert_eq!(parent_of(&world, leaf), Some(middle));
+    }
+
+    #[test]
+    fn children_of_a_bare_pivot_are_propagated() {
+        let mut world = World::new();
+        world.register::<Children>();
+        world.register::<GlobalTransform>();
+
+        let anchor = spawn_at(&mut world, Vec3::new(0.0, 5.0, 0.0));
+        let pivot = world.spawn().unwrap();
+        let orbiting = spawn_at(&mut world, Vec3::X);
+        let loose_pivot = world.spawn().unwrap();
+        let loose = spawn_at(&mut world, Vec3::Z);
+
+        assert!(set_parent(&mut world, orbiting, Some(pivot)));
+        assert!(set_parent(&mut world, pivot, Some(anchor)));
+        assert!(set_parent(&mut world, loose, Some(loose_pivot)));
+
+        let global = |world: &World, entity| {
+            world
+                .get::<GlobalTransform>(entity)
+                .map(|g| g.translation())
+        };
+
+        propagate_transforms(&world);
+        assert_eq!(global(&world, orbiting), Some(Vec3::new(1.0, 5.0, 0.0)));
+        assert_eq!(
+            world_matrix(&world, orbiting).translation(),
+            Vec3::new(1.0, 5.0, 0.0)
+        );
+        assert_eq!(global(&world, loose), Some(Vec3::Z));
+        // Pivots themselves have nothing to draw.
+        assert_eq!(global(&world, pivot), None);
+        assert_eq!(global(&world, loose_pivot), None);
+
+        // Later moves are picked up rather than frozen.
+        world.get_mut::<Transform>(loose).unwrap().position = Vec3::Y;
+        propagate_transforms(&world);
+        assert_eq!(global(&world, loose), Some(Vec3::Y));
+    }
+
+    #[test]
+    fn despawn_recursive_takes_descendants_and_detaches() {
+        let mut world = World::new();
+        let root = spawn_at(&mut world, Vec3::ZERO);
+        let branch = spawn_at(&mut world, Vec3::X);
+        let leaf = spawn_at(&mut world, Vec3::Y);
+        let sibling = spawn_at(&mut world, Vec3::Z);
+
+        set_parent(&mut world, branch, Some(root));
+        set_parent(&mut world, leaf, Some(branch));
+        set_parent(&mut world, sibling, Some(root));
+
+        assert!(despawn_recursive(&mut world, branch));
+
+        assert!(!world.is_alive(branch));
+        assert!(!world.is_alive(leaf));
+        assert!(world.is_alive(root));
+        assert!(world.is_alive(sibling));
+        assert_eq!(children(&world, root), [sibling]);
+        assert!(!despawn_recursive(&mut world, branch));
+    }
+
+    #[test]
+    fn despawn_command_is_recursive() {
+        let mut world = World::new();
+        let root = spawn_at(&mut world, Vec3::ZERO);
+        let branch = spawn_at(&mut world, Vec3::X);
+        let leaf = spawn_at(&mut world, Vec3::Y);
+
+        set_parent(&mut world, branch, Some(root));
+        set_parent(&mut world, leaf, Some(branch));
+
+        let mut commands = Commands::new();
+        commands.despawn(branch);
+        commands.apply(&mut world);
+
+        assert!(!world.is_alive(branch));
+        assert!(!world.is_alive(leaf));
+        assert!(children(&world, root).is_empty());
+    }
+}
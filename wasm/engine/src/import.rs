@@ -0,0 +1,1146 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::components::{Hidden, Material, Mesh, Transform};
+use crate::hierarchy;
+use crate::json::{Json, JsonError};
+use crate::math::{Mat4, Quat, Vec3};
+use crate::mesh::{MeshData, Meshes};
+use crate::world::{Entity, EntityLimit, World};
+
+#[derive(Debug, PartialEq)]
+pub enum ImportError {
+    /// Not a binary glTF file, or its header and chunks disagree.
+    InvalidGlb(&'static str),
+    /// The glTF JSON chunk is not valid JSON.
+    Json(JsonError),
+    /// Valid JSON that is not a usable glTF document.
+    InvalidGltf(String),
+    /// A glTF feature the importer does not handle.
+    Unsupported(String),
+    /// An OBJ line that could not be read, numbered from 1.
+    Obj { line: usize, message: String },
+    /// The OBJ file has no faces to build a mesh from.
+    NoFaces,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::InvalidGlb(message) => write!(f, "invalid glb: {message}"),
+            ImportError::Json(err) => write!(f, "invalid glTF JSON: {err}"),
+            ImportError::InvalidGltf(message) => write!(f, "invalid glTF: {message}"),
+            ImportError::Unsupported(feature) => write!(f, "unsupported glTF: {feature}"),
+            ImportError::Obj { line, message } => write!(f, "OBJ line {line}: {message}"),
+            ImportError::NoFaces => write!(f, "OBJ file has no faces"),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<JsonError> for ImportError {
+    fn from(err: JsonError) -> Self {
+        ImportError::Json(err)
+    }
+}
+
+fn invalid(message: impl Into<String>) -> ImportError {
+    ImportError::InvalidGltf(message.into())
+}
+
+// ===== OBJ =====
+
+/// Reads Wavefront OBJ text into one mesh. Only `v`, `vt`, `vn` and `f`
+/// matter; groups, objects and materials are ignored. Polygons are
+/// split into fans, and normals are computed when any face lacks them.
+pub fn parse_obj(bytes: &[u8]) -> Result<MeshData, ImportError> {
+    let text = std::str::from_utf8(bytes).map_err(|err| ImportError::Obj {
+        line: bytes[..err.valid_up_to()]
+            .iter()
+            .filter(|&&b| b == b'\n')
+            .count()
+            + 1,
+        message: "not UTF-8".into(),
+    })?;
+
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+
+    // One vertex per distinct position/uv/normal triple.
+    let mut corners: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+    let mut mesh = MeshData::default();
+    let mut missing_normals = false;
+
+    for (number, line) in text.lines().enumerate() {
+        let error = |message: String| ImportError::Obj {
+            line: number + 1,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let floats: Vec<&str> = words.collect();
+        let float = |i: usize| -> Result<f32, ImportError> {
+            let word = floats
+                .get(i)
+                .ok_or_else(|| error(format!("`{keyword}` needs more values")))?;
+            word.parse()
+                .map_err(|_| error(format!("`{word}` is not a number")))
+        };
+
+        match keyword {
+            "v" => positions.push(Vec3::new(float(0)?, float(1)?, float(2)?)),
+            // OBJ puts v = 0 at the bottom; the renderer at the top.
+            "vt" => uvs.push([float(0)?, 1.0 - float(1).unwrap_or(0.0)]),
+            "vn" => normals.push(Vec3::new(float(0)?, float(1)?, float(2)?).normalize()),
+            "f" => {
+                if floats.len() < 3 {
+                    return Err(error("face needs at least 3 corners".into()));
+                }
+
+                let mut face = Vec::with_capacity(floats.len());
+
+                for corner in &floats {
+                    let mut refs = corner.split('/');
+                    let mut index = |len: usize, required: bool| -> Result<Option<usize>, _> {
+                        match refs.next() {
+                            Some("") | None if !required => Ok(None),
+                            Some(word) => obj_index(word, len).map(Some).map_err(&error),
+                            None => Err(error(format!("bad face corner `{corner}`"))),
+                        }
+                    };
+
+                    let key = (
+                        index(positions.len(), true)?.unwrap_or_default(),
+                        index(uvs.len(), false)?,
+                        index(normals.len(), false)?,
+                    );
+
+                    missing_normals |= key.2.is_none();
+
+                    let vertex = *corners.entry(key).or_insert_with(|| {
+                        let (p, uv, n) = key;
+                        mesh.push_vertex(
+                            positions[p],
+                            n.map_or(Vec3::ZERO, |n| normals[n]),
+                            uv.map_or([0.0; 2], |uv| uvs[uv]),
+                        )
+                    });
+                    face.push(vertex);
+                }
+
+                for i in 1..face.len() - 1 {
+                    mesh.indices
+                        .extend_from_slice(&[face[0], face[i], face[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if mesh.indices.is_empty() {
+        return Err(ImportError::NoFaces);
+    }
+    if missing_normals {
+        mesh.compute_normals();
+    }
+
+    Ok(mesh)
+}
+
+/// A 1-based OBJ index, negative counting back from the end, as a
+/// 0-based index into a list of `len` items.
+fn obj_index(word: &str, len: usize) -> Result<usize, String> {
+    let index: i64 = word
+        .parse()
+        .map_err(|_| format!("`{word}` is not an index"))?;
+
+    let resolved = match index {
+        1.. => index - 1,
+        ..0 => len as i64 + index,
+        0 => return Err("index 0 is not valid".into()),
+    };
+
+    if (0..len as i64).contains(&resolved) {
+        Ok(resolved as usize)
+    } else {
+        Err(format!("index {index} is out of range"))
+    }
+}
+
+// ===== glTF =====
+
+const GLB_MAGIC: u32 = 0x4654_6c67; // "glTF"
+const CHUNK_JSON: u32 = 0x4e4f_534a;
+const CHUNK_BIN: u32 = 0x004e_4942;
+
+/// One glTF primitive: a mesh plus its material, if it has one.
+pub struct Primitive {
+    pub mesh: MeshData,
+    pub material: Option<Material>,
+}
+
+pub struct Node {
+    pub transform: Transform,
+    /// Index into `GltfScene::meshes`.
+    pub mesh: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+/// The default scene of a glTF file, checked to be a forest: every node
+/// is reached at most once from `roots`.
+pub struct GltfScene {
+    pub meshes: Vec<Vec<Primitive>>,
+    pub nodes: Vec<Node>,
+    pub roots: Vec<usize>,
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    let word = bytes.get(at..at + 4)?;
+    Some(u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+}
+
+/// Reads a binary glTF 2.0 file. Only the embedded binary chunk is
+/// supported as a buffer; images and textures are ignored.
+pub fn parse_glb(bytes: &[u8]) -> Result<GltfScene, ImportError> {
+    if read_u32(bytes, 0) != Some(GLB_MAGIC) {
+        return Err(ImportError::InvalidGlb("not a glb file"));
+    }
+    if read_u32(bytes, 4) != Some(2) {
+        return Err(ImportError::InvalidGlb("only glb version 2 is supported"));
+    }
+
+    let length = read_u32(bytes, 8).ok_or(ImportError::InvalidGlb("truncated header"))? as usize;
+    let bytes = bytes.get(..length).ok_or(ImportError::InvalidGlb(
+        "file is shorter than its header says",
+    ))?;
+
+    let mut chunks = Vec::new();
+    let mut at = 12;
+
+    while at < bytes.len() {
+        let (Some(len), Some(kind)) = (read_u32(bytes, at), read_u32(bytes, at + 4)) else {
+            return Err(ImportError::InvalidGlb("truncated chunk header"));
+        };
+        let data = (at + 8)
+            .checked_add(len as usize)
+            .and_then(|end| bytes.get(at + 8..end))
+            .ok_or(ImportError::InvalidGlb(
+                "chunk runs past the end of the file",
+            ))?;
+
+        chunks.push((kind, data));
+        at += 8 + len as usize;
+    }
+
+    let json = match chunks.first() {
+        Some(&(CHUNK_JSON, data)) => std::str::from_utf8(data)
+            .map_err(|_| ImportError::InvalidGlb("JSON chunk is not UTF-8"))?,
+        _ => return Err(ImportError::InvalidGlb("first chunk is not JSON")),
+    };
+    let bin = chunks
+        .get(1)
+        .filter(|&&(kind, _)| kind == CHUNK_BIN)
+        .map(|&(_, data)| data);
+
+    let doc = Json::parse(json)?;
+    Document { json: &doc, bin }.scene()
+}
+
+struct Document<'a> {
+    json: &'a Json,
+    bin: Option<&'a [u8]>,
+}
+
+/// Layout of an accessor's elements within its buffer view.
+struct Accessor<'a> {
+    data: &'a [u8],
+    stride: usize,
+    component_type: u32,
+    count: usize,
+    normalized: bool,
+}
+
+impl Accessor<'_> {
+    /// Component `c` of element `i`, normalized integers mapped to 0..1
+    /// (or -1..1).
+    fn get(&self, i: usize, c: usize) -> f32 {
+        let at = i * self.stride + c * component_size(self.component_type);
+        let b = &self.data[at..];
+
+        match (self.component_type, self.normalized) {
+            (5126, _) => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            (5125, _) => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32,
+            (5123, false) => u16::from_le_bytes([b[0], b[1]]) as f32,
+            (5123, true) => u16::from_le_bytes([b[0], b[1]]) as f32 / 65535.0,
+            (5122, false) => i16::from_le_bytes([b[0], b[1]]) as f32,
+            (5122, true) => (i16::from_le_bytes([b[0], b[1]]) as f32 / 32767.0).max(-1.0),
+            (5121, false) => b[0] as f32,
+            (5121, true) => b[0] as f32 / 255.0,
+            (5120, false) => b[0] as i8 as f32,
+            (_, _) => (b[0] as i8 as f32 / 127.0).max(-1.0),
+        }
+    }
+    /// Integer element `i`, for index accessors.
+    fn index(&self, i: usize) -> u32 {
+        let b = &self.data[i * self.stride..];
+
+        match self.component_type {
+            5125 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            5123 => u16::from_le_bytes([b[0], b[1]]) as u32,
+            _ => b[0] as u32,
+        }
+    }
+}
+
+fn component_size(component_type: u32) -> usize {
+    match component_type {
+        5120 | 5121 => 1,
+        5122 | 5123 => 2,
+        _ => 4,
+    }
+}
+
+impl<'a> Document<'a> {
+    /// Item `index` of the top-level array `key`.
+    fn item(&self, key: &str, index: usize) -> Result<&'a Json, ImportError> {
+        self.json
+            .get(key)
+            .and_then(Json::as_array)
+            .and_then(|items| items.get(index))
+            .ok_or_else(|| invalid(format!("{key}[{index}] does not exist")))
+    }
+    fn len(&self, key: &str) -> usize {
+        self.json
+            .get(key)
+            .and_then(Json::as_array)
+            .map_or(0, <[Json]>::len)
+    }
+
+    fn scene(&self) -> Result<GltfScene, ImportError> {
+        let version = self
+            .json
+            .get("asset")
+            .and_then(|asset| asset.get("version"))
+            .and_then(Json::as_str)
+            .ok_or_else(|| invalid("asset.version is missing"))?;
+
+        if !version.starts_with("2.") {
+            return Err(ImportError::Unsupported(format!("version {version}")));
+        }
+
+        let meshes = (0..self.len("meshes"))
+            .map(|i| self.mesh(i))
+            .collect::<Result<Vec<_>, _>>()?;
+        let nodes = (0..self.len("nodes"))
+            .map(|i| self.node(i, meshes.len()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let roots = match self.json.get("scene").map(|s| s.as_usize()) {
+            Some(None) => return Err(invalid("scene is not an index")),
+            Some(Some(scene)) => self.scene_roots(scene)?,
+            None if self.len("scenes") > 0 => self.scene_roots(0)?,
+            // No scene: every node nobody lists as a child.
+            None => {
+                let mut is_child = vec![false; nodes.len()];
+                for &child in nodes.iter().flat_map(|n| &n.children) {
+                    if let Some(flag) = is_child.get_mut(child) {
+                        *flag = true;
+                    }
+                }
+                (0..nodes.len()).filter(|&i| !is_child[i]).collect()
+            }
+        };
+
+        let mut seen = vec![false; nodes.len()];
+        let mut stack = roots.clone();
+
+        while let Some(node) = stack.pop() {
+            let Some(seen) = seen.get_mut(node) else {
+                return Err(invalid(format!("nodes[{node}] does not exist")));
+            };
+            if std::mem::replace(seen, true) {
+                return Err(invalid(format!("nodes[{node}] has more than one parent")));
+            }
+            stack.extend(&nodes[node].children);
+        }
+
+        Ok(GltfScene {
+            meshes,
+            nodes,
+            roots,
+        })
+    }
+    fn scene_roots(&self, scene: usize) -> Result<Vec<usize>, ImportError> {
+        let scene = self.item("scenes", scene)?;
+
+        scene
+            .get("nodes")
+            .map_or(Ok(Vec::new()), |nodes| indices(nodes, "scene nodes"))
+    }
+
+    fn node(&self, index: usize, mesh_count: usize) -> Result<Node, ImportError> {
+        let node = self.item("nodes", index)?;
+        let what = format!("nodes[{index}]");
+
+        let mesh = match node.get("mesh") {
+            Some(mesh) => match mesh.as_usize() {
+                Some(mesh) if mesh < mesh_count => Some(mesh),
+                _ => return Err(invalid(format!("{what}.mesh is not a mesh index"))),
+            },
+            None => None,
+        };
+
+        let children = match node.get("children") {
+            Some(children) => indices(children, &format!("{what}.children"))?,
+            None => Vec::new(),
+        };
+
+        let transform = if let Some(matrix) = node.get("matrix") {
+            let m: [f32; 16] = floats(matrix, &format!("{what}.matrix"))?;
+            Transform::from_matrix(Mat4(m))
+        } else {
+            let vector = |key: &str, default: Vec3| match node.get(key) {
+                Some(v) => floats::<3>(v, &format!("{what}.{key}")).map(Vec3::from),
+                None => Ok(default),
+            };
+
+            let rotation = match node.get("rotation") {
+                Some(r) => {
+                    let [x, y, z, w] = floats(r, &format!("{what}.rotation"))?;
+                    Quat::from_xyzw(x, y, z, w).normalize()
+                }
+                None => Quat::IDENTITY,
+            };
+
+            Transform {
+                position: vector("translation", Vec3::ZERO)?,
+                rotation,
+                scale: vector("scale", Vec3::ONE)?,
+            }
+        };
+
+        Ok(Node {
+            transform,
+            mesh,
+            children,
+        })
+    }
+
+    fn mesh(&self, index: usize) -> Result<Vec<Primitive>, ImportError> {
+        let primitives = self
+            .item("meshes", index)?
+            .get("primitives")
+            .and_then(Json::as_array)
+            .ok_or_else(|| invalid(format!("meshes[{index}] has no primitives")))?;
+
+        primitives
+            .iter()
+            .map(|primitive| self.primitive(primitive, index))
+            .collect()
+    }
+    fn primitive(&self, primitive: &Json, mesh: usize) -> Result<Primitive, ImportError> {
+        let mode = primitive.get("mode").map_or(Some(4), Json::as_usize);
+        if mode != Some(4) {
+            return Err(ImportError::Unsupported(format!(
+                "meshes[{mesh}] is not a triangle list"
+            )));
+        }
+
+        let attribute = |name: &str| {
+            primitive
+                .get("attributes")
+                .and_then(|a| a.get(name))
+                .map(|a| {
+                    a.as_usize()
+                        .ok_or_else(|| invalid(format!("meshes[{mesh}] {name} is not an index")))
+                })
+                .transpose()
+        };
+
+        let positions = attribute("POSITION")?
+            .ok_or_else(|| invalid(format!("meshes[{mesh}] has no POSITION")))?;
+        let positions = self.accessor(positions, 3, true)?;
+        let normals = attribute("NORMAL")?
+            .map(|a| self.accessor(a, 3, true))
+            .transpose()?;
+        let uvs = attribute("TEXCOORD_0")?
+            .map(|a| self.accessor(a, 2, false))
+            .transpose()?;
+
+        for (name, accessor) in [("NORMAL", &normals), ("TEXCOORD_0", &uvs)] {
+            if accessor
+                .as_ref()
+                .is_some_and(|a| a.count != positions.count)
+            {
+                return Err(invalid(format!(
+                    "meshes[{mesh}] {name} count differs from POSITION"
+                )));
+            }
+        }
+
+        let mut data = MeshData::default();
+
+        for i in 0..positions.count {
+            let vec3 = |a: &Accessor| Vec3::new(a.get(i, 0), a.get(i, 1), a.get(i, 2));
+
+            data.push_vertex(
+                vec3(&positions),
+                normals.as_ref().map_or(Vec3::ZERO, |n| vec3(n).normalize()),
+                uvs.as_ref()
+                    .map_or([0.0; 2], |uv| [uv.get(i, 0), uv.get(i, 1)]),
+            );
+        }
+
+        data.indices = match primitive.get("indices") {
+            Some(indices) => {
+                let index = indices
+                    .as_usize()
+                    .ok_or_else(|| invalid(format!("meshes[{mesh}] indices is not an index")))?;
+                let accessor = self.accessor(index, 1, false)?;
+
+                if !matches!(accessor.component_type, 5121 | 5123 | 5125) {
+                    return Err(invalid(format!("accessors[{index}] is not unsigned")));
+                }
+
+                (0..accessor.count).map(|i| accessor.index(i)).collect()
+            }
+            None => (0..positions.count as u32).collect(),
+        };
+
+        if data.indices.len() % 3 != 0 {
+            return Err(invalid(format!("meshes[{mesh}] has a partial triangle")));
+        }
+        if data.indices.iter().any(|&i| i as usize >= positions.count) {
+            return Err(invalid(format!("meshes[{mesh}] has an index out of range")));
+        }
+        if normals.is_none() {
+            data.compute_normals();
+        }
+
+        Ok(Primitive {
+            mesh: data,
+            material: self.material(primitive)?,
+        })
+    }
+    /// The primitive's metallic-roughness factors, emission (with
+    /// `KHR_materials_emissive_strength`) and `KHR_materials_unlit`.
+    /// Textures are ignored.
+    fn material(&self, primitive: &Json) -> Result<Option<Material>, ImportError> {
+        let Some(material) = primitive.get("material") else {
+            return Ok(None);
+        };
+        let index = material
+            .as_usize()
+            .ok_or_else(|| invalid("material is not an index"))?;
+
+        let json = self.item("materials", index)?;
+        let what = format!("materials[{index}]");
+        let pbr = json.get("pbrMetallicRoughness");
+        let extension = |name: &str| json.get("extensions").and_then(|e| e.get(name));
+
+        let factor = |value: Option<&Json>, key: &str, default: f32| match value {
+            Some(v) => v
+                .as_f32()
+                .ok_or_else(|| invalid(format!("{what}.{key} is not a number"))),
+            None => Ok(default),
+        };
+
+        let mut base_color = match pbr.and_then(|p| p.get("baseColorFactor")) {
+            Some(c) => floats(c, &format!("{what}.baseColorFactor"))?,
+            None => [1.0; 4],
+        };
+        // Alpha only counts for blended materials.
+        if json.get("alphaMode").and_then(Json::as_str) != Some("BLEND") {
+            base_color[3] = 1.0;
+        }
+
+        Ok(Some(Material {
+            base_color,
+            metallic: factor(
+                pbr.and_then(|p| p.get("metallicFactor")),
+                "metallicFactor",
+                1.0,
+            )?,
+            roughness: factor(
+                pbr.and_then(|p| p.get("roughnessFactor")),
+                "roughnessFactor",
+                1.0,
+            )?,
+            emissive: match json.get("emissiveFactor") {
+                Some(e) => floats(e, &format!("{what}.emissiveFactor"))?,
+                None => [0.0; 3],
+            },
+            emissive_strength: factor(
+                extension("KHR_materials_emissive_strength")
+                    .and_then(|e| e.get("emissiveStrength")),
+                "emissiveStrength",
+                1.0,
+            )?,
+            unlit: extension("KHR_materials_unlit").is_some(),
+        }))
+    }
+
+    /// Resolves accessor `index`, checking it has `width` components
+    /// (of type float if `float_only`) and lies inside the binary chunk.
+    fn accessor(
+        &self,
+        index: usize,
+        width: usize,
+        float_only: bool,
+    ) -> Result<Accessor<'a>, ImportError> {
+        let accessor = self.item("accessors", index)?;
+        let what = format!("accessors[{index}]");
+
+        if accessor.get("sparse").is_some() {
+            return Err(ImportError::Unsupported(format!("{what} is sparse")));
+        }
+
+        let field = |key: &str| {
+            accessor
+                .get(key)
+                .and_then(Json::as_usize)
+                .ok_or_else(|| invalid(format!("{what}.{key} is missing")))
+        };
+
+        let count = field("count")?;
+        let component_type = field("componentType")? as u32;
+        let components = match accessor.get("type").and_then(Json::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            _ => return Err(invalid(format!("{what}.type is not a vector type"))),
+        };
+
+        if components != width {
+            return Err(invalid(format!("{what} has {components} components")));
+        }
+        if !(5120..=5126).contains(&component_type) || component_type == 5124 {
+            return Err(invalid(format!("{what}.componentType is not valid")));
+        }
+
+        let normalized = accessor.get("normalized") == Some(&Json::Bool(true));
+
+        if float_only && component_type != 5126 {
+            return Err(ImportError::Unsupported(format!("{what} is not float")));
+        }
+
+        let Some(view) = accessor.get("bufferView") else {
+            return Err(ImportError::Unsupported(format!(
+                "{what} has no bufferView"
+            )));
+        };
+        let view_index = view
+            .as_usize()
+            .ok_or_else(|| invalid(format!("{what}.bufferView is not an index")))?;
+        let data = self.buffer_view(view_index)?;
+
+        let element = components * component_size(component_type);
+        let stride = match self
+            .item("bufferViews", view_index)?
+            .get("byteStride")
+            .map(Json::as_usize)
+        {
+            Some(Some(stride)) if stride >= element => stride,
+            Some(_) => return Err(invalid(format!("bufferViews[{view_index}].byteStride"))),
+            None => element,
+        };
+
+        // Bytes spanned by `count` elements. `count` and the stride come
+        // from the file, so this must not be allowed to wrap.
+        let span = match count.checked_sub(1) {
+            Some(last) => last
+                .checked_mul(stride)
+                .and_then(|end| end.checked_add(element)),
+            None => Some(0),
+        };
+        let offset = accessor.get("byteOffset").map_or(Some(0), Json::as_usize);
+        let data = offset
+            .zip(span)
+            .and_then(|(offset, span)| data.get(offset..offset.checked_add(span)?))
+            .ok_or_else(|| invalid(format!("{what} runs past its bufferView")))?;
+
+        Ok(Accessor {
+            data,
+            stride,
+            component_type,
+            count,
+            normalized,
+        })
+    }
+    fn buffer_view(&self, index: usize) -> Result<&'a [u8], ImportError> {
+        let view = self.item("bufferViews", index)?;
+        let what = format!("bufferViews[{index}]");
+        let field = |key: &str| view.get(key).map(Json::as_usize);
+
+        let buffer = field("buffer")
+            .flatten()
+            .ok_or_else(|| invalid(format!("{what}.buffer")))?;
+        if buffer != 0 || self.item("buffers", 0)?.get("uri").is_some() {
+            return Err(ImportError::Unsupported(
+                "buffers outside the glb binary chunk".into(),
+            ));
+        }
+
+        let bin = self.bin.ok_or_else(|| invalid("glb has no binary chunk"))?;
+        let offset = field("byteOffset").unwrap_or(Some(0));
+        let length = field("byteLength").flatten();
+
+        offset
+            .zip(length)
+            .and_then(|(offset, length)| bin.get(offset..offset.checked_add(length)?))
+            .ok_or_else(|| invalid(format!("{what} runs past the binary chunk")))
+    }
+}
+
+fn indices(json: &Json, what: &str) -> Result<Vec<usize>, ImportError> {
+    json.as_array()
+        .and_then(|items| items.iter().map(Json::as_usize).collect())
+        .ok_or_else(|| invalid(format!("{what} is not a list of indices")))
+}
+
+fn floats<const N: usize>(json: &Json, what: &str) -> Result<[f32; N], ImportError> {
+    json.as_array()
+        .filter(|items| items.len() == N)
+        .and_then(|items| items.iter().map(Json::as_f32).collect::<Option<Vec<_>>>())
+        .and_then(|v| v.try_into().ok())
+        .ok_or_else(|| invalid(format!("{what} is not {N} numbers")))
+}
+
+/// Spawns the scene's nodes under a new `Hidden` root, which is
+/// returned, and adds its meshes to `Meshes`. Nodes without a mesh are
+/// `Hidden`; a mesh with several primitives gets one child entity per
+/// primitive. If the world runs out of entities, whatever was spawned is
+/// removed and no meshes are added.
+pub fn spawn_gltf(world: &mut World, scene: GltfScene) -> Result<Entity, EntityLimit> {
+    let root = world.spawn()?;
+    world.insert(root, Transform::IDENTITY);
+    world.insert(root, Hidden);
+
+    let drawn = match spawn_nodes(world, root, &scene) {
+        Ok(drawn) => drawn,
+        Err(err) => {
+            hierarchy::despawn_recursive(world, root);
+            return Err(err);
+        }
+    };
+
+    let meshes: Vec<Vec<(Mesh, Option<Material>)>> = {
+        let mut assets = world.resource_mut::<Meshes>();
+
+        scene
+            .meshes
+            .into_iter()
+            .map(|primitives| {
+                primitives
+                    .into_iter()
+                    .map(|p| (assets.add(p.mesh), p.material))
+                    .collect()
+            })
+            .collect()
+    };
+
+    for (entity, mesh, primitive) in drawn {
+        attach_mesh(world, entity, meshes[mesh][primitive]);
+    }
+
+    Ok(root)
+}
+
+/// Spawns the node entities and returns each one that draws a primitive,
+/// with the mesh and primitive index it draws.
+fn spawn_nodes(
+    world: &mut World,
+    root: Entity,
+    scene: &GltfScene,
+) -> Result<Vec<(Entity, usize, usize)>, EntityLimit> {
+    let mut drawn = Vec::new();
+    let mut stack: Vec<(usize, Entity)> = scene.roots.iter().rev().map(|&n| (n, root)).collect();
+
+    while let Some((index, parent)) = stack.pop() {
+        let node = &scene.nodes[index];
+        let entity = spawn_child(world, parent, node.transform)?;
+        let (mesh, count) = node
+            .mesh
+            .map_or((0, 0), |mesh| (mesh, scene.meshes[mesh].len()));
+
+        match count {
+            0 => {
+                world.insert(entity, Hidden);
+            }
+            1 => drawn.push((entity, mesh, 0)),
+            _ => {
+                world.insert(entity, Hidden);
+                for primitive in 0..count {
+                    let child = spawn_child(world, entity, Transform::IDENTITY)?;
+                    drawn.push((child, mesh, primitive));
+                }
+            }
+        }
+
+        // Reversed, so children are spawned in document order.
+        stack.extend(node.children.iter().rev().map(|&child| (child, entity)));
+    }
+
+    Ok(drawn)
+}
+
+fn spawn_child(
+    world: &mut World,
+    parent: Entity,
+    transform: Transform,
+) -> Result<Entity, EntityLimit> {
+    let entity = world.spawn()?;
+
+    // `transform` is already local to `parent`, so there is no world
+    // position to keep and the chain above need not be walked.
+    hierarchy::link(world, entity, parent);
+    world.insert(entity, transform);
+
+    Ok(entity)
+}
+
+fn attach_mesh(world: &mut World, entity: Entity, (mesh, material): (Mesh, Option<Material>)) {
+    let bounds = world.resource::<Meshes>().bounds(mesh);
+
+    world.insert(entity, mesh);
+    if let Some(bounds) = bounds {
+        world.insert(entity, bounds);
+    }
+    if let Some(material) = material {
+        world.insert(entity, material);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A glb file holding `json` and, unless empty, a binary chunk.
+    fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let pad = |bytes: &[u8], fill: u8| {
+            let mut bytes = bytes.to_vec();
+            bytes.resize(bytes.len().next_multiple_of(4), fill);
+            bytes
+        };
+        let json = pad(json.as_bytes(), b' ');
+        let bin = pad(bin, 0);
+
+        let mut chunks = Vec::new();
+        for (kind, data) in [(CHUNK_JSON, &json), (CHUNK_BIN, &bin)] {
+            if data.is_empty() {
+                continue;
+            }
+            chunks.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            chunks.extend_from_slice(&kind.to_le_bytes());
+            chunks.extend_from_slice(data);
+        }
+
+        let mut file = Vec::new();
+        file.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+        file.extend_from_slice(&2u32.to_le_bytes());
+        file.extend_from_slice(&(12 + chunks.len() as u32).to_le_bytes());
+        file.extend_from_slice(&chunks);
+        file
+    }
+
+    /// One triangle: three float positions, then three `u16` indices.
+    fn triangle_bin() -> Vec<u8> {
+        let positions = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let mut bin: Vec<u8> = positions.iter().flat_map(|f| f.to_le_bytes()).collect();
+        bin.extend([0u16, 1, 2].iter().flat_map(|i| i.to_le_bytes()));
+        bin
+    }
+
+    /// A document drawing `triangle_bin` through the given accessors
+    /// and buffer views.
+    fn triangle_gltf(accessors: &str, buffer_views: &str) -> String {
+        format!(
+            r#"{{
+                "asset": {{"version": "2.0"}},
+                "buffers": [{{"byteLength": 44}}],
+                "bufferViews": {buffer_views},
+                "accessors": {accessors},
+                "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}, "indices": 1}}]}}],
+                "nodes": [{{"mesh": 0, "translation": [1, 2, 3]}}],
+                "scenes": [{{"nodes": [0]}}]
+            }}"#
+        )
+    }
+
+    const VIEWS: &str = r#"[
+        {"buffer": 0, "byteOffset": 0, "byteLength": 36},
+        {"buffer": 0, "byteOffset": 36, "byteLength": 6}
+    ]"#;
+    const ACCESSORS: &str = r#"[
+        {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"},
+        {"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}
+    ]"#;
+
+    fn is_invalid_gltf<T>(result: Result<T, ImportError>) -> bool {
+        matches!(result, Err(ImportError::InvalidGltf(_)))
+    }
+
+    #[test]
+    fn accessor_span_overflow_is_an_error() {
+        // Wraps `(count - 1) * stride` on 32-bit targets.
+        let views = r#"[
+            {"buffer": 0, "byteOffset": 0, "byteLength": 36, "byteStride": 16},
+            {"buffer": 0, "byteOffset": 36, "byteLength": 6}
+        ]"#;
+        let accessors = r#"[
+            {"bufferView": 0, "componentType": 5126, "count": 1073741825, "type": "VEC3"},
+            {"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}
+        ]"#;
+        let file = glb(&triangle_gltf(accessors, views), &triangle_bin());
+
+        assert!(is_invalid_gltf(parse_glb(&file)));
+
+        let views = r#"[
+            {"buffer": 0, "byteOffset": 4294967295, "byteLength": 4294967295},
+            {"buffer": 0, "byteOffset": 36, "byteLength": 6}
+        ]"#;
+        let file = glb(&triangle_gltf(ACCESSORS, views), &triangle_bin());
+
+        assert!(is_invalid_gltf(parse_glb(&file)));
+
+        let accessors = r#"[
+            {"bufferView": 0, "byteOffset": 4294967295, "componentType": 5126, "count": 3, "type": "VEC3"},
+            {"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}
+        ]"#;
+        let file = glb(&triangle_gltf(accessors, VIEWS), &triangle_bin());
+
+        assert!(is_invalid_gltf(parse_glb(&file)));
+    }
+
+    #[test]
+    fn obj_quads_and_negative_indices() {
+        let obj = b"\
+# unit quad, then a triangle using relative indices
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 1
+vn 0 0 1
+f 1/1/1 2/1/1 3/2/1 4/2/1
+v 2 0 0
+f -1//1 -4//1 -2//1
+";
+        let mesh = parse_obj(obj).unwrap();
+
+        // The quad splits into a fan of two triangles; the last face
+        // reuses corners 2 and 4 with different uvs.
+        assert_eq!(mesh.indices.len(), 9);
+        assert_eq!(mesh.indices[..6], [0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.vertex_count(), 7);
+        assert_eq!(mesh.position(4), Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(mesh.position(5), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(mesh.position(6), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(mesh.normal(0), Vec3::Z);
+        // V is flipped to put 0 at the top.
+        assert_eq!(mesh.vertices[6..8], [0.0, 1.0]);
+    }
+
+    #[test]
+    fn obj_without_normals_gets_computed_ones() {
+        let mesh = parse_obj(b"v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+
+        for i in 0..3 {
+            assert!((mesh.normal(i) - Vec3::Z).length() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn obj_errors_name_the_line() {
+        let err = |text: &[u8]| parse_obj(text).err().unwrap();
+
+        assert_eq!(
+            err(b"v 0 0 0\nv 1 0 0\nf 1 2 3\n"),
+            ImportError::Obj {
+                line: 3,
+                message: "index 3 is out of range".into()
+            }
+        );
+        assert!(matches!(
+            err(b"v 0 0 0\nf 0 1 1\n"),
+            ImportError::Obj { line: 2, .. }
+        ));
+        assert!(matches!(
+            err(b"v 0 0 0\nf -2 1 1\n"),
+            ImportError::Obj { line: 2, .. }
+        ));
+        assert!(matches!(
+            err(b"v 0 x 0\n"),
+            ImportError::Obj { line: 1, .. }
+        ));
+        assert!(matches!(
+            err(b"v 0 0 0\nf 1 1\n"),
+            ImportError::Obj { line: 2, .. }
+        ));
+        assert_eq!(err(b"v 0 0 0\n"), ImportError::NoFaces);
+    }
+
+    #[test]
+    fn glb_triangle() {
+        let file = glb(&triangle_gltf(ACCESSORS, VIEWS), &triangle_bin());
+        let scene = parse_glb(&file).unwrap();
+
+        assert_eq!(scene.roots, [0]);
+        assert_eq!(scene.nodes[0].mesh, Some(0));
+        assert_eq!(scene.nodes[0].transform.position, Vec3::new(1.0, 2.0, 3.0));
+
+        let primitive = &scene.meshes[0][0];
+        assert_eq!(primitive.mesh.indices, [0, 1, 2]);
+        assert_eq!(primitive.mesh.position(1), Vec3::X);
+        assert!((primitive.mesh.normal(0) - Vec3::Z).length() < 1e-6);
+        assert!(primitive.material.is_none());
+    }
+
+    fn triangle_scene(nodes: Vec<Node>) -> GltfScene {
+        let file = glb(&triangle_gltf(ACCESSORS, VIEWS), &triangle_bin());
+        let mut scene = parse_glb(&file).unwrap();
+
+        scene.roots = vec![0];
+        scene.nodes = nodes;
+        scene
+    }
+
+    fn chain(depth: usize) -> Vec<Node> {
+        (0..depth)
+            .map(|i| Node {
+                transform: Transform {
+                    position: Vec3::X,
+                    ..Transform::IDENTITY
+                },
+                mesh: Some(0),
+                children: if i + 1 < depth {
+                    vec![i + 1]
+                } else {
+                    Vec::new()
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn deep_node_chains_spawn_without_recursion() {
+        let mut world = World::new();
+        world.insert_resource(Meshes::default());
+        let meshes = world.resource::<Meshes>().len();
+
+        let root = spawn_gltf(&mut world, triangle_scene(chain(20_000))).unwrap();
+
+        assert_eq!(world.resource::<Meshes>().len(), meshes + 1);
+
+        let mut leaf = root;
+        while let Some(children) = world.get::<hierarchy::Children>(leaf).map(|c| c.0.clone()) {
+            assert_eq!(children.len(), 1);
+            leaf = children[0];
+        }
+        assert_eq!(
+            world.get::<Mesh>(leaf).map(|m| *m),
+            Some(Mesh(meshes as u32))
+        );
+        assert_eq!(
+            hierarchy::world_matrix(&world, leaf).translation(),
+            Vec3::new(20_000.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn failed_spawn_adds_no_meshes() {
+        let mut world = World::new();
+        world.insert_resource(Meshes::default());
+        let meshes = world.resource::<Meshes>().len();
+
+        while world.spawn().is_ok() {}
+        // Room for the root and one node, but not the third.
+        world.despawn(world.entity(0).unwrap());
+        world.despawn(world.entity(1).unwrap());
+
+        let result = spawn_gltf(&mut world, triangle_scene(chain(2)));
+
+        assert_eq!(result.err(), Some(EntityLimit));
+        assert_eq!(world.resource::<Meshes>().len(), meshes);
+        assert!(world.entity(0).is_none());
+        assert!(world.entity(1).is_none());
+    }
+
+    #[test]
+    fn glb_container_errors() {
+        let file = glb(&triangle_gltf(ACCESSORS, VIEWS), &triangle_bin());
+
+        let mut bad_magic = file.clone();
+        bad_magic[0] = b'x';
+        assert_eq!(
+            parse_glb(&bad_magic).err(),
+            Some(ImportError::InvalidGlb("not a glb file"))
+        );
+
+        // Header length cut to end inside the binary chunk.
+        let mut truncated = file.clone();
+        let cut = file.len() as u32 - 8;
+        truncated[8..12].copy_from_slice(&cut.to_le_bytes());
+        assert_eq!(
+            parse_glb(&truncated[..cut as usize]).err(),
+            Some(ImportError::InvalidGlb(
+                "chunk runs past the end of the file"
+            ))
+        );
+
+        assert_eq!(
+            parse_glb(&file[..file.len() - 4]).err(),
+            Some(ImportError::InvalidGlb(
+                "file is shorter than its header says"
+            ))
+        );
+        assert!(matches!(
+            parse_glb(&glb("{", &[])),
+            Err(ImportError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn glb_out_of_range_references() {
+        let run = |accessors: &str, views: &str| {
+            parse_glb(&glb(&triangle_gltf(accessors, views), &triangle_bin()))
+        };
+
+        // Accessor longer than its view.
+        let accessors = r#"[
+            {"bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3"},
+            {"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}
+        ]"#;
+        assert!(is_invalid_gltf(run(accessors, VIEWS)));
+
+        // View past the end of the binary chunk.
+        let views = r#"[
+            {"buffer": 0, "byteOffset": 0, "byteLength": 36},
+            {"buffer": 0, "byteOffset": 40, "byteLength": 6}
+        ]"#;
+        assert!(is_invalid_gltf(run(ACCESSORS, views)));
+
+        // Missing view, and an index past the last vertex.
+        let accessors = r#"[
+            {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"},
+            {"bufferView": 5, "componentType": 5123, "count": 3, "type": "SCALAR"}
+        ]"#;
+        assert!(is_invalid_gltf(run(accessors, VIEWS)));
+
+        let mut bin = triangle_bin();
+        bin[40..42].copy_from_slice(&3u16.to_le_bytes());
+        assert!(is_invalid_gltf(parse_glb(&glb(
+            &triangle_gltf(ACCESSORS, VIEWS),
+            &bin
+        ))));
+    }
+}
@@ -0,0 +1,428 @@
+use std::fmt;
+
+/// A parsed JSON value. Objects keep their keys in document order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct JsonError {
+    /// Byte offset into the input.
+    pub offset: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, JsonError> {
+        let mut parser = Parser {
+            bytes: text.as_bytes(),
+            pos: 0,
+            depth: 0,
+        };
+
+        let value = parser.value()?;
+        parser.skip_whitespace();
+
+        if parser.pos < parser.bytes.len() {
+            return Err(parser.error("trailing characters"));
+        }
+
+        Ok(value)
+    }
+
+    /// Member `key` of an object; `None` for other values.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Json::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+    pub fn as_f32(&self) -> Option<f32> {
+        self.as_f64().map(|n| n as f32)
+    }
+    /// The number as an index or count: `None` unless it is a whole,
+    /// non-negative number that fits.
+    pub fn as_usize(&self) -> Option<usize> {
+        let n = self.as_f64()?;
+        (n >= 0.0 && n.fract() == 0.0 && n <= u32::MAX as f64).then_some(n as usize)
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+// Deeper nesting than any real document; keeps hostile input from
+// overflowing the stack.
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &'static str) -> JsonError {
+        JsonError {
+            offset: self.pos,
+            message,
+        }
+    }
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+    fn expect(&mut self, byte: u8, message: &'static str) -> Result<(), JsonError> {
+        if self.peek() != Some(byte) {
+            return Err(self.error(message));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, JsonError> {
+        if !self.bytes[self.pos..].starts_with(word.as_bytes()) {
+            return Err(self.error("invalid literal"));
+        }
+        self.pos += word.len();
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            None => Err(self.error("unexpected end of input")),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'[' | b'{') => {
+                self.depth += 1;
+                if self.depth > MAX_DEPTH {
+                    return Err(self.error("nesting too deep"));
+                }
+
+                let value = if self.peek() == Some(b'[') {
+                    self.array()
+                } else {
+                    self.object()
+                };
+
+                self.depth -= 1;
+                value
+            }
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+        }
+    }
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.pos += 1;
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.pos += 1;
+        let mut members = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a key"));
+            }
+            let key = self.string()?;
+
+            self.skip_whitespace();
+            self.expect(b':', "expected ':'")?;
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(members));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.pos;
+        let digits = |p: &mut Self| {
+            let from = p.pos;
+            while matches!(p.peek(), Some(b'0'..=b'9')) {
+                p.pos += 1;
+            }
+            p.pos > from
+        };
+
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        if !digits(self) {
+            return Err(self.error("invalid number"));
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            if !digits(self) {
+                return Err(self.error("invalid number"));
+            }
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                return Err(self.error("invalid number"));
+            }
+        }
+
+        // The bytes checked above are ASCII, so this is valid UTF-8.
+        let text = std::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or_default();
+
+        text.parse().map(Json::Number).map_err(|_| JsonError {
+            offset: start,
+            message: "invalid number",
+        })
+    }
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.pos += 1;
+        let mut out = Vec::new();
+
+        loop {
+            let Some(byte) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += 1;
+
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let Some(escape) = self.peek() else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.pos += 1;
+
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+
+                    out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                0..=0x1f => return Err(self.error("control character in string")),
+                _ => out.push(byte),
+            }
+        }
+
+        // The input was a `&str` and escapes are encoded as UTF-8.
+        String::from_utf8(out).map_err(|_| self.error("invalid UTF-8"))
+    }
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let hex = self
+            .bytes
+            .get(self.pos..self.pos + 4)
+            .filter(|h| h.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u32::from_str_radix(h, 16).ok())
+            .ok_or_else(|| self.error("invalid \\u escape"))?;
+
+        self.pos += 4;
+        Ok(hex)
+    }
+    /// The code point after `\u`, joining UTF-16 surrogate pairs.
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.hex4()?;
+
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if !self.bytes[self.pos..].starts_with(b"\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            self.pos += 2;
+
+            let low = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("unpaired surrogate"));
+            }
+
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+
+        char::from_u32(code).ok_or_else(|| self.error("unpaired surrogate"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> &'static str {
+        Json::parse(text).unwrap_err().message
+    }
+
+    #[test]
+    fn parses_nested_values_in_order() {
+        let json = Json::parse(r#" {"b": [1, true, null], "a": {"c": "d"}} "#).unwrap();
+
+        assert_eq!(
+            json,
+            Json::Object(vec![
+                (
+                    "b".into(),
+                    Json::Array(vec![Json::Number(1.0), Json::Bool(true), Json::Null])
+                ),
+                (
+                    "a".into(),
+                    Json::Object(vec![("c".into(), Json::String("d".into()))])
+                ),
+            ])
+        );
+        assert_eq!(
+            json.get("a")
+                .and_then(|a| a.get("c"))
+                .and_then(Json::as_str),
+            Some("d")
+        );
+        assert_eq!(Json::parse("[]").unwrap(), Json::Array(Vec::new()));
+        assert_eq!(Json::parse("{}").unwrap(), Json::Object(Vec::new()));
+    }
+
+    #[test]
+    fn numbers() {
+        let number = |text: &str| Json::parse(text).unwrap().as_f64().unwrap();
+
+        assert_eq!(number("0"), 0.0);
+        assert_eq!(number("-12"), -12.0);
+        assert_eq!(number("3.25"), 3.25);
+        assert_eq!(number("1e3"), 1000.0);
+        assert_eq!(number("-2.5E-1"), -0.25);
+
+        for bad in ["-", "1.", ".5", "1e", "+1", "1e+"] {
+            assert!(Json::parse(bad).is_err(), "{bad}");
+        }
+
+        assert_eq!(Json::parse("3").unwrap().as_usize(), Some(3));
+        assert_eq!(Json::parse("3.5").unwrap().as_usize(), None);
+        assert_eq!(Json::parse("-1").unwrap().as_usize(), None);
+    }
+
+    #[test]
+    fn string_escapes() {
+        let string = |text: &str| Json::parse(text).unwrap().as_str().unwrap().to_owned();
+
+        assert_eq!(string(r#""a\"b\\c\/d""#), "a\"b\\c/d");
+        assert_eq!(string(r#""\b\f\n\r\t""#), "\u{8}\u{c}\n\r\t");
+        assert_eq!(string(r#""\u00e9\u4E2D""#), "é中");
+        assert_eq!(string("\"é\""), "é");
+
+        assert_eq!(error(r#""\x""#), "invalid escape");
+        assert_eq!(error(r#""\u12g4""#), "invalid \\u escape");
+        assert_eq!(error(r#""\u12""#), "invalid \\u escape");
+        assert_eq!(error("\"a\nb\""), "control character in string");
+        assert_eq!(error(r#""abc"#), "unterminated string");
+    }
+
+    #[test]
+    fn surrogate_pairs() {
+        let json = Json::parse(r#""\ud83d\ude00""#).unwrap();
+        assert_eq!(json.as_str(), Some("😀"));
+
+        assert_eq!(error(r#""\ud83d""#), "unpaired surrogate");
+        assert_eq!(error(r#""\ud83dA""#), "unpaired surrogate");
+        assert_eq!(error(r#""\ude00""#), "unpaired surrogate");
+    }
+
+    #[test]
+    fn trailing_and_malformed_input() {
+        assert_eq!(error("1 2"), "trailing characters");
+        assert_eq!(error("{} x"), "trailing characters");
+        assert_eq!(error(""), "unexpected end of input");
+        assert_eq!(error("[1,]"), "unexpected character");
+        assert_eq!(error("[1 2]"), "expected ',' or ']'");
+        assert_eq!(error(r#"{"a" 1}"#), "expected ':'");
+        assert_eq!(error("{1: 2}"), "expected a key");
+        assert_eq!(error("nul"), "invalid literal");
+
+        let err = Json::parse("[1, ?]").unwrap_err();
+        assert_eq!(err.offset, 4);
+    }
+
+    #[test]
+    fn nesting_depth_is_limited() {
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(error(&nested(MAX_DEPTH + 1)), "nesting too deep");
+        // Far deeper than the stack could take unchecked.
+        assert_eq!(error(&"[".repeat(1_000_000)), "nesting too deep");
+    }
+}
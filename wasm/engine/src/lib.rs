@@ -1,271 +1,439 @@
 use wasm_bindgen::prelude::*;
 
-struct Storage<T> {
-    dense: Vec<T>,
-    dense_entities: Vec<u32>,
-    sparse: Vec<Option<usize>>,
+pub mod batch;
+pub mod bounds;
+pub mod camera;
+pub mod commands;
+pub mod components;
+pub mod events;
+pub mod hierarchy;
+pub mod import;
+pub mod json;
+pub mod light;
+pub mod math;
+pub mod mesh;
+#[cfg(all(feature = "raster", not(target_arch = "wasm32")))]
+pub mod png;
+pub mod query;
+#[cfg(all(feature = "raster", not(target_arch = "wasm32")))]
+pub mod raster;
+pub mod render;
+pub mod resources;
+pub mod schedule;
+pub mod sky;
+pub mod storage;
+pub mod world;
+
+use bounds::{Aabb, Bounds, WorldBounds};
+use camera::{Camera, Ray, ScreenPoint, Viewport};
+use commands::Commands;
+use components::{Color, Dragging, Hidden, Material, Mesh, Selected, Static, Transform, Velocity};
+use events::{EngineEvent, Events};
+use hierarchy::{Children, GlobalTransform, Parent};
+use light::{DirectionalLight, LightBuffer, PointLight, SpotLight};
+use math::{Mat4, Quat, Vec3};
+use mesh::Meshes;
+use query::{With, Without};
+use render::{RenderInstances, StreamKind};
+use schedule::{Schedule, ScheduleError, Stage, SystemConfig};
+use sky::{Sky, TimeOfDay};
+use world::{Component, Entity, Lifecycle, World};
+
+/// Frame timing, written at the start of every `Engine::update`.
+#[derive(Clone, Copy, Default)]
+pub struct Time {
+    pub elapsed: f32,
+    pub delta: f32,
 }
-impl<T> Storage<T> {
-    fn new() -> Self {
-        Self {
-            dense: Vec::new(),
-            dense_entities: Vec::new(),
-            sparse: Vec::new(),
-        }
-    }
-    fn clear(&mut self) {
-        for &entity_index in &self.dense_entities {
-            if let Some(slot) = self.sparse.get_mut(entity_index as usize) {
-                *slot = None;
-            }
-        }
-
-        self.dense.clear();
-        self.dense_entities.clear();
-    }
-    fn contains(&self, entity: Entity) -> bool {
-        self.sparse
-            .get(entity.index as usize)
-            .and_then(|o| *o)
-            .is_some()
-    }
-    fn insert(&mut self, entity: Entity, component: T) {
-        let index = entity.index as usize;
-
-        if index >= self.sparse.len() {
-            self.sparse.resize(index + 1, None);
-        }
-
-        if let Some(dense_index) = self.sparse[index] {
-            self.dense[dense_index] = component;
-            return;
-        }
-
-        let dense_index = self.dense.len();
-
-        self.dense.push(component);
-        self.dense_entities.push(entity.index);
-        self.sparse[index] = Some(dense_index);
-    }
-    fn remove_entity(&mut self, entity: Entity) {
-        let index = entity.index as usize;
-
-        if index >= self.sparse.len() {
-            return;
-        }
-
-        let Some(dense_index) = self.sparse[index] else {
-            return;
-        };
-
-        let last_dense = self.dense.len() - 1;
 
-        self.dense.swap(dense_index, last_dense);
-        self.dense_entities.swap(dense_index, last_dense);
+/// When set, systems in the simulation stage are skipped.
+#[derive(Clone, Copy, Default)]
+pub struct Paused(pub bool);
 
-        let moved_entity = self.dense_entities[dense_index];
-        self.sparse[moved_entity as usize] = Some(dense_index);
-
-        self.dense.pop();
-        self.dense_entities.pop();
-        self.sparse[index] = None;
-    }
-    fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
-        let index = entity.index as usize;
-
-        if index >= self.sparse.len() {
-            return None;
-        }
-
-        let dense_index = self.sparse[index]?;
-        self.dense.get_mut(dense_index)
-    }
-    fn get(&self, entity: Entity) -> Option<&T> {
-        let index = entity.index as usize;
-
-        if index >= self.sparse.len() {
-            return None;
-        }
-
-        let dense_index = self.sparse[index]?;
-        self.dense.get(dense_index)
-    }
-    fn iter(&self) -> impl Iterator<Item = (u32, &T)> {
-        self.dense_entities.iter().copied().zip(self.dense.iter())
-    }
-}
-
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub struct Entity {
-    index: u32,
-    generation: u32,
-}
-#[derive(Clone, Copy)]
-pub struct Color {
-    pub rgb: [f32; 3],
-}
-#[derive(Clone, Copy)]
-pub struct Transform {
-    pub position: [f32; 3],
-    pub rotation: [f32; 3],
-    pub scale: [f32; 3],
+#[derive(Default)]
+struct DragState {
+    ray: Option<Ray>,
+    // Overlapping (dragged, other) pairs seen last frame.
+    contacts: Vec<(Entity, Entity)>,
 }
 
-#[derive(Clone, Copy)]
-pub struct Velocity {
-    pub linear: [f32; 3],
+/// Projection * view from the last camera update, column-major, and
+/// the eye it looks from.
+#[derive(Clone, Copy, Default)]
+pub struct ViewProj {
+    pub matrix: Mat4,
+    pub eye: Vec3,
 }
 
-#[derive(Clone, Copy)]
-pub struct Camera {
-    pub target: [f32; 3],
-    pub yaw: f32,
-    pub pitch: f32,
-    pub distance: f32,
-
-    pub fov: f32,
-    pub near: f32,
-    pub far: f32,
-    pub aspect: f32,
-}
-
-#[derive(Clone, Copy)]
-struct DragRay {
-    origin: [f32; 3],
-    dir: [f32; 3],
+/// Output of `Engine::project_all`: one `ScreenPoint` record per entry
+/// of `entities`.
+#[derive(Default)]
+pub struct ProjectionBuffer {
+    pub data: Vec<f32>,
+    pub entities: Vec<u32>,
 }
 
 #[wasm_bindgen]
 pub struct Engine {
-    time: f32,
-    delta: f32,
-    static_objects: Storage<()>,
-    generations: Vec<u32>,
-    free_indices: Vec<u32>,
-    colors: Storage<Color>,
-    transforms: Storage<Transform>,
-    velocities: Storage<Velocity>,
-    selected: Storage<()>,
-    dragging: Storage<[f32; 3]>, // offset stored directly
-    current_drag_ray: Option<DragRay>,
-
-    camera: Option<Camera>,
-
-    render_buffer: Vec<f32>,
-    view_proj: [f32; 16],
+    world: World,
+    schedule: Schedule,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
 impl Engine {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Engine {
+        let mut world = World::new();
+
+        world.register::<Color>();
+        world.register::<Material>();
+        world.register::<Transform>();
+        world.register::<Velocity>();
+        world.register::<Static>();
+        world.register::<Selected>();
+        world.register::<Dragging>();
+        world.register::<Parent>();
+        world.register::<Children>();
+        world.register::<GlobalTransform>();
+        world.register::<Mesh>();
+        world.register::<Hidden>();
+        world.register::<Bounds>();
+        world.register::<WorldBounds>();
+        world.register::<DirectionalLight>();
+        world.register::<PointLight>();
+        world.register::<SpotLight>();
+
+        world.insert_resource(Time::default());
+        world.insert_resource(Paused::default());
+        world.insert_resource(DragState::default());
+        world.insert_resource(ViewProj::default());
+        world.insert_resource(Viewport::default());
+        world.insert_resource(RenderInstances::default());
+        world.insert_resource(Meshes::default());
+        world.insert_resource(LightBuffer::default());
+        world.insert_resource(TimeOfDay::default());
+        world.insert_resource(Sky::default());
+        world.insert_resource(ProjectionBuffer::default());
+        world.insert_resource(Commands::new());
+        world.insert_resource(Events::new());
+
         Engine {
-            time: 0.0,
-            delta: 0.0,
-            static_objects: Storage::new(),
-            generations: Vec::new(),
-            free_indices: Vec::new(),
-            colors: Storage::new(),
-            transforms: Storage::new(),
-            velocities: Storage::new(),
-            selected: Storage::new(),
-            dragging: Storage::new(),
-            current_drag_ray: None,
-            camera: None,
-            render_buffer: Vec::new(),
-            view_proj: [0.0; 16],
+            world,
+            schedule: Self::default_schedule(),
         }
     }
 
+    fn default_schedule() -> Schedule {
+        let mut schedule = Schedule::new();
+
+        schedule
+            .add_system("drag", Stage::Input, Engine::update_drag_system)
+            .reads::<DragState>();
+        schedule
+            .add_system(
+                "integrate_velocity",
+                Stage::Simulation,
+                Engine::integrate_velocity,
+            )
+            .reads::<Time>()
+            .run_if(|world| !world.resource::<Paused>().0);
+        schedule
+            .add_system(
+                "advance_time_of_day",
+                Stage::Simulation,
+                sky::advance_time_of_day,
+            )
+            .reads::<Time>()
+            .writes::<TimeOfDay>()
+            .run_if(|world| !world.resource::<Paused>().0);
+        // Not gated on `Paused`, so `set_time_of_day` shows while paused.
+        schedule
+            .add_system("sun", Stage::Simulation, sky::update_sun)
+            .reads::<TimeOfDay>()
+            .writes::<Sky>()
+            .after("advance_time_of_day");
+
+        // Structural changes queued by the systems above land here,
+        // before anything is extracted for rendering.
+        schedule
+            .add_system(
+                "apply_commands",
+                Stage::PostSimulation,
+                Engine::apply_commands,
+            )
+            .writes::<Commands>();
+        schedule
+            .add_system("propagate_transforms", Stage::PostSimulation, |world| {
+                hierarchy::propagate_transforms(world)
+            })
+            .after("apply_commands");
+        schedule
+            .add_system("world_bounds", Stage::PostSimulation, |world| {
+                bounds::update_world_bounds(world)
+            })
+            .after("propagate_transforms");
+        schedule
+            .add_system(
+                "drag_collisions",
+                Stage::PostSimulation,
+                Engine::detect_drag_collisions,
+            )
+            .writes::<DragState>()
+            .writes::<Events>()
+            .after("world_bounds");
+
+        schedule
+            .add_system("camera", Stage::RenderExtract, Engine::update_camera)
+            .reads::<Camera>()
+            .writes::<ViewProj>();
+        schedule
+            .add_system(
+                "render_instances",
+                Stage::RenderExtract,
+                render::extract_instances,
+            )
+            .reads::<Camera>()
+            .reads::<ViewProj>()
+            .writes::<RenderInstances>()
+            .after("camera");
+        schedule
+            .add_system("lights", Stage::RenderExtract, light::extract_lights)
+            .writes::<LightBuffer>();
+
+        schedule
+            .rebuild()
+            .expect("built-in systems have a valid order");
+        schedule
+    }
+
     pub fn entity_count(&self) -> usize {
-        self.transforms.dense.len()
+        self.world.storage::<Transform>().len()
     }
     pub fn selected_count(&self) -> usize {
-        self.selected.dense.len()
+        self.world.storage::<Selected>().len()
     }
     pub fn update(&mut self, delta: f32) {
-        self.delta = delta;
-        self.time += delta;
+        {
+            let mut time = self.world.resource_mut::<Time>();
+            time.delta = delta;
+            time.elapsed += delta;
+        }
+
+        self.schedule.run(&mut self.world);
 
-        self.update_drag_system();
-        self.integrate_velocity();
-        self.update_camera();
-        self.build_render_buffer();
+        self.world.advance_tick();
     }
 
-    fn make_entity(&self, index: u32) -> Option<Entity> {
-        let idx = index as usize;
+    // ===== SCHEDULING =====
 
-        if idx >= self.generations.len() {
-            return None;
+    fn apply_commands(world: &mut World) {
+        // Taken out so `apply` can have the world mutably; put back to
+        // keep the queue's allocation.
+        if let Some(mut commands) = world.remove_resource::<Commands>() {
+            commands.apply(world);
+            world.insert_resource(commands);
         }
+    }
+
+    /// Pauses simulation systems; input and rendering keep running.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.world.resource_mut::<Paused>().0 = paused;
+    }
+    pub fn is_paused(&self) -> bool {
+        self.world.resource::<Paused>().0
+    }
+    /// Turns a system on or off by name. Returns `false` if no system has
+    /// that name.
+    pub fn set_system_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        self.schedule.set_enabled(name, enabled)
+    }
+    /// `stage/name` for every system, in execution order.
+    pub fn system_names(&mut self) -> Vec<String> {
+        self.schedule.describe()
+    }
+
+    // ===== EVENTS =====
 
-        Some(Entity {
-            index,
-            generation: self.generations[idx],
-        })
+    /// Queues an event behind any spawns/despawns the world has recorded,
+    /// so JS sees everything in the order it happened.
+    fn emit(world: &mut World, event: EngineEvent) {
+        Self::flush_lifecycle(world);
+        world.resource_mut::<Events>().push(event);
     }
-    pub fn set_static(&mut self, index: u32) {
-        if let Some(entity) = self.make_entity(index) {
-            self.static_objects.insert(entity, ());
+    fn flush_lifecycle(world: &mut World) {
+        let changes: Vec<Lifecycle> = world.drain_lifecycle().collect();
+        let mut events = world.resource_mut::<Events>();
+
+        for change in changes {
+            events.push(match change {
+                Lifecycle::Spawned(entity) => EngineEvent::EntitySpawned(entity),
+                Lifecycle::Despawned(entity) => EngineEvent::EntityDespawned(entity),
+            });
         }
     }
-    pub fn set_position(&mut self, index: u32, x: f32, y: f32, z: f32) {
-        if let Some(entity) = self.make_entity(index) {
-            if let Some(transform) = self.transforms.get_mut(entity) {
-                transform.position = [x, y, z];
+    /// Drains all pending events as a flat `Uint32Array` of
+    /// `[kind, a, b]` records (see `EventKind`). Entities are handles.
+    pub fn drain_events(&mut self) -> Vec<u32> {
+        Self::flush_lifecycle(&mut self.world);
+        self.world.resource_mut::<Events>().drain_encoded()
+    }
+
+    /// Resolves a handle from JS, rejecting ones whose entity has been
+    /// destroyed (even if its index has since been reused).
+    fn resolve(&self, handle: u32) -> Option<Entity> {
+        let entity = Entity::from_bits(handle);
+        self.world.is_alive(entity).then_some(entity)
+    }
+    pub fn is_alive(&self, handle: u32) -> bool {
+        self.resolve(handle).is_some()
+    }
+    /// Stops drawing and picking the entity, or starts again. Its
+    /// children and any light on it are unaffected.
+    pub fn set_hidden(&mut self, handle: u32, hidden: bool) {
+        if let Some(entity) = self.resolve(handle) {
+            if hidden {
+                self.world.insert(entity, Hidden);
+            } else {
+                self.world.remove::<Hidden>(entity);
             }
         }
     }
-    pub fn set_scale(&mut self, index: u32, sx: f32, sy: f32, sz: f32) {
-        if let Some(entity) = self.make_entity(index) {
-            if let Some(t) = self.transforms.get_mut(entity) {
-                t.scale = [sx, sy, sz];
-            }
+    pub fn set_static(&mut self, handle: u32) {
+        if let Some(entity) = self.resolve(handle) {
+            self.world.insert(entity, Static);
         }
     }
-    pub fn add_color(&mut self, index: u32, r: f32, g: f32, b: f32) {
-        if let Some(entity) = self.make_entity(index) {
-            self.colors.insert(entity, Color { rgb: [r, g, b] });
+    pub fn set_position(&mut self, handle: u32, x: f32, y: f32, z: f32) {
+        if let Some(entity) = self.resolve(handle)
+            && let Some(mut transform) = self.world.get_mut::<Transform>(entity)
+        {
+            transform.position = Vec3::new(x, y, z);
         }
     }
-    pub fn set_selected_color(&mut self, r: f32, g: f32, b: f32) {
-        for (entity_index, _) in self.selected.iter() {
-            let entity = Entity {
-                index: entity_index,
-                generation: self.generations[entity_index as usize],
-            };
-
-            if let Some(color) = self.colors.get_mut(entity) {
-                color.rgb = [r, g, b];
-            }
+    pub fn set_scale(&mut self, handle: u32, sx: f32, sy: f32, sz: f32) {
+        if let Some(entity) = self.resolve(handle)
+            && let Some(mut t) = self.world.get_mut::<Transform>(entity)
+        {
+            t.scale = Vec3::new(sx, sy, sz);
         }
     }
-    fn integrate_velocity(&mut self) {
-        for (entity_index, velocity) in self.velocities.iter() {
-            let entity = Entity {
-                index: entity_index,
-                generation: self.generations[entity_index as usize],
-            };
-            if self.static_objects.contains(entity) {
-                continue;
-            }
-            if let Some(transform) = self.transforms.get_mut(entity) {
-                transform.position[0] += velocity.linear[0] * self.delta;
-                transform.position[1] += velocity.linear[1] * self.delta;
-                transform.position[2] += velocity.linear[2] * self.delta;
-            }
+    /// Sets the local rotation from Euler angles in radians; see
+    /// `add_transform` for the order.
+    pub fn set_rotation(&mut self, handle: u32, rx: f32, ry: f32, rz: f32) {
+        if let Some(entity) = self.resolve(handle)
+            && let Some(mut t) = self.world.get_mut::<Transform>(entity)
+        {
+            t.rotation = Quat::from_euler(rx, ry, rz);
         }
     }
-    fn orbit_position(cam: &Camera) -> [f32; 3] {
-        let x = cam.distance * cam.pitch.cos() * cam.yaw.sin();
-        let y = cam.distance * cam.pitch.sin();
-        let z = cam.distance * cam.pitch.cos() * cam.yaw.cos();
+    /// Local rotation as `[rx, ry, rz]` Euler angles, or `undefined` if
+    /// the entity has no transform.
+    pub fn get_rotation(&self, handle: u32) -> Option<Vec<f32>> {
+        let entity = self.resolve(handle)?;
+        let (x, y, z) = self.world.get::<Transform>(entity)?.rotation.to_euler();
+
+        Some(vec![x, y, z])
+    }
+    /// Draws the entity with mesh asset `mesh` instead of the cube, and
+    /// fits its `Bounds` to the mesh. Unknown ids are ignored.
+    pub fn set_mesh(&mut self, handle: u32, mesh: u32) {
+        let Some(entity) = self.resolve(handle) else {
+            return;
+        };
+        let Some(bounds) = self.world.resource::<Meshes>().bounds(Mesh(mesh)) else {
+            return;
+        };
+
+        self.world.insert(entity, Mesh(mesh));
+        self.world.insert(entity, bounds);
+    }
+    pub fn add_color(&mut self, handle: u32, r: f32, g: f32, b: f32) {
+        if let Some(entity) = self.resolve(handle) {
+            self.world.insert(entity, Color { rgb: [r, g, b] });
+        }
+    }
+    pub fn set_selected_color(&mut self, r: f32, g: f32, b: f32) {
+        self.world
+            .query_filtered::<&mut Color, With<Selected>>()
+            .for_each(|_, color| color.rgb = [r, g, b]);
+        self.world
+            .query_filtered::<&mut Material, With<Selected>>()
+            .for_each(|_, material| material.base_color[..3].copy_from_slice(&[r, g, b]));
+    }
+    /// Sets the entity's base color, opacity and surface response,
+    /// keeping its emission. An entity without a `Material` starts from
+    /// the one it is drawn with.
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_material(
+        &mut self,
+        handle: u32,
+        r: f32,
+        g: f32,
+        b: f32,
+        alpha: f32,
+        metallic: f32,
+        roughness: f32,
+    ) {
+        self.update_material(handle, |material| {
+            material.base_color = [r, g, b, alpha.clamp(0.0, 1.0)];
+            material.metallic = metallic.clamp(0.0, 1.0);
+            material.roughness = roughness.clamp(0.0, 1.0);
+        });
+    }
+    /// Makes the entity glow with color `r, g, b` times `strength`.
+    pub fn set_emissive(&mut self, handle: u32, r: f32, g: f32, b: f32, strength: f32) {
+        self.update_material(handle, |material| {
+            material.emissive = [r, g, b];
+            material.emissive_strength = strength.max(0.0);
+        });
+    }
+    /// Draws the entity without lighting, at its base and emissive color.
+    pub fn set_unlit(&mut self, handle: u32, unlit: bool) {
+        self.update_material(handle, |material| material.unlit = unlit);
+    }
+    /// The entity's material as `[r, g, b, alpha, metallic, roughness,
+    /// emissive r, g, b, strength, unlit]`, as drawn (see
+    /// `render::resolve_material`).
+    pub fn get_material(&self, handle: u32) -> Option<Vec<f32>> {
+        let m = self.material(self.resolve(handle)?);
+        let mut out = m.base_color.to_vec();
+
+        out.extend([m.metallic, m.roughness]);
+        out.extend(m.emissive);
+        out.extend([m.emissive_strength, m.unlit as u32 as f32]);
+        Some(out)
+    }
+    fn material(&self, entity: Entity) -> Material {
+        render::resolve_material(
+            self.world.get::<Material>(entity).as_deref(),
+            self.world.get::<Color>(entity).as_deref(),
+        )
+    }
+    fn update_material(&mut self, handle: u32, update: impl FnOnce(&mut Material)) {
+        let Some(entity) = self.resolve(handle) else {
+            return;
+        };
+
+        let mut material = self.material(entity);
+
+        update(&mut material);
+        self.world.insert(entity, material);
+    }
+    fn integrate_velocity(world: &mut World) {
+        let delta = world.resource::<Time>().delta;
 
-        [cam.target[0] + x, cam.target[1] + y, cam.target[2] + z]
+        world
+            .query_filtered::<(&mut Transform, &Velocity), Without<Static>>()
+            .for_each(|_, (transform, velocity)| transform.position += velocity.linear * delta);
     }
     pub fn camera_orbit(&mut self, dx: f32, dy: f32) {
-        if let Some(ref mut cam) = self.camera {
+        if let Some(mut cam) = self.world.get_resource_mut::<Camera>() {
             cam.yaw += dx;
             cam.pitch += dy;
 
@@ -274,349 +442,159 @@ impl Engine {
     }
 
     pub fn camera_zoom(&mut self, delta: f32) {
-        if let Some(ref mut cam) = self.camera {
+        if let Some(mut cam) = self.world.get_resource_mut::<Camera>() {
             cam.distance += delta;
             cam.distance = cam.distance.clamp(1.5, 50.0);
         }
     }
 
     pub fn camera_pan(&mut self, dx: f32, dy: f32) {
-        if let Some(ref mut cam) = self.camera {
-            cam.target[0] += dx;
-            cam.target[1] += dy;
-        }
-    }
-
-    fn update_camera(&mut self) {
-        if let Some(cam) = self.camera {
-            let eye = Self::orbit_position(&cam);
-
-            let proj = Self::perspective(cam.fov, cam.aspect, cam.near, cam.far);
-
-            let view = Self::view_matrix(eye, cam.target);
-
-            self.view_proj = Self::mul_mat4(proj, view);
-        }
-    }
-    fn view_matrix(eye: [f32; 3], target: [f32; 3]) -> [f32; 16] {
-        let fx = target[0] - eye[0];
-        let fy = target[1] - eye[1];
-        let fz = target[2] - eye[2];
-
-        let fl = (fx * fx + fy * fy + fz * fz).sqrt();
-        let fx = fx / fl;
-        let fy = fy / fl;
-        let fz = fz / fl;
-
-        let up = [0.0, 1.0, 0.0];
-
-        let sx = fy * up[2] - fz * up[1];
-        let sy = fz * up[0] - fx * up[2];
-        let sz = fx * up[1] - fy * up[0];
-
-        let sl = (sx * sx + sy * sy + sz * sz).sqrt();
-        let sx = sx / sl;
-        let sy = sy / sl;
-        let sz = sz / sl;
-
-        let ux = sy * fz - sz * fy;
-        let uy = sz * fx - sx * fz;
-        let uz = sx * fy - sy * fx;
-
-        [
-            sx,
-            ux,
-            -fx,
-            0.0,
-            sy,
-            uy,
-            -fy,
-            0.0,
-            sz,
-            uz,
-            -fz,
-            0.0,
-            -(sx * eye[0] + sy * eye[1] + sz * eye[2]),
-            -(ux * eye[0] + uy * eye[1] + uz * eye[2]),
-            fx * eye[0] + fy * eye[1] + fz * eye[2],
-            1.0,
-        ]
-    }
-
-    fn perspective(fov: f32, aspect: f32, near: f32, far: f32) -> [f32; 16] {
-        let f = 1.0 / (fov / 2.0).tan();
-        let nf = 1.0 / (near - far);
-
-        [
-            f / aspect,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            f,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            (far + near) * nf,
-            -1.0,
-            0.0,
-            0.0,
-            (2.0 * far * near) * nf,
-            0.0,
-        ]
-    }
-
-    fn mul_mat4(a: [f32; 16], b: [f32; 16]) -> [f32; 16] {
-        let mut r = [0.0; 16];
-
-        for col in 0..4 {
-            for row in 0..4 {
-                r[col * 4 + row] = a[0 * 4 + row] * b[col * 4 + 0]
-                    + a[1 * 4 + row] * b[col * 4 + 1]
-                    + a[2 * 4 + row] * b[col * 4 + 2]
-                    + a[3 * 4 + row] * b[col * 4 + 3];
-            }
+        if let Some(mut cam) = self.world.get_resource_mut::<Camera>() {
+            cam.target += Vec3::new(dx, dy, 0.0);
         }
+    }
 
-        r
-    }
-
-    fn model_matrix(t: Transform) -> [f32; 16] {
-        let sx = t.scale[0];
-        let sy = t.scale[1];
-        let sz = t.scale[2];
-
-        let px = t.position[0];
-        let py = t.position[1];
-        let pz = t.position[2];
-
-        let ry = t.rotation[1];
-        let c = ry.cos();
-        let s = ry.sin();
-
-        // Column-major
-        [
-            sx * c,
-            0.0,
-            sx * s,
-            0.0,
-            0.0,
-            sy,
-            0.0,
-            0.0,
-            -sz * s,
-            0.0,
-            sz * c,
-            0.0,
-            px,
-            py,
-            pz,
-            1.0,
-        ]
-    }
-    fn transform_point(m: [f32; 16], p: [f32; 3]) -> [f32; 3] {
-        [
-            m[0] * p[0] + m[4] * p[1] + m[8] * p[2] + m[12],
-            m[1] * p[0] + m[5] * p[1] + m[9] * p[2] + m[13],
-            m[2] * p[0] + m[6] * p[1] + m[10] * p[2] + m[14],
-        ]
-    }
-
-    fn transform_dir(m: [f32; 16], d: [f32; 3]) -> [f32; 3] {
-        [
-            m[0] * d[0] + m[4] * d[1] + m[8] * d[2],
-            m[1] * d[0] + m[5] * d[1] + m[9] * d[2],
-            m[2] * d[0] + m[6] * d[1] + m[10] * d[2],
-        ]
-    }
-
-    fn invert_model(t: Transform) -> [f32; 16] {
-        let sx = t.scale[0];
-        let sy = t.scale[1];
-        let sz = t.scale[2];
-
-        let px = t.position[0];
-        let py = t.position[1];
-        let pz = t.position[2];
-
-        let ry = t.rotation[1];
-        let c = ry.cos();
-        let s = ry.sin();
-
-        let isx = 1.0 / sx;
-        let isy = 1.0 / sy;
-        let isz = 1.0 / sz;
-
-        // Column-major inverse
-        [
-            isx * c,
-            0.0,
-            -isz * s,
-            0.0,
-            0.0,
-            isy,
-            0.0,
-            0.0,
-            isx * s,
-            0.0,
-            isz * c,
-            0.0,
-            -(px * isx * c + pz * isx * s),
-            -py * isy,
-            -(-px * isz * s + pz * isz * c),
-            1.0,
-        ]
-    }
-
-    fn build_render_buffer(&mut self) {
-        self.render_buffer.clear();
-
-        for (entity_index, transform) in self.transforms.iter() {
-            let entity = Entity {
-                index: entity_index,
-                generation: self.generations[entity_index as usize],
-            };
-
-            let model = Self::model_matrix(*transform);
-            self.render_buffer.extend_from_slice(&model);
-
-            let mut color = if let Some(c) = self.colors.get(entity) {
-                [c.rgb[0], c.rgb[1], c.rgb[2], 1.0]
-            } else {
-                [0.2, 0.7, 1.0, 1.0] // fallback default
+    fn update_camera(world: &mut World) {
+        if let Some(cam) = world.get_resource::<Camera>() {
+            *world.resource_mut::<ViewProj>() = ViewProj {
+                matrix: cam.view_proj(),
+                eye: cam.eye(),
             };
-
-            if self.selected.contains(entity) {
-                // brighten selected cubes slightly
-                color[0] = (color[0] + 0.3).min(1.0);
-                color[1] = (color[1] + 0.3).min(1.0);
-                color[2] = (color[2] + 0.3).min(1.0);
-            }
-
-            self.render_buffer.extend_from_slice(&color);
         }
     }
 
-    fn ray_plane_intersection(
-        ray_origin: [f32; 3],
-        ray_dir: [f32; 3],
-        plane_y: f32,
-    ) -> Option<[f32; 3]> {
-        if ray_dir[1].abs() < 0.0001 {
+    fn ray_plane_intersection(ray_origin: Vec3, ray_dir: Vec3, plane_y: f32) -> Option<Vec3> {
+        if ray_dir.y.abs() < 0.0001 {
             return None; // parallel to plane
         }
 
-        let t = (plane_y - ray_origin[1]) / ray_dir[1];
+        let t = (plane_y - ray_origin.y) / ray_dir.y;
 
         if t < 0.0 {
             return None;
         }
 
-        Some([
-            ray_origin[0] + ray_dir[0] * t,
+        Some(Vec3::new(
+            ray_origin.x + ray_dir.x * t,
             plane_y,
-            ray_origin[2] + ray_dir[2] * t,
-        ])
+            ray_origin.z + ray_dir.z * t,
+        ))
     }
 
     // ===== ENTITY =====
-    fn ray_aabb(origin: [f32; 3], dir: [f32; 3]) -> Option<f32> {
-        let min = [-0.5, -0.5, -0.5];
-        let max = [0.5, 0.5, 0.5];
-
-        let mut tmin = f32::NEG_INFINITY;
-        let mut tmax = f32::INFINITY;
-
-        for i in 0..3 {
-            if dir[i].abs() < 1e-6 {
-                if origin[i] < min[i] || origin[i] > max[i] {
-                    return None;
-                }
-            } else {
-                let inv = 1.0 / dir[i];
-                let mut t1 = (min[i] - origin[i]) * inv;
-                let mut t2 = (max[i] - origin[i]) * inv;
-
-                if t1 > t2 {
-                    std::mem::swap(&mut t1, &mut t2);
-                }
+    pub fn focus_selected(&mut self) {
+        if let Some(mut cam) = self.world.get_resource_mut::<Camera>() {
+            let mut count = 0;
+            let mut center = Vec3::ZERO;
 
-                tmin = tmin.max(t1);
-                tmax = tmax.min(t2);
+            self.world
+                .query_filtered::<&GlobalTransform, With<Selected>>()
+                .for_each(|_, global| {
+                    center += global.translation();
+                    count += 1;
+                });
 
-                if tmin > tmax {
-                    return None;
-                }
+            if count > 0 {
+                cam.target = center / count as f32;
             }
         }
-
-        if tmax < 0.0 {
-            None
-        } else {
-            Some(tmin.max(0.0))
-        }
     }
-    pub fn focus_selected(&mut self) {
-        if let Some(ref mut cam) = self.camera {
-            let mut count = 0;
-            let mut center = [0.0, 0.0, 0.0];
 
-            for (entity_index, _) in self.selected.iter() {
-                let entity = Entity {
-                    index: entity_index,
-                    generation: self.generations[entity_index as usize],
-                };
-
-                if let Some(transform) = self.transforms.get_mut(entity) {
-                    center[0] += transform.position[0];
-                    center[1] += transform.position[1];
-                    center[2] += transform.position[2];
-                    count += 1;
-                }
-            }
+    /// Points the camera at the center of the whole scene and backs it
+    /// off until the scene's bounding sphere fits the vertical field of
+    /// view.
+    pub fn frame_scene(&mut self) {
+        let Some(scene) = bounds::scene_bounds(&self.world) else {
+            return;
+        };
 
-            if count > 0 {
-                center[0] /= count as f32;
-                center[1] /= count as f32;
-                center[2] /= count as f32;
+        if let Some(mut cam) = self.world.get_resource_mut::<Camera>() {
+            let radius = scene.half_extents().length();
 
-                cam.target = center;
-            }
+            cam.target = scene.center();
+            cam.distance = (radius / (cam.fov * 0.5).sin()).max(cam.near + radius);
         }
     }
+    /// Union of all entities' world bounds as `[minX, minY, minZ, maxX,
+    /// maxY, maxZ]`, as of the last `update`. `undefined` if the scene is
+    /// empty.
+    pub fn scene_bounds(&self) -> Option<Vec<f32>> {
+        let Aabb { min, max } = bounds::scene_bounds(&self.world)?;
 
-    pub fn create_entity(&mut self) -> u32 {
-        if let Some(index) = self.free_indices.pop() {
-            index
-        } else {
-            let index = self.generations.len() as u32;
-            self.generations.push(0);
-            index
+        Some(vec![min.x, min.y, min.z, max.x, max.y, max.z])
+    }
+    /// Sets the entity's local bounding box, before its transform. Without
+    /// one it is treated as a unit cube.
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_bounds(
+        &mut self,
+        handle: u32,
+        min_x: f32,
+        min_y: f32,
+        min_z: f32,
+        max_x: f32,
+        max_y: f32,
+        max_z: f32,
+    ) {
+        if let Some(entity) = self.resolve(handle) {
+            let aabb = Aabb::new(
+                Vec3::new(min_x, min_y, min_z),
+                Vec3::new(max_x, max_y, max_z),
+            );
+            self.world.insert(entity, Bounds::from_aabb(aabb));
         }
     }
-    pub fn destroy_entity(&mut self, index: u32) {
-        let Some(entity) = self.make_entity(index) else {
-            return;
-        };
 
-        let idx = entity.index as usize;
+    /// Throws once every entity index is in use.
+    pub fn create_entity(&mut self) -> Result<u32, JsError> {
+        Ok(self.world.spawn()?.to_bits())
+    }
+    /// Spawns one entity per record of `data`, laid out as described by
+    /// the `SpawnField` bitmask `layout`. Returns the new handles in
+    /// record order.
+    pub fn spawn_batch(&mut self, data: &[f32], layout: u32) -> Result<Vec<u32>, JsError> {
+        let entities = batch::spawn_batch(&mut self.world, data, layout)?;
 
-        if self.generations[idx] != entity.generation {
+        Ok(entities.into_iter().map(Entity::to_bits).collect())
+    }
+    /// Destroys the entity and everything attached below it.
+    pub fn destroy_entity(&mut self, handle: u32) {
+        let Some(entity) = self.resolve(handle) else {
             return;
-        }
+        };
 
-        self.generations[idx] += 1;
-        self.free_indices.push(index);
+        let selected = self.selected_count();
+        hierarchy::despawn_recursive(&mut self.world, entity);
 
-        self.transforms.remove_entity(entity);
-        self.velocities.remove_entity(entity);
-        self.selected.remove_entity(entity);
-        self.dragging.remove_entity(entity);
+        // Despawning only ever shrinks the selection.
+        let count = self.selected_count();
+        if count != selected {
+            Self::emit(&mut self.world, EngineEvent::SelectionChanged(count as u32));
+        }
     }
+    /// Attaches `child` to `parent`, keeping its current world position.
+    /// Returns `false` for stale handles or if it would create a cycle.
+    pub fn set_parent(&mut self, child: u32, parent: u32) -> bool {
+        match (self.resolve(child), self.resolve(parent)) {
+            (Some(child), Some(parent)) => {
+                hierarchy::set_parent(&mut self.world, child, Some(parent))
+            }
+            _ => false,
+        }
+    }
+    /// Detaches `child` from its parent, keeping its current world position.
+    pub fn clear_parent(&mut self, child: u32) -> bool {
+        match self.resolve(child) {
+            Some(child) => hierarchy::set_parent(&mut self.world, child, None),
+            None => false,
+        }
+    }
+    /// Rotation is intrinsic Y-X-Z Euler angles in radians (yaw, pitch,
+    /// roll), passed as `rx, ry, rz`.
+    #[allow(clippy::too_many_arguments)]
     pub fn add_transform(
         &mut self,
-        index: u32,
+        handle: u32,
         px: f32,
         py: f32,
         pz: f32,
@@ -624,95 +602,253 @@ impl Engine {
         ry: f32,
         rz: f32,
     ) {
-        if let Some(entity) = self.make_entity(index) {
-            self.transforms.insert(
+        if let Some(entity) = self.resolve(handle) {
+            self.world.insert(
                 entity,
                 Transform {
-                    position: [px, py, pz],
-                    rotation: [rx, ry, rz],
-                    scale: [0.5, 0.5, 0.5],
+                    position: Vec3::new(px, py, pz),
+                    rotation: Quat::from_euler(rx, ry, rz),
+                    scale: Vec3::splat(0.5),
                 },
             );
         }
     }
     pub fn move_selected_y(&mut self, delta: f32) {
-        for (entity_index, _) in self.selected.iter() {
-            let entity = Entity {
-                index: entity_index,
-                generation: self.generations[entity_index as usize],
-            };
-
-            if let Some(transform) = self.transforms.get_mut(entity) {
-                transform.position[1] += delta;
-            }
-        }
+        self.world
+            .query_filtered::<&mut Transform, With<Selected>>()
+            .for_each(|_, transform| transform.position.y += delta);
     }
-    pub fn add_velocity(&mut self, index: u32, vx: f32, vy: f32, vz: f32) {
-        if let Some(entity) = self.make_entity(index) {
-            self.velocities.insert(
+    pub fn add_velocity(&mut self, handle: u32, vx: f32, vy: f32, vz: f32) {
+        if let Some(entity) = self.resolve(handle) {
+            self.world.insert(
                 entity,
                 Velocity {
-                    linear: [vx, vy, vz],
+                    linear: Vec3::new(vx, vy, vz),
                 },
             );
         }
     }
 
     pub fn begin_drag(&mut self, ox: f32, oy: f32, oz: f32, dx: f32, dy: f32, dz: f32) {
-        let origin = [ox, oy, oz];
-        let dir = [dx, dy, dz];
-
-        if let Some(hit_point) = Self::ray_plane_intersection(origin, dir, 0.0) {
-            for (entity_index, _) in self.selected.iter() {
-                let entity = Entity {
-                    index: entity_index,
-                    generation: self.generations[entity_index as usize],
-                };
-                if self.static_objects.contains(entity) {
-                    continue;
-                }
-                if let Some(transform) = self.transforms.get_mut(entity) {
-                    let offset = [
-                        transform.position[0] - hit_point[0],
-                        transform.position[1] - hit_point[1],
-                        transform.position[2] - hit_point[2],
-                    ];
-
-                    self.dragging.insert(entity, offset);
-                }
+        self.begin_drag_ray(Ray {
+            origin: Vec3::new(ox, oy, oz),
+            dir: Vec3::new(dx, dy, dz),
+        });
+    }
+    /// `begin_drag` from a pixel position in the viewport.
+    pub fn begin_drag_at(&mut self, px: f32, py: f32) {
+        if let Some(ray) = self.screen_ray(px, py) {
+            self.begin_drag_ray(ray);
+        }
+    }
+    fn begin_drag_ray(&mut self, ray: Ray) {
+        if let Some(hit_point) = Self::ray_plane_intersection(ray.origin, ray.dir, 0.0) {
+            // Written straight into the storage: queued commands belong to
+            // the apply stage of `update`, not to input handlers.
+            let mut dragging = self.world.storage_mut::<Dragging>();
+            let mut count = 0;
+
+            self.world
+                .query_filtered::<&GlobalTransform, (With<Selected>, Without<Static>)>()
+                .for_each(|entity, global| {
+                    let offset = global.translation() - hit_point;
+
+                    dragging.insert(entity, Dragging { offset });
+                    count += 1;
+                });
+
+            drop(dragging);
+
+            if count > 0 {
+                Self::emit(&mut self.world, EngineEvent::DragStarted(count));
             }
         }
     }
     pub fn end_drag(&mut self) {
-        self.dragging.clear();
-        self.current_drag_ray = None;
+        let was_dragging = !self.world.storage::<Dragging>().is_empty();
+
+        self.world.storage_mut::<Dragging>().clear();
+
+        let mut drag = self.world.resource_mut::<DragState>();
+        drag.ray = None;
+        drag.contacts.clear();
+        drop(drag);
+
+        if was_dragging {
+            Self::emit(&mut self.world, EngineEvent::DragEnded);
+        }
     }
 
-    fn update_drag_system(&mut self) {
-        if let Some(ray) = self.current_drag_ray {
-            if let Some(hit_point) = Self::ray_plane_intersection(ray.origin, ray.dir, 0.0) {
-                for (entity_index, offset) in self.dragging.iter() {
-                    let entity = Entity {
-                        index: entity_index,
-                        generation: self.generations[entity_index as usize],
-                    };
+    /// Emits `Collision` when a dragged entity starts overlapping another
+    /// non-static entity.
+    fn detect_drag_collisions(world: &mut World) {
+        if world.storage::<Dragging>().is_empty() {
+            return;
+        }
 
-                    if let Some(transform) = self.transforms.get_mut(entity) {
-                        transform.position[0] = hit_point[0] + offset[0];
-                        transform.position[1] = hit_point[1] + offset[1];
-                        transform.position[2] = hit_point[2] + offset[2];
+        let mut dragged = Vec::new();
+
+        world
+            .query_filtered::<&WorldBounds, With<Dragging>>()
+            .for_each(|entity, bounds| dragged.push((entity, bounds.aabb)));
+
+        let mut contacts = Vec::new();
+
+        world
+            .query_filtered::<&WorldBounds, (Without<Dragging>, Without<Static>)>()
+            .for_each(|other, bounds| {
+                for &(entity, aabb) in &dragged {
+                    if aabb.overlaps(&bounds.aabb) {
+                        contacts.push((entity, other));
                     }
                 }
+            });
+
+        let previous = std::mem::replace(
+            &mut world.resource_mut::<DragState>().contacts,
+            contacts.clone(),
+        );
+
+        for (a, b) in contacts {
+            if !previous.contains(&(a, b)) {
+                Self::emit(world, EngineEvent::Collision(a, b));
             }
         }
     }
+
+    fn update_drag_system(world: &mut World) {
+        let ray = world.resource::<DragState>().ray;
+
+        if let Some(ray) = ray
+            && let Some(hit_point) = Self::ray_plane_intersection(ray.origin, ray.dir, 0.0)
+        {
+            let globals = world.storage::<GlobalTransform>();
+
+            world
+                .query::<(&mut Transform, &Dragging, Option<&Parent>)>()
+                .for_each(|_, (transform, drag, parent)| {
+                    let target = hit_point + drag.offset;
+
+                    // Children are moved in their parent's space.
+                    let parent_inv = parent
+                        .and_then(|p| globals.get(p.0))
+                        .and_then(|g| g.matrix.inverse());
+
+                    transform.position = match parent_inv {
+                        Some(inv) => inv.transform_point3(target),
+                        None => target,
+                    };
+                });
+        }
+    }
     pub fn update_drag_ray(&mut self, ox: f32, oy: f32, oz: f32, dx: f32, dy: f32, dz: f32) {
-        self.current_drag_ray = Some(DragRay {
-            origin: [ox, oy, oz],
-            dir: [dx, dy, dz],
+        self.world.resource_mut::<DragState>().ray = Some(Ray {
+            origin: Vec3::new(ox, oy, oz),
+            dir: Vec3::new(dx, dy, dz),
         });
     }
+    /// `update_drag_ray` from a pixel position in the viewport.
+    pub fn update_drag_at(&mut self, px: f32, py: f32) {
+        if let Some(ray) = self.screen_ray(px, py) {
+            self.world.resource_mut::<DragState>().ray = Some(ray);
+        }
+    }
+
+    /// Sets the canvas size used by the `*_at` entry points and keeps the
+    /// camera's aspect ratio in step with it.
+    pub fn set_viewport(&mut self, width: f32, height: f32) {
+        let viewport = Viewport { width, height };
+
+        if !viewport.is_empty()
+            && let Some(mut cam) = self.world.get_resource_mut::<Camera>()
+        {
+            cam.aspect = width / height;
+        }
+
+        self.world.insert_resource(viewport);
+    }
+    /// World-space ray through a pixel as `[ox, oy, oz, dx, dy, dz]`, or
+    /// `undefined` before a camera and viewport are set.
+    pub fn screen_to_ray(&self, px: f32, py: f32) -> Option<Vec<f32>> {
+        let ray = self.screen_ray(px, py)?;
+        let (o, d) = (ray.origin, ray.dir);
 
+        Some(vec![o.x, o.y, o.z, d.x, d.y, d.z])
+    }
+    fn screen_ray(&self, px: f32, py: f32) -> Option<Ray> {
+        let view_proj = self.live_view_proj()?;
+        let viewport = self.world.resource::<Viewport>();
+
+        Ray::from_screen(&view_proj, &viewport, px, py)
+    }
+    /// Built from the camera rather than `ViewProj`, so it is never a
+    /// frame behind orbit/zoom input.
+    fn live_view_proj(&self) -> Option<Mat4> {
+        self.world
+            .get_resource::<Camera>()
+            .map(|cam| cam.view_proj())
+    }
+
+    // ===== SCREEN PROJECTION =====
+
+    /// Projects the entity's world position into the viewport as
+    /// `[x, y, depth, state]` (see `ScreenState`). `undefined` for stale
+    /// handles, entities without a transform, or before a camera is set.
+    pub fn project_entity(&self, handle: u32) -> Option<Vec<f32>> {
+        let entity = self.resolve(handle)?;
+        self.world.get::<Transform>(entity)?;
+
+        let view_proj = self.live_view_proj()?;
+        let position = hierarchy::world_matrix(&self.world, entity).translation();
+        let viewport = self.world.resource::<Viewport>();
+
+        Some(
+            ScreenPoint::project(&view_proj, &viewport, position)
+                .to_array()
+                .to_vec(),
+        )
+    }
+    /// Projects every entity with a transform, as of the last `update`,
+    /// into the projection buffer: `[x, y, depth, state]` per entity at
+    /// `projection_ptr`, and the matching handles at
+    /// `projection_handles_ptr`. Returns the number of entities.
+    ///
+    /// Entities spawned since that update have no `GlobalTransform` yet
+    /// and are placed by walking their parents instead.
+    pub fn project_all(&mut self) -> usize {
+        let mut buffer = self.world.resource_mut::<ProjectionBuffer>();
+        buffer.data.clear();
+        buffer.entities.clear();
+
+        let Some(view_proj) = self.live_view_proj() else {
+            return 0;
+        };
+        let viewport = *self.world.resource::<Viewport>();
+        let ProjectionBuffer { data, entities } = &mut *buffer;
+
+        self.world
+            .query_filtered::<Option<&GlobalTransform>, With<Transform>>()
+            .for_each(|entity, global| {
+                let position = match global {
+                    Some(global) => global.translation(),
+                    None => hierarchy::world_matrix(&self.world, entity).translation(),
+                };
+                let point = ScreenPoint::project(&view_proj, &viewport, position);
+
+                data.extend_from_slice(&point.to_array());
+                entities.push(entity.to_bits());
+            });
+
+        entities.len()
+    }
+    pub fn projection_ptr(&self) -> *const f32 {
+        self.world.resource::<ProjectionBuffer>().data.as_ptr()
+    }
+    pub fn projection_handles_ptr(&self) -> *const u32 {
+        self.world.resource::<ProjectionBuffer>().entities.as_ptr()
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn set_camera(
         &mut self,
         px: f32,
@@ -724,22 +860,18 @@ impl Engine {
         far: f32,
     ) {
         // Default orbit target at origin
-        let target = [0.0, 0.0, 0.0];
+        let target = Vec3::ZERO;
 
         // Compute vector from target → camera
-        let dx = px - target[0];
-        let dy = py - target[1];
-        let dz = pz - target[2];
-
-        let distance = (dx * dx + dy * dy + dz * dz).sqrt();
+        let offset = Vec3::new(px, py, pz) - target;
 
         // Prevent divide-by-zero
-        let safe_distance = if distance < 0.0001 { 0.0001 } else { distance };
+        let safe_distance = offset.length().max(0.0001);
 
-        let pitch = (dy / safe_distance).asin();
-        let yaw = dx.atan2(dz);
+        let pitch = (offset.y / safe_distance).asin();
+        let yaw = offset.x.atan2(offset.z);
 
-        self.camera = Some(Camera {
+        self.world.insert_resource(Camera {
             target,
             yaw,
             pitch,
@@ -751,6 +883,7 @@ impl Engine {
         });
     }
     // ===== Ray Picking =====
+    #[allow(clippy::too_many_arguments)]
     pub fn pick(
         &mut self,
         ox: f32,
@@ -761,67 +894,593 @@ impl Engine {
         dz: f32,
         additive: bool,
         toggle: bool,
-    ) -> i32 {
-        let origin = [ox, oy, oz];
-        let dir = [dx, dy, dz];
+    ) -> Option<u32> {
+        let ray = Ray {
+            origin: Vec3::new(ox, oy, oz),
+            dir: Vec3::new(dx, dy, dz),
+        };
+
+        self.pick_ray(ray, additive, toggle)
+    }
+    /// `pick` from a pixel position in the viewport.
+    pub fn pick_at(&mut self, px: f32, py: f32, additive: bool, toggle: bool) -> Option<u32> {
+        let ray = self.screen_ray(px, py)?;
+
+        self.pick_ray(ray, additive, toggle)
+    }
+    fn pick_ray(&mut self, ray: Ray, additive: bool, toggle: bool) -> Option<u32> {
+        let Ray { origin, dir } = ray;
 
         let mut closest_entity: Option<Entity> = None;
         let mut closest_t = f32::MAX;
 
         // Iterate only entities with Transform
-        for (entity_index, transform) in self.transforms.iter() {
-            let entity = Entity {
-                index: entity_index,
-                generation: self.generations[entity_index as usize],
-            };
-
-            let inv_model = Self::invert_model(*transform);
+        self.world
+            .query_filtered::<(&GlobalTransform, Option<&Bounds>, Option<&WorldBounds>), Without<Hidden>>()
+            .for_each(|entity, (global, bounds, world_bounds)| {
+                // Cheap reject before inverting the model matrix.
+                if world_bounds.is_some_and(|b| b.aabb.ray_hit(origin, dir).is_none()) {
+                    return;
+                }
+                let Some(inv_model) = global.matrix.inverse() else {
+                    return;
+                };
 
-            let local_origin = Self::transform_point(inv_model, origin);
-            let local_dir = Self::transform_dir(inv_model, dir);
+                let local_origin = inv_model.transform_point3(origin);
+                let local_dir = inv_model.transform_vector3(dir);
+                let local_aabb = bounds.map_or(Aabb::UNIT_CUBE, |b| b.aabb);
 
-            if let Some(t_hit) = Self::ray_aabb(local_origin, local_dir) {
-                if t_hit < closest_t {
+                // Same parameter in both spaces, since the map is affine.
+                if let Some(t_hit) = local_aabb.ray_hit(local_origin, local_dir)
+                    && t_hit < closest_t
+                {
                     closest_t = t_hit;
                     closest_entity = Some(entity);
                 }
-            }
-        }
+            });
 
         if let Some(entity) = closest_entity {
-            let is_selected = self.selected.contains(entity);
+            let is_selected = self.world.has::<Selected>(entity);
+            let only_selected = is_selected && self.selected_count() == 1;
 
-            if toggle {
+            let changed = if toggle {
                 if is_selected {
-                    self.selected.remove_entity(entity);
+                    self.world.remove::<Selected>(entity);
                 } else {
-                    self.selected.insert(entity, ());
+                    self.world.insert(entity, Selected);
                 }
+                true
             } else if additive {
-                self.selected.insert(entity, ());
+                self.world.insert(entity, Selected);
+                !is_selected
             } else {
                 // Clear previous selection
-                self.selected.clear();
-                self.selected.insert(entity, ());
+                self.world.storage_mut::<Selected>().clear();
+                self.world.insert(entity, Selected);
+                !only_selected
+            };
+
+            if changed {
+                let count = self.selected_count() as u32;
+                Self::emit(&mut self.world, EngineEvent::SelectionChanged(count));
             }
 
-            return entity.index as i32;
+            return Some(entity.to_bits());
         }
 
-        -1
+        None
+    }
+
+    // ===== MESHES =====
+
+    /// Parses a binary glTF (`.glb`) file and spawns its scene under a
+    /// new hidden root entity, whose handle is returned. Move or scale the
+    /// root to place the model.
+    pub fn import_glb(&mut self, bytes: &[u8]) -> Result<u32, JsError> {
+        let scene = import::parse_glb(bytes)?;
+
+        Ok(import::spawn_gltf(&mut self.world, scene)?.to_bits())
+    }
+    /// Parses a Wavefront OBJ file into a new mesh asset and returns its
+    /// id, for `set_mesh`.
+    pub fn import_obj(&mut self, bytes: &[u8]) -> Result<u32, JsError> {
+        let mesh = import::parse_obj(bytes)?;
+
+        Ok(self.world.resource_mut::<Meshes>().add(mesh).0)
+    }
+
+    /// Number of mesh assets; ids run from 0. The first ones are the
+    /// `MeshShape`s.
+    pub fn mesh_count(&self) -> usize {
+        self.world.resource::<Meshes>().len()
+    }
+    /// Interleaved vertices of a mesh, `mesh::VERTEX_STRIDE` floats each
+    /// (position, normal, UV). Null for an unknown id.
+    pub fn mesh_vertices_ptr(&self, mesh: u32) -> *const f32 {
+        self.world
+            .resource::<Meshes>()
+            .get(Mesh(mesh))
+            .map_or(std::ptr::null(), |data| data.vertices.as_ptr())
+    }
+    /// Length of the mesh's vertex data in floats.
+    pub fn mesh_vertices_len(&self, mesh: u32) -> usize {
+        self.world
+            .resource::<Meshes>()
+            .get(Mesh(mesh))
+            .map_or(0, |data| data.vertices.len())
+    }
+    /// Triangle list indices of a mesh, one `u32` each.
+    pub fn mesh_indices_ptr(&self, mesh: u32) -> *const u32 {
+        self.world
+            .resource::<Meshes>()
+            .get(Mesh(mesh))
+            .map_or(std::ptr::null(), |data| data.indices.as_ptr())
+    }
+    pub fn mesh_indices_len(&self, mesh: u32) -> usize {
+        self.world
+            .resource::<Meshes>()
+            .get(Mesh(mesh))
+            .map_or(0, |data| data.indices.len())
+    }
+
+    // ===== LIGHTS =====
+
+    /// Makes the entity a sun-like light shining along its forward (-Z)
+    /// axis; aim it with `set_light_direction`.
+    pub fn add_directional_light(&mut self, handle: u32, r: f32, g: f32, b: f32, intensity: f32) {
+        if let Some(entity) = self.resolve(handle) {
+            self.world.insert(
+                entity,
+                DirectionalLight {
+                    color: [r, g, b],
+                    intensity,
+                },
+            );
+        }
+    }
+    /// Makes the entity a light shining in all directions, reaching
+    /// `range` units.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_point_light(
+        &mut self,
+        handle: u32,
+        r: f32,
+        g: f32,
+        b: f32,
+        intensity: f32,
+        range: f32,
+    ) {
+        if let Some(entity) = self.resolve(handle) {
+            self.world.insert(
+                entity,
+                PointLight {
+                    color: [r, g, b],
+                    intensity,
+                    range: range.max(0.0),
+                },
+            );
+        }
+    }
+    /// Makes the entity a cone of light along its forward (-Z) axis.
+    /// Angles are half-angles in radians.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_spot_light(
+        &mut self,
+        handle: u32,
+        r: f32,
+        g: f32,
+        b: f32,
+        intensity: f32,
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) {
+        if let Some(entity) = self.resolve(handle) {
+            self.world.insert(
+                entity,
+                SpotLight {
+                    color: [r, g, b],
+                    intensity,
+                    range: range.max(0.0),
+                    inner_angle,
+                    outer_angle,
+                },
+            );
+        }
+    }
+    /// Removes every light component from the entity.
+    pub fn remove_lights(&mut self, handle: u32) {
+        if let Some(entity) = self.resolve(handle) {
+            self.world.remove::<DirectionalLight>(entity);
+            self.world.remove::<PointLight>(entity);
+            self.world.remove::<SpotLight>(entity);
+        }
+    }
+    /// Turns the entity so its forward (-Z) axis, the way its light
+    /// shines, points along `dx, dy, dz` in its parent's space.
+    pub fn set_light_direction(&mut self, handle: u32, dx: f32, dy: f32, dz: f32) {
+        if let Some(entity) = self.resolve(handle)
+            && let Some(mut transform) = self.world.get_mut::<Transform>(entity)
+        {
+            transform.rotation = Quat::look_to(Vec3::new(dx, dy, dz), Vec3::Y);
+        }
+    }
+
+    /// The packed light buffer: a `LIGHT_HEADER` float header holding
+    /// the light count, then `LIGHT_STRIDE` floats per light (see
+    /// `light::LIGHT_STRIDE`). Moves when it grows; re-read every frame.
+    pub fn light_ptr(&self) -> *const f32 {
+        self.world.resource::<LightBuffer>().data().as_ptr()
+    }
+    /// Length of the light buffer in floats, header included.
+    pub fn light_len(&self) -> usize {
+        self.world.resource::<LightBuffer>().data().len()
+    }
+    pub fn light_count(&self) -> u32 {
+        self.world.resource::<LightBuffer>().count()
+    }
+    /// Whether the light buffer changed since the last call.
+    pub fn take_lights_dirty(&mut self) -> bool {
+        self.world.resource_mut::<LightBuffer>().take_dirty()
+    }
+
+    // ===== TIME OF DAY =====
+
+    /// Puts the entity on the sun's arc: each frame it is moved around
+    /// the origin and aimed at it, and its directional light (added if
+    /// missing) follows the sunlight color and intensity.
+    pub fn set_sun(&mut self, handle: u32) {
+        if let Some(entity) = self.resolve(handle) {
+            if self.world.get::<DirectionalLight>(entity).is_none() {
+                self.world.insert(
+                    entity,
+                    DirectionalLight {
+                        color: [1.0; 3],
+                        intensity: 0.0,
+                    },
+                );
+            }
+            self.world.resource_mut::<TimeOfDay>().sun = Some(entity);
+        }
+    }
+    /// Hour of the day in `0..24`; wraps out-of-range values.
+    pub fn set_time_of_day(&mut self, hour: f32) {
+        self.world.resource_mut::<TimeOfDay>().hour = hour.rem_euclid(24.0);
+    }
+    pub fn time_of_day(&self) -> f32 {
+        self.world.resource::<TimeOfDay>().hour
+    }
+    /// Hours advanced per second; 0 stops the clock.
+    pub fn set_day_speed(&mut self, hours_per_second: f32) {
+        self.world.resource_mut::<TimeOfDay>().speed = hours_per_second;
+    }
+    /// Sunlight direction and color, ambient level and sky colors for
+    /// the current hour, `sky::SKY_LEN` floats laid out as described
+    /// there.
+    pub fn sky(&self) -> Vec<f32> {
+        self.world.resource::<Sky>().to_array().to_vec()
     }
 
     // ===== RENDER EXTRACTION =====
 
-    pub fn render_buffer_ptr(&self) -> *const f32 {
-        self.render_buffer.as_ptr()
+    /// Version of the instance layout (`render::INSTANCE_LAYOUT_VERSION`)
+    /// the streams are written in.
+    pub fn instance_layout_version(&self) -> u32 {
+        render::INSTANCE_LAYOUT_VERSION
+    }
+    /// Floats per instance slot.
+    pub fn instance_stride(&self) -> usize {
+        render::INSTANCE_STRIDE
+    }
+    /// Start of the stream's instance slots, `INSTANCE_STRIDE` floats
+    /// each. Moves whenever the stream grows, so re-read it every frame.
+    pub fn instance_ptr(&self, stream: StreamKind) -> *const f32 {
+        self.world
+            .resource::<RenderInstances>()
+            .stream(stream)
+            .data()
+            .as_ptr()
+    }
+    /// Length of the stream's slot buffer in floats, free slots included.
+    pub fn instance_len(&self, stream: StreamKind) -> usize {
+        self.world
+            .resource::<RenderInstances>()
+            .stream(stream)
+            .data()
+            .len()
+    }
+    /// Byte ranges of the stream written since the last call, as flat
+    /// `[offset, length]` pairs ready for `writeBuffer`.
+    pub fn take_dirty_ranges(&mut self, stream: StreamKind) -> Vec<u32> {
+        self.world
+            .resource_mut::<RenderInstances>()
+            .stream_mut(stream)
+            .take_dirty_ranges()
+    }
+    /// Slot indices to draw from the stream, one `u32` each.
+    pub fn visible_ptr(&self, stream: StreamKind) -> *const u32 {
+        self.world
+            .resource::<RenderInstances>()
+            .stream(stream)
+            .visible()
+            .as_ptr()
+    }
+    pub fn visible_len(&self, stream: StreamKind) -> usize {
+        self.world
+            .resource::<RenderInstances>()
+            .stream(stream)
+            .visible()
+            .len()
+    }
+    /// Instanced draws as flat `[mesh, firstInstance, instanceCount]`
+    /// triples, indexing the stream's visible list: one per mesh for
+    /// the opaque range, then one per run of a mesh for the transparent
+    /// range.
+    pub fn batches(&self, stream: StreamKind) -> Vec<u32> {
+        self.world
+            .resource::<RenderInstances>()
+            .stream(stream)
+            .batches()
+            .iter()
+            .flat_map(|b| [b.mesh.0, b.offset, b.count])
+            .collect()
+    }
+    /// `[firstInstance, instanceCount]` of the stream's opaque instances
+    /// in its visible list. Batches starting inside it draw opaque.
+    pub fn opaque_range(&self, stream: StreamKind) -> Vec<u32> {
+        let range = self
+            .world
+            .resource::<RenderInstances>()
+            .stream(stream)
+            .opaque_range();
+        vec![range.start, range.len() as u32]
+    }
+    /// `[firstInstance, instanceCount]` of the stream's transparent
+    /// instances, sorted back to front; draw them after every opaque
+    /// batch of both streams, with blending. Only `Dynamic` has any.
+    pub fn transparent_range(&self, stream: StreamKind) -> Vec<u32> {
+        let range = self
+            .world
+            .resource::<RenderInstances>()
+            .stream(stream)
+            .transparent_range();
+        vec![range.start, range.len() as u32]
+    }
+    /// Whether the stream's visible list changed since the last call.
+    pub fn take_visible_dirty(&mut self, stream: StreamKind) -> bool {
+        self.world
+            .resource_mut::<RenderInstances>()
+            .stream_mut(stream)
+            .take_visible_dirty()
     }
 
-    pub fn render_buffer_len(&self) -> usize {
-        self.render_buffer.len()
+    /// Instances drawn, across both streams, after frustum culling.
+    pub fn visible_count(&self) -> u32 {
+        self.world.resource::<RenderInstances>().visible
+    }
+    /// Instances skipped by frustum culling.
+    pub fn culled_count(&self) -> u32 {
+        self.world.resource::<RenderInstances>().culled
     }
 
     pub fn view_proj_ptr(&self) -> *const f32 {
-        self.view_proj.as_ptr()
+        self.world.resource::<ViewProj>().matrix.0.as_ptr()
+    }
+    /// World position of the camera eye, as of the last update.
+    pub fn camera_eye(&self) -> Vec<f32> {
+        self.world.resource::<ViewProj>().eye.to_array().to_vec()
+    }
+}
+
+/// Extension points for Rust code built on the engine. Generic, so not
+/// exported to JS.
+impl Engine {
+    /// Registers `T` so queries and despawns see its storage before the
+    /// first insert. Inserting a component registers it too.
+    pub fn register_component<T: Component>(&mut self) {
+        self.world.register::<T>();
+    }
+    /// Adds or replaces a resource, returning the old value. Systems
+    /// still declare it with `reads`/`writes` to use it.
+    pub fn insert_resource<T: Component>(&mut self, value: T) -> Option<T> {
+        self.world.insert_resource(value)
+    }
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+    /// Adds a system next to the built-in ones, which it can be ordered
+    /// against by name (see `system_names`). It runs from the next
+    /// `update`.
+    pub fn add_system(
+        &mut self,
+        name: &str,
+        stage: Stage,
+        run: impl FnMut(&mut World) + 'static,
+    ) -> SystemConfig<'_> {
+        self.schedule.add_system(name, stage, run)
+    }
+    /// Checks the order of every system added so far. `update` falls
+    /// back to registration order on an invalid schedule instead.
+    pub fn rebuild_schedule(&mut self) -> Result<(), ScheduleError> {
+        self.schedule.rebuild()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use events::{EVENT_STRIDE, EventKind};
+
+    /// A half-size cube at `x` on the ground plane.
+    fn cube(engine: &mut Engine, x: f32) -> u32 {
+        let handle = engine.create_entity().unwrap();
+        engine.add_transform(handle, x, 0.0, 0.0, 0.0, 0.0, 0.0);
+        handle
+    }
+
+    /// Picks straight down onto `x`.
+    fn pick(engine: &mut Engine, x: f32, additive: bool, toggle: bool) -> Option<u32> {
+        engine.pick(x, 10.0, 0.0, 0.0, -1.0, 0.0, additive, toggle)
+    }
+
+    /// Counts carried by the `SelectionChanged` events drained so far.
+    fn selection_events(engine: &mut Engine) -> Vec<u32> {
+        engine
+            .drain_events()
+            .chunks(EVENT_STRIDE)
+            .filter(|event| event[0] == EventKind::SelectionChanged as u32)
+            .map(|event| event[1])
+            .collect()
+    }
+
+    #[test]
+    fn picking_reports_only_real_selection_changes() {
+        let mut engine = Engine::new();
+        let a = cube(&mut engine, 0.0);
+        let b = cube(&mut engine, 3.0);
+        engine.update(0.0);
+        engine.drain_events();
+
+        assert_eq!(pick(&mut engine, 0.0, false, false), Some(a));
+        assert_eq!(selection_events(&mut engine), [1]);
+
+        // Already the whole selection.
+        pick(&mut engine, 0.0, false, false);
+        assert_eq!(selection_events(&mut engine), []);
+
+        assert_eq!(pick(&mut engine, 3.0, true, false), Some(b));
+        pick(&mut engine, 3.0, true, false);
+        assert_eq!(selection_events(&mut engine), [2]);
+
+        pick(&mut engine, 3.0, false, true);
+        assert_eq!(selection_events(&mut engine), [1]);
+
+        // A miss leaves the selection alone.
+        assert_eq!(pick(&mut engine, 20.0, false, false), None);
+        pick(&mut engine, 0.0, false, false);
+        assert_eq!(selection_events(&mut engine), []);
+        assert_eq!(engine.selected_count(), 1);
+    }
+
+    #[test]
+    fn destroying_selected_entities_reports_the_new_selection() {
+        let mut engine = Engine::new();
+        let parent = cube(&mut engine, 0.0);
+        let child = cube(&mut engine, 3.0);
+        let other = cube(&mut engine, 6.0);
+        assert!(engine.set_parent(child, parent));
+        engine.update(0.0);
+
+        pick(&mut engine, 3.0, false, false);
+        pick(&mut engine, 6.0, true, false);
+        engine.drain_events();
+
+        // Only the child was selected; it goes with its parent.
+        engine.destroy_entity(parent);
+        let events = engine.drain_events();
+        let kinds: Vec<u32> = events.chunks(EVENT_STRIDE).map(|e| e[0]).collect();
+
+        assert_eq!(
+            kinds,
+            [
+                EventKind::EntityDespawned as u32,
+                EventKind::EntityDespawned as u32,
+                EventKind::SelectionChanged as u32
+            ]
+        );
+        assert_eq!(events[events.len() - 2], 1);
+
+        let unselected = cube(&mut engine, 9.0);
+        engine.destroy_entity(unselected);
+        assert_eq!(selection_events(&mut engine), []);
+
+        engine.destroy_entity(other);
+        assert_eq!(selection_events(&mut engine), [0]);
+    }
+
+    #[test]
+    fn components_can_be_registered_from_outside() {
+        struct Health(u32);
+
+        let mut engine = Engine::new();
+        engine.register_component::<Health>();
+        assert_eq!(engine.world().query::<&Health>().count(), 0);
+
+        let handle = engine.create_entity().unwrap();
+        let entity = Entity::from_bits(handle);
+        engine.world_mut().insert(entity, Health(3));
+        assert_eq!(engine.world().get::<Health>(entity).map(|h| h.0), Some(3));
+
+        engine.destroy_entity(handle);
+        let reused = engine.create_entity().unwrap();
+
+        assert_eq!(Entity::from_bits(reused).index(), entity.index());
+        assert_eq!(engine.world().query::<&Health>().count(), 0);
+    }
+
+    #[test]
+    fn systems_can_be_added_from_outside() {
+        struct Frames(u32);
+
+        let mut engine = Engine::new();
+        engine.insert_resource(Frames(0));
+        engine
+            .add_system("count_frames", Stage::Simulation, |world| {
+                world.resource_mut::<Frames>().0 += 1;
+            })
+            .writes::<Frames>()
+            .after("integrate_velocity")
+            .run_if(|world| !world.resource::<Paused>().0);
+
+        assert_eq!(engine.rebuild_schedule(), Ok(()));
+        assert!(
+            engine
+                .system_names()
+                .contains(&"simulation/count_frames".to_string())
+        );
+
+        engine.update(0.1);
+        engine.set_paused(true);
+        engine.update(0.1);
+        engine.set_paused(false);
+        assert!(engine.set_system_enabled("count_frames", false));
+        engine.update(0.1);
+
+        assert_eq!(engine.world().resource::<Frames>().0, 1);
+
+        engine
+            .add_system("count_frames", Stage::Input, |_| {})
+            .after("drag");
+        assert_eq!(
+            engine.rebuild_schedule(),
+            Err(ScheduleError::DuplicateName("count_frames".into()))
+        );
+    }
+
+    #[test]
+    fn resources_can_be_inserted_from_outside() {
+        #[derive(Debug, PartialEq)]
+        struct Gravity(f32);
+        struct Fallen(u32);
+
+        let mut engine = Engine::new();
+        assert_eq!(engine.insert_resource(Gravity(-9.8)), None);
+        assert_eq!(engine.insert_resource(Gravity(-1.6)), Some(Gravity(-9.8)));
+        engine.insert_resource(Fallen(0));
+
+        engine
+            .add_system("fall", Stage::Simulation, |world| {
+                if world.resource::<Gravity>().0 < 0.0 {
+                    world.resource_mut::<Fallen>().0 += 1;
+                }
+            })
+            .reads::<Gravity>()
+            .writes::<Fallen>();
+        engine.update(0.1);
+
+        assert_eq!(engine.world().resource::<Fallen>().0, 1);
     }
 }
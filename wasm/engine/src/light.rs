@@ -0,0 +1,344 @@
+use wasm_bindgen::prelude::*;
+
+use crate::hierarchy::GlobalTransform;
+use crate::math::{Mat4, Vec3};
+use crate::world::World;
+
+/// Light shining along its entity's forward (-Z) axis from infinitely
+/// far away, like the sun. Position is ignored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirectionalLight {
+    pub color: [f32; 3],
+    pub intensity: f32,
+}
+
+/// Light shining in all directions from its entity's position, fading to
+/// nothing at `range`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointLight {
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: f32,
+}
+
+/// Point light limited to a cone around its entity's forward (-Z) axis.
+/// Full strength inside `inner_angle`, fading out to `outer_angle`; both
+/// are half-angles in radians.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpotLight {
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: f32,
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+}
+
+/// Light type, stored in each record of the light buffer.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LightKind {
+    Directional = 0,
+    Point = 1,
+    Spot = 2,
+}
+
+/// Floats before the first light record: the light count as a `u32`,
+/// then padding to 16 bytes.
+pub const LIGHT_HEADER: usize = 4;
+
+/// Floats per light record, laid out for a WGSL struct of 16-byte
+/// aligned fields:
+///
+/// | floats | field |
+/// |--------|-------|
+/// | 0..3   | world position |
+/// | 3      | `LightKind`, stored as a `u32` |
+/// | 4..7   | world direction the light travels in, unit length |
+/// | 7      | range |
+/// | 8..11  | color RGB |
+/// | 11     | intensity |
+/// | 12     | cosine of the inner cone angle |
+/// | 13     | cosine of the outer cone angle |
+/// | 14..16 | padding |
+pub const LIGHT_STRIDE: usize = 16;
+
+/// Every light in the scene, packed by [`extract_lights`]: the header,
+/// then directional, point and spot lights in that order.
+pub struct LightBuffer {
+    data: Vec<f32>,
+    count: u32,
+    dirty: bool,
+}
+
+impl Default for LightBuffer {
+    fn default() -> Self {
+        LightBuffer {
+            data: vec![0.0; LIGHT_HEADER],
+            count: 0,
+            dirty: true,
+        }
+    }
+}
+
+impl LightBuffer {
+    pub fn data(&self) -> &[f32] {
+        &self.data
+    }
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+    /// Whether `data` changed since the last call.
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+}
+
+struct Record {
+    kind: LightKind,
+    color: [f32; 3],
+    intensity: f32,
+    range: f32,
+    cos_inner: f32,
+    cos_outer: f32,
+}
+
+fn push_record(data: &mut Vec<f32>, matrix: &Mat4, light: Record) {
+    let position = matrix.translation();
+    let direction = matrix.transform_vector3(-Vec3::Z).normalize();
+    let [r, g, b] = light.color;
+
+    data.extend_from_slice(&[
+        position.x,
+        position.y,
+        position.z,
+        f32::from_bits(light.kind as u32),
+        direction.x,
+        direction.y,
+        direction.z,
+        light.range,
+        r,
+        g,
+        b,
+        light.intensity,
+        light.cos_inner,
+        light.cos_outer,
+        0.0,
+        0.0,
+    ]);
+}
+
+/// Packs every light with a `GlobalTransform` into the `LightBuffer`.
+/// Lights are few, so the buffer is rebuilt each frame and only marked
+/// dirty when its contents differ.
+pub fn extract_lights(world: &mut World) {
+    let mut data = vec![0.0; LIGHT_HEADER];
+
+    world
+        .query::<(&GlobalTransform, &DirectionalLight)>()
+        .for_each(|_, (global, light)| {
+            push_record(
+                &mut data,
+                &global.matrix,
+                Record {
+                    kind: LightKind::Directional,
+                    color: light.color,
+                    intensity: light.intensity,
+                    range: 0.0,
+                    cos_inner: 1.0,
+                    cos_outer: 1.0,
+                },
+            );
+        });
+    world
+        .query::<(&GlobalTransform, &PointLight)>()
+        .for_each(|_, (global, light)| {
+            push_record(
+                &mut data,
+                &global.matrix,
+                Record {
+                    kind: LightKind::Point,
+                    color: light.color,
+                    intensity: light.intensity,
+                    range: light.range,
+                    cos_inner: -1.0,
+                    cos_outer: -1.0,
+                },
+            );
+        });
+    world
+        .query::<(&GlobalTransform, &SpotLight)>()
+        .for_each(|_, (global, light)| {
+            let outer = light.outer_angle.clamp(0.0, std::f32::consts::PI);
+            let inner = light.inner_angle.clamp(0.0, outer);
+
+            push_record(
+                &mut data,
+                &global.matrix,
+                Record {
+                    kind: LightKind::Spot,
+                    color: light.color,
+                    intensity: light.intensity,
+                    range: light.range,
+                    // Kept apart so the fade between them is defined.
+                    cos_inner: inner.cos().max(outer.cos() + 1e-4),
+                    cos_outer: outer.cos(),
+                },
+            );
+        });
+
+    let count = ((data.len() - LIGHT_HEADER) / LIGHT_STRIDE) as u32;
+    data[0] = f32::from_bits(count);
+
+    let mut buffer = world.resource_mut::<LightBuffer>();
+
+    if buffer.data != data {
+        buffer.data = data;
+        buffer.count = count;
+        buffer.dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Quat;
+
+    const EPS: f32 = 1e-5;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register::<GlobalTransform>();
+        world.register::<DirectionalLight>();
+        world.register::<PointLight>();
+        world.register::<SpotLight>();
+        world.insert_resource(LightBuffer::default());
+        world
+    }
+
+    fn spawn_light<T: crate::world::Component>(world: &mut World, matrix: Mat4, light: T) {
+        let entity = world.spawn().unwrap();
+        world.insert(entity, GlobalTransform { matrix });
+        world.insert(entity, light);
+    }
+
+    fn record(buffer: &LightBuffer, i: usize) -> &[f32] {
+        &buffer.data()[LIGHT_HEADER + i * LIGHT_STRIDE..][..LIGHT_STRIDE]
+    }
+
+    fn assert_floats(a: &[f32], b: &[f32]) {
+        let close = a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).abs() < EPS);
+        assert!(close, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn records_are_packed_by_kind() {
+        let mut world = world();
+        let quarter = std::f32::consts::FRAC_PI_2;
+
+        spawn_light(
+            &mut world,
+            Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0))
+                * Mat4::from_quat(Quat::from_rotation_x(-quarter)),
+            SpotLight {
+                color: [1.0, 0.5, 0.25],
+                intensity: 4.0,
+                range: 8.0,
+                inner_angle: 0.5,
+                outer_angle: 1.0,
+            },
+        );
+        spawn_light(
+            &mut world,
+            Mat4::from_translation(Vec3::new(-1.0, 0.0, 0.0)),
+            PointLight {
+                color: [0.0, 1.0, 0.0],
+                intensity: 2.0,
+                range: 5.0,
+            },
+        );
+        spawn_light(
+            &mut world,
+            Mat4::from_quat(Quat::from_rotation_y(quarter)),
+            DirectionalLight {
+                color: [1.0, 1.0, 1.0],
+                intensity: 3.0,
+            },
+        );
+
+        extract_lights(&mut world);
+        let buffer = world.resource::<LightBuffer>();
+
+        assert_eq!(buffer.count(), 3);
+        assert_eq!(buffer.data()[0].to_bits(), 3);
+        assert_eq!(buffer.data().len(), LIGHT_HEADER + 3 * LIGHT_STRIDE);
+
+        // Directional first, whatever order the lights were spawned in.
+        let sun = record(&buffer, 0);
+        assert_eq!(sun[3].to_bits(), LightKind::Directional as u32);
+        assert_floats(&sun[4..8], &[-1.0, 0.0, 0.0, 0.0]);
+        assert_floats(&sun[8..], &[1.0, 1.0, 1.0, 3.0, 1.0, 1.0, 0.0, 0.0]);
+
+        let point = record(&buffer, 1);
+        assert_eq!(point[3].to_bits(), LightKind::Point as u32);
+        assert_floats(&point[..3], &[-1.0, 0.0, 0.0]);
+        assert_floats(
+            &point[7..],
+            &[5.0, 0.0, 1.0, 0.0, 2.0, -1.0, -1.0, 0.0, 0.0],
+        );
+
+        let spot = record(&buffer, 2);
+        assert_eq!(spot[3].to_bits(), LightKind::Spot as u32);
+        assert_floats(&spot[..3], &[1.0, 2.0, 3.0]);
+        assert_floats(&spot[4..8], &[0.0, -1.0, 0.0, 8.0]);
+        assert_floats(&spot[8..12], &[1.0, 0.5, 0.25, 4.0]);
+        assert_floats(&spot[12..], &[0.5f32.cos(), 1.0f32.cos(), 0.0, 0.0]);
+    }
+
+    #[test]
+    fn spot_cones_are_clamped_apart() {
+        let mut world = world();
+        spawn_light(
+            &mut world,
+            Mat4::IDENTITY,
+            SpotLight {
+                color: [1.0; 3],
+                intensity: 1.0,
+                range: 1.0,
+                inner_angle: 2.0,
+                outer_angle: 1.0,
+            },
+        );
+
+        extract_lights(&mut world);
+        let buffer = world.resource::<LightBuffer>();
+        let spot = record(&buffer, 0);
+
+        assert!(spot[12] > spot[13]);
+        assert!((spot[13] - 1.0f32.cos()).abs() < EPS);
+    }
+
+    #[test]
+    fn unchanged_lights_do_not_mark_the_buffer_dirty() {
+        let mut world = world();
+        assert!(world.resource_mut::<LightBuffer>().take_dirty());
+
+        extract_lights(&mut world);
+        assert!(!world.resource_mut::<LightBuffer>().take_dirty());
+
+        spawn_light(
+            &mut world,
+            Mat4::IDENTITY,
+            PointLight {
+                color: [1.0; 3],
+                intensity: 1.0,
+                range: 1.0,
+            },
+        );
+        extract_lights(&mut world);
+        assert!(world.resource_mut::<LightBuffer>().take_dirty());
+        assert_eq!(world.resource::<LightBuffer>().count(), 1);
+
+        extract_lights(&mut world);
+        assert!(!world.resource_mut::<LightBuffer>().take_dirty());
+    }
+}
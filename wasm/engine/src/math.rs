@@ -0,0 +1,793 @@
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Right-handed, y-up. Matrices are column-major, the same layout the GPU
+/// and gl-matrix use.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vec3 {
+    pub const ZERO: Vec3 = Vec3::splat(0.0);
+    pub const ONE: Vec3 = Vec3::splat(1.0);
+    pub const X: Vec3 = Vec3::new(1.0, 0.0, 0.0);
+    pub const Y: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    pub const Z: Vec3 = Vec3::new(0.0, 0.0, 1.0);
+
+    pub const fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+    pub const fn splat(v: f32) -> Self {
+        Self::new(v, v, v)
+    }
+    pub const fn to_array(self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
+    pub const fn extend(self, w: f32) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, w)
+    }
+    pub fn dot(self, rhs: Vec3) -> f32 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+    pub fn cross(self, rhs: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * rhs.z - self.z * rhs.y,
+            self.z * rhs.x - self.x * rhs.z,
+            self.x * rhs.y - self.y * rhs.x,
+        )
+    }
+    pub fn length_squared(self) -> f32 {
+        self.dot(self)
+    }
+    pub fn length(self) -> f32 {
+        self.length_squared().sqrt()
+    }
+    /// Unit vector in the same direction. Zero stays zero.
+    pub fn normalize(self) -> Vec3 {
+        let len = self.length();
+        if len > 0.0 { self / len } else { self }
+    }
+    pub fn abs(self) -> Vec3 {
+        Vec3::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+    pub fn min(self, rhs: Vec3) -> Vec3 {
+        Vec3::new(self.x.min(rhs.x), self.y.min(rhs.y), self.z.min(rhs.z))
+    }
+    pub fn max(self, rhs: Vec3) -> Vec3 {
+        Vec3::new(self.x.max(rhs.x), self.y.max(rhs.y), self.z.max(rhs.z))
+    }
+    pub fn lerp(self, rhs: Vec3, t: f32) -> Vec3 {
+        self + (rhs - self) * t
+    }
+}
+
+impl From<[f32; 3]> for Vec3 {
+    fn from([x, y, z]: [f32; 3]) -> Self {
+        Vec3::new(x, y, z)
+    }
+}
+
+impl From<Vec3> for [f32; 3] {
+    fn from(v: Vec3) -> Self {
+        v.to_array()
+    }
+}
+
+impl Index<usize> for Vec3 {
+    type Output = f32;
+
+    fn index(&self, i: usize) -> &f32 {
+        match i {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 index {i} out of range"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct Vec4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Vec4 {
+    pub const ZERO: Vec4 = Vec4::new(0.0, 0.0, 0.0, 0.0);
+
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+    pub const fn to_array(self) -> [f32; 4] {
+        [self.x, self.y, self.z, self.w]
+    }
+    /// Drops `w` without dividing by it.
+    pub const fn truncate(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+    pub fn dot(self, rhs: Vec4) -> f32 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z + self.w * rhs.w
+    }
+}
+
+impl From<[f32; 4]> for Vec4 {
+    fn from([x, y, z, w]: [f32; 4]) -> Self {
+        Vec4::new(x, y, z, w)
+    }
+}
+
+// Component-wise operators for both vector types.
+macro_rules! impl_vec_ops {
+    ($ty:ident { $($f:ident),+ }) => {
+        impl Add for $ty {
+            type Output = $ty;
+            fn add(self, rhs: $ty) -> $ty {
+                $ty { $($f: self.$f + rhs.$f),+ }
+            }
+        }
+        impl Sub for $ty {
+            type Output = $ty;
+            fn sub(self, rhs: $ty) -> $ty {
+                $ty { $($f: self.$f - rhs.$f),+ }
+            }
+        }
+        impl Mul for $ty {
+            type Output = $ty;
+            fn mul(self, rhs: $ty) -> $ty {
+                $ty { $($f: self.$f * rhs.$f),+ }
+            }
+        }
+        impl Mul<f32> for $ty {
+            type Output = $ty;
+            fn mul(self, rhs: f32) -> $ty {
+                $ty { $($f: self.$f * rhs),+ }
+            }
+        }
+        impl Mul<$ty> for f32 {
+            type Output = $ty;
+            fn mul(self, rhs: $ty) -> $ty {
+                rhs * self
+            }
+        }
+        impl Div<f32> for $ty {
+            type Output = $ty;
+            fn div(self, rhs: f32) -> $ty {
+                $ty { $($f: self.$f / rhs),+ }
+            }
+        }
+        impl Neg for $ty {
+            type Output = $ty;
+            fn neg(self) -> $ty {
+                $ty { $($f: -self.$f),+ }
+            }
+        }
+        impl AddAssign for $ty {
+            fn add_assign(&mut self, rhs: $ty) {
+                *self = *self + rhs;
+            }
+        }
+        impl SubAssign for $ty {
+            fn sub_assign(&mut self, rhs: $ty) {
+                *self = *self - rhs;
+            }
+        }
+        impl MulAssign<f32> for $ty {
+            fn mul_assign(&mut self, rhs: f32) {
+                *self = *self * rhs;
+            }
+        }
+        impl DivAssign<f32> for $ty {
+            fn div_assign(&mut self, rhs: f32) {
+                *self = *self / rhs;
+            }
+        }
+    };
+}
+
+impl_vec_ops!(Vec3 { x, y, z });
+impl_vec_ops!(Vec4 { x, y, z, w });
+
+/// Unit quaternion rotation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Quat {
+    pub const IDENTITY: Quat = Quat::from_xyzw(0.0, 0.0, 0.0, 1.0);
+
+    pub const fn from_xyzw(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+    /// Counter-clockwise rotation of `angle` radians about `axis`, looking
+    /// down the axis towards the origin.
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let (s, c) = (angle * 0.5).sin_cos();
+        let axis = axis.normalize() * s;
+
+        Self::from_xyzw(axis.x, axis.y, axis.z, c)
+    }
+    pub fn from_rotation_x(angle: f32) -> Self {
+        Self::from_axis_angle(Vec3::X, angle)
+    }
+    pub fn from_rotation_y(angle: f32) -> Self {
+        Self::from_axis_angle(Vec3::Y, angle)
+    }
+    pub fn from_rotation_z(angle: f32) -> Self {
+        Self::from_axis_angle(Vec3::Z, angle)
+    }
+    /// Intrinsic Y-X-Z Euler angles in radians: yaw about Y, then pitch
+    /// about the rotated X, then roll about the rotated Z.
+    pub fn from_euler(x: f32, y: f32, z: f32) -> Self {
+        Self::from_rotation_y(y) * Self::from_rotation_x(x) * Self::from_rotation_z(z)
+    }
+    /// Inverse of [`Quat::from_euler`], as `(x, y, z)`. At ±90° pitch the
+    /// roll is folded into the yaw.
+    pub fn to_euler(self) -> (f32, f32, f32) {
+        let q = self.normalize();
+        let (xx, yy, zz) = (q.x * q.x, q.y * q.y, q.z * q.z);
+
+        let m13 = 2.0 * (q.x * q.z + q.w * q.y);
+        let m23 = 2.0 * (q.y * q.z - q.w * q.x);
+        let m33 = 1.0 - 2.0 * (xx + yy);
+
+        // atan2 rather than asin(-m23), which loses precision near ±90°.
+        let cos_x = m13.hypot(m33);
+        let x = (-m23).atan2(cos_x);
+
+        if cos_x > 1e-4 {
+            let m21 = 2.0 * (q.x * q.y + q.w * q.z);
+            let m22 = 1.0 - 2.0 * (xx + zz);
+
+            (x, m13.atan2(m33), m21.atan2(m22))
+        } else {
+            let m31 = 2.0 * (q.x * q.z - q.w * q.y);
+            let m11 = 1.0 - 2.0 * (yy + zz);
+
+            (x, (-m31).atan2(m11), 0.0)
+        }
+    }
+    /// Rotation taking the standard basis onto the orthonormal axes `x`,
+    /// `y`, `z`.
+    pub fn from_axes(x: Vec3, y: Vec3, z: Vec3) -> Self {
+        let trace = x.x + y.y + z.z;
+
+        let q = if trace > 0.0 {
+            let s = 0.5 / (trace + 1.0).sqrt();
+            Self::from_xyzw((y.z - z.y) * s, (z.x - x.z) * s, (x.y - y.x) * s, 0.25 / s)
+        } else if x.x > y.y && x.x > z.z {
+            let s = 2.0 * (1.0 + x.x - y.y - z.z).sqrt();
+            Self::from_xyzw(0.25 * s, (y.x + x.y) / s, (z.x + x.z) / s, (y.z - z.y) / s)
+        } else if y.y > z.z {
+            let s = 2.0 * (1.0 + y.y - x.x - z.z).sqrt();
+            Self::from_xyzw((y.x + x.y) / s, 0.25 * s, (z.y + y.z) / s, (z.x - x.z) / s)
+        } else {
+            let s = 2.0 * (1.0 + z.z - x.x - y.y).sqrt();
+            Self::from_xyzw((z.x + x.z) / s, (z.y + y.z) / s, 0.25 * s, (x.y - y.x) / s)
+        };
+
+        q.normalize()
+    }
+    /// Rotation turning -Z (the forward of cameras and lights) to `dir`,
+    /// keeping +Y as close to `up` as it can. Falls back to another up
+    /// when `dir` is parallel to `up`.
+    pub fn look_to(dir: Vec3, up: Vec3) -> Self {
+        let z = -dir.normalize();
+        let mut x = up.cross(z);
+
+        if x.length_squared() < 1e-12 {
+            x = if z.x.abs() < 0.9 { Vec3::X } else { Vec3::Y }.cross(z);
+        }
+
+        let x = x.normalize();
+        Self::from_axes(x, z.cross(x), z)
+    }
+    pub fn dot(self, rhs: Quat) -> f32 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z + self.w * rhs.w
+    }
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+    pub fn normalize(self) -> Quat {
+        let len = self.length();
+        if len > 0.0 {
+            Self::from_xyzw(self.x / len, self.y / len, self.z / len, self.w / len)
+        } else {
+            Self::IDENTITY
+        }
+    }
+    /// Inverse of a unit quaternion.
+    pub fn conjugate(self) -> Quat {
+        Self::from_xyzw(-self.x, -self.y, -self.z, self.w)
+    }
+    fn xyz(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+}
+
+/// `a * b` applies `b` first, then `a`.
+impl Mul for Quat {
+    type Output = Quat;
+
+    fn mul(self, rhs: Quat) -> Quat {
+        let (a, b) = (self, rhs);
+
+        Quat::from_xyzw(
+            a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+            a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+        )
+    }
+}
+
+/// Rotates a vector.
+impl Mul<Vec3> for Quat {
+    type Output = Vec3;
+
+    fn mul(self, v: Vec3) -> Vec3 {
+        let q = self.xyz();
+        let t = q.cross(v) * 2.0;
+
+        v + t * self.w + q.cross(t)
+    }
+}
+
+/// 4x4 matrix, column-major: element (row, col) is at `col * 4 + row`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(transparent)]
+pub struct Mat4(pub [f32; 16]);
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4([
+        1.0, 0.0, 0.0, 0.0, //
+        0.0, 1.0, 0.0, 0.0, //
+        0.0, 0.0, 1.0, 0.0, //
+        0.0, 0.0, 0.0, 1.0,
+    ]);
+
+    pub fn from_cols(x: Vec4, y: Vec4, z: Vec4, w: Vec4) -> Self {
+        let [x, y, z, w] = [x, y, z, w].map(Vec4::to_array);
+
+        Mat4([
+            x[0], x[1], x[2], x[3], //
+            y[0], y[1], y[2], y[3], //
+            z[0], z[1], z[2], z[3], //
+            w[0], w[1], w[2], w[3],
+        ])
+    }
+    pub fn from_translation(t: Vec3) -> Self {
+        Self::from_scale_rotation_translation(Vec3::ONE, Quat::IDENTITY, t)
+    }
+    pub fn from_scale(s: Vec3) -> Self {
+        Self::from_scale_rotation_translation(s, Quat::IDENTITY, Vec3::ZERO)
+    }
+    pub fn from_quat(q: Quat) -> Self {
+        Self::from_scale_rotation_translation(Vec3::ONE, q, Vec3::ZERO)
+    }
+    /// `translation * rotation * scale`: scales first, translates last.
+    pub fn from_scale_rotation_translation(scale: Vec3, q: Quat, translation: Vec3) -> Self {
+        let (x2, y2, z2) = (q.x + q.x, q.y + q.y, q.z + q.z);
+        let (xx, xy, xz) = (q.x * x2, q.x * y2, q.x * z2);
+        let (yy, yz, zz) = (q.y * y2, q.y * z2, q.z * z2);
+        let (wx, wy, wz) = (q.w * x2, q.w * y2, q.w * z2);
+
+        let x = Vec3::new(1.0 - (yy + zz), xy + wz, xz - wy) * scale.x;
+        let y = Vec3::new(xy - wz, 1.0 - (xx + zz), yz + wx) * scale.y;
+        let z = Vec3::new(xz + wy, yz - wx, 1.0 - (xx + yy)) * scale.z;
+
+        Self::from_cols(
+            x.extend(0.0),
+            y.extend(0.0),
+            z.extend(0.0),
+            translation.extend(1.0),
+        )
+    }
+    /// View matrix for an eye at `eye` looking at `target`.
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        let f = (target - eye).normalize();
+        let s = f.cross(up).normalize();
+        let u = s.cross(f);
+
+        Self::from_cols(
+            Vec4::new(s.x, u.x, -f.x, 0.0),
+            Vec4::new(s.y, u.y, -f.y, 0.0),
+            Vec4::new(s.z, u.z, -f.z, 0.0),
+            Vec4::new(-s.dot(eye), -u.dot(eye), f.dot(eye), 1.0),
+        )
+    }
+    /// Perspective projection with a vertical field of view in radians.
+    /// Clip-space depth runs from -1 (near) to 1 (far).
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let f = 1.0 / (fov_y / 2.0).tan();
+        let nf = 1.0 / (near - far);
+
+        Self::from_cols(
+            Vec4::new(f / aspect, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, f, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, (far + near) * nf, -1.0),
+            Vec4::new(0.0, 0.0, 2.0 * far * near * nf, 0.0),
+        )
+    }
+    /// Orthographic projection of the given view box. Same depth range as
+    /// [`Mat4::perspective`].
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        let lr = 1.0 / (left - right);
+        let bt = 1.0 / (bottom - top);
+        let nf = 1.0 / (near - far);
+
+        Self::from_cols(
+            Vec4::new(-2.0 * lr, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, -2.0 * bt, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, 2.0 * nf, 0.0),
+            Vec4::new(
+                (left + right) * lr,
+                (top + bottom) * bt,
+                (far + near) * nf,
+                1.0,
+            ),
+        )
+    }
+
+    pub fn col(&self, i: usize) -> Vec4 {
+        let m = &self.0;
+        Vec4::new(m[i * 4], m[i * 4 + 1], m[i * 4 + 2], m[i * 4 + 3])
+    }
+    pub fn row(&self, i: usize) -> Vec4 {
+        let m = &self.0;
+        Vec4::new(m[i], m[4 + i], m[8 + i], m[12 + i])
+    }
+    pub fn translation(&self) -> Vec3 {
+        self.col(3).truncate()
+    }
+    /// Inverse of [`Mat4::from_scale_rotation_translation`] for matrices
+    /// without shear. A mirroring matrix gets a negative x scale.
+    pub fn to_scale_rotation_translation(&self) -> (Vec3, Quat, Vec3) {
+        let [x, y, z] = [0, 1, 2].map(|i| self.col(i).truncate());
+
+        let sign = if self.determinant() < 0.0 { -1.0 } else { 1.0 };
+        let scale = Vec3::new(x.length() * sign, y.length(), z.length());
+
+        let rotation = if scale.x == 0.0 || scale.y == 0.0 || scale.z == 0.0 {
+            Quat::IDENTITY
+        } else {
+            Quat::from_axes(x / scale.x, y / scale.y, z / scale.z)
+        };
+
+        (scale, rotation, self.translation())
+    }
+    pub fn transpose(&self) -> Mat4 {
+        Self::from_cols(self.row(0), self.row(1), self.row(2), self.row(3))
+    }
+    /// Applies the full matrix to a point (w = 1), without a perspective
+    /// divide.
+    pub fn transform_point3(&self, p: Vec3) -> Vec3 {
+        (*self * p.extend(1.0)).truncate()
+    }
+    /// Applies only the linear part, for directions (w = 0).
+    pub fn transform_vector3(&self, v: Vec3) -> Vec3 {
+        (*self * v.extend(0.0)).truncate()
+    }
+    /// Applies the matrix to a point and divides by the resulting `w`, as
+    /// the GPU does for clip space.
+    pub fn project_point3(&self, p: Vec3) -> Vec3 {
+        let clip = *self * p.extend(1.0);
+        clip.truncate() / clip.w
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let c = Cofactors::new(&self.0);
+        c.det()
+    }
+    /// General inverse via cofactor expansion. `None` if singular.
+    pub fn inverse(&self) -> Option<Mat4> {
+        let m = &self.0;
+        let c = Cofactors::new(m);
+        let det = c.det();
+
+        if det.abs() < 1e-12 {
+            return None;
+        }
+
+        let inv = 1.0 / det;
+        let [b00, b01, b02, b03, b04, b05, b06, b07, b08, b09, b10, b11] = c.0;
+
+        Some(Mat4([
+            (m[5] * b11 - m[6] * b10 + m[7] * b09) * inv,
+            (m[2] * b10 - m[1] * b11 - m[3] * b09) * inv,
+            (m[13] * b05 - m[14] * b04 + m[15] * b03) * inv,
+            (m[10] * b04 - m[9] * b05 - m[11] * b03) * inv,
+            (m[6] * b08 - m[4] * b11 - m[7] * b07) * inv,
+            (m[0] * b11 - m[2] * b08 + m[3] * b07) * inv,
+            (m[14] * b02 - m[12] * b05 - m[15] * b01) * inv,
+            (m[8] * b05 - m[10] * b02 + m[11] * b01) * inv,
+            (m[4] * b10 - m[5] * b08 + m[7] * b06) * inv,
+            (m[1] * b08 - m[0] * b10 - m[3] * b06) * inv,
+            (m[12] * b04 - m[13] * b02 + m[15] * b00) * inv,
+            (m[9] * b02 - m[8] * b04 - m[11] * b00) * inv,
+            (m[5] * b07 - m[4] * b09 - m[6] * b06) * inv,
+            (m[0] * b09 - m[1] * b07 + m[2] * b06) * inv,
+            (m[13] * b01 - m[12] * b03 - m[14] * b00) * inv,
+            (m[8] * b03 - m[9] * b01 + m[10] * b00) * inv,
+        ]))
+    }
+}
+
+/// The 2x2 sub-determinants shared by `determinant` and `inverse`.
+struct Cofactors([f32; 12]);
+
+impl Cofactors {
+    fn new(m: &[f32; 16]) -> Self {
+        Cofactors([
+            m[0] * m[5] - m[1] * m[4],
+            m[0] * m[6] - m[2] * m[4],
+            m[0] * m[7] - m[3] * m[4],
+            m[1] * m[6] - m[2] * m[5],
+            m[1] * m[7] - m[3] * m[5],
+            m[2] * m[7] - m[3] * m[6],
+            m[8] * m[13] - m[9] * m[12],
+            m[8] * m[14] - m[10] * m[12],
+            m[8] * m[15] - m[11] * m[12],
+            m[9] * m[14] - m[10] * m[13],
+            m[9] * m[15] - m[11] * m[13],
+            m[10] * m[15] - m[11] * m[14],
+        ])
+    }
+    fn det(&self) -> f32 {
+        let [b00, b01, b02, b03, b04, b05, b06, b07, b08, b09, b10, b11] = self.0;
+        b00 * b11 - b01 * b10 + b02 * b09 + b03 * b08 - b04 * b07 + b05 * b06
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        Mat4::from_cols(
+            self * rhs.col(0),
+            self * rhs.col(1),
+            self * rhs.col(2),
+            self * rhs.col(3),
+        )
+    }
+}
+
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+
+    fn mul(self, v: Vec4) -> Vec4 {
+        self.col(0) * v.x + self.col(1) * v.y + self.col(2) * v.z + self.col(3) * v.w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    const EPS: f32 = 1e-5;
+
+    fn assert_vec3(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < EPS, "{a:?} != {b:?}");
+    }
+
+    fn assert_mat4(a: Mat4, b: Mat4) {
+        let close = a.0.iter().zip(&b.0).all(|(x, y)| (x - y).abs() < EPS);
+        assert!(close, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn vec3_ops() {
+        let a = Vec3::new(1.0, 2.0, 3.0);
+        let b = Vec3::new(4.0, 5.0, 6.0);
+
+        assert_eq!(a + b, Vec3::new(5.0, 7.0, 9.0));
+        assert_eq!(b - a, Vec3::splat(3.0));
+        assert_eq!(a * 2.0, Vec3::new(2.0, 4.0, 6.0));
+        assert_eq!(2.0 * a, a * 2.0);
+        assert_eq!(-a, Vec3::new(-1.0, -2.0, -3.0));
+        assert_eq!(a.dot(b), 32.0);
+        assert_eq!(a.cross(b), Vec3::new(-3.0, 6.0, -3.0));
+        assert_eq!(Vec3::X.cross(Vec3::Y), Vec3::Z);
+        assert_eq!(Vec3::new(3.0, 4.0, 0.0).length(), 5.0);
+        assert_eq!(Vec3::ZERO.normalize(), Vec3::ZERO);
+        assert_eq!(a[2], 3.0);
+    }
+
+    #[test]
+    fn quat_rotates_counter_clockwise() {
+        let q = Quat::from_rotation_y(FRAC_PI_2);
+
+        assert_vec3(q * Vec3::X, -Vec3::Z);
+        assert_vec3(Quat::from_rotation_z(FRAC_PI_2) * Vec3::X, Vec3::Y);
+        assert_vec3(Quat::from_rotation_x(FRAC_PI_2) * Vec3::Y, Vec3::Z);
+        assert_vec3(q.conjugate() * (q * Vec3::X), Vec3::X);
+    }
+
+    #[test]
+    fn look_to_points_forward_at_dir() {
+        for dir in [Vec3::new(1.0, -2.0, 0.5), -Vec3::Y, Vec3::Z] {
+            let q = Quat::look_to(dir, Vec3::Y);
+
+            assert_vec3(q * -Vec3::Z, dir.normalize());
+            assert!((q * Vec3::X).y.abs() < EPS || dir.cross(Vec3::Y).length() < EPS);
+        }
+    }
+
+    #[test]
+    fn quat_product_applies_right_first() {
+        let a = Quat::from_rotation_z(FRAC_PI_2);
+        let b = Quat::from_rotation_y(FRAC_PI_2);
+        let v = Vec3::new(1.0, 2.0, 3.0);
+
+        assert_vec3((a * b) * v, a * (b * v));
+    }
+
+    #[test]
+    fn euler_is_yaw_pitch_roll() {
+        assert_vec3(Quat::from_euler(0.0, FRAC_PI_2, 0.0) * Vec3::Z, Vec3::X);
+        assert_vec3(Quat::from_euler(FRAC_PI_2, 0.0, 0.0) * Vec3::Z, -Vec3::Y);
+
+        // Pitch tips +Y forward onto +Z, then the yaw turns that onto +X.
+        let q = Quat::from_euler(FRAC_PI_2, FRAC_PI_2, 0.0);
+        assert_vec3(q * Vec3::Y, Vec3::X);
+    }
+
+    #[test]
+    fn euler_round_trips() {
+        for (x, y, z) in [(0.3, -1.2, 2.5), (-1.0, 3.0, 0.1), (0.0, 0.0, 0.0)] {
+            let (ex, ey, ez) = Quat::from_euler(x, y, z).to_euler();
+
+            assert!((ex - x).abs() < 1e-4 && (ey - y).abs() < 1e-4 && (ez - z).abs() < 1e-4);
+        }
+
+        // Gimbal lock: angles differ but the rotation is the same.
+        let q = Quat::from_euler(FRAC_PI_2, 0.4, 0.3);
+        let (x, y, z) = q.to_euler();
+        let v = Vec3::new(1.0, 2.0, 3.0);
+        assert_vec3(Quat::from_euler(x, y, z) * v, q * v);
+    }
+
+    #[test]
+    fn decompose_recovers_trs() {
+        let rotation = Quat::from_euler(0.5, -2.0, 1.0);
+        let m = Mat4::from_scale_rotation_translation(
+            Vec3::new(0.5, 2.0, 3.0),
+            rotation,
+            Vec3::new(1.0, 2.0, 3.0),
+        );
+        let (scale, q, translation) = m.to_scale_rotation_translation();
+
+        assert_vec3(scale, Vec3::new(0.5, 2.0, 3.0));
+        assert_vec3(translation, Vec3::new(1.0, 2.0, 3.0));
+        assert!(q.dot(rotation).abs() > 1.0 - EPS);
+        assert_mat4(
+            Mat4::from_scale_rotation_translation(scale, q, translation),
+            m,
+        );
+    }
+
+    #[test]
+    fn quat_matrix_matches_quat() {
+        let q = Quat::from_axis_angle(Vec3::new(1.0, 2.0, 3.0), 0.7);
+        let v = Vec3::new(-2.0, 0.5, 4.0);
+
+        assert_vec3(Mat4::from_quat(q).transform_vector3(v), q * v);
+    }
+
+    #[test]
+    fn trs_applies_scale_rotation_translation() {
+        let m = Mat4::from_scale_rotation_translation(
+            Vec3::splat(2.0),
+            Quat::from_rotation_z(FRAC_PI_2),
+            Vec3::new(10.0, 0.0, 0.0),
+        );
+
+        assert_vec3(m.transform_point3(Vec3::X), Vec3::new(10.0, 2.0, 0.0));
+        assert_vec3(m.transform_vector3(Vec3::X), Vec3::new(0.0, 2.0, 0.0));
+        assert_eq!(m.translation(), Vec3::new(10.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn mat4_product_composes() {
+        let t = Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0));
+        let s = Mat4::from_scale(Vec3::new(2.0, 3.0, 4.0));
+
+        // Scale first, then translate.
+        assert_vec3(
+            (t * s).transform_point3(Vec3::ONE),
+            Vec3::new(3.0, 5.0, 7.0),
+        );
+        assert_mat4(Mat4::IDENTITY * t, t);
+    }
+
+    #[test]
+    fn inverse_round_trips() {
+        let m = Mat4::from_scale_rotation_translation(
+            Vec3::new(0.5, 2.0, 3.0),
+            Quat::from_axis_angle(Vec3::new(1.0, 1.0, 0.0), 1.1),
+            Vec3::new(4.0, -5.0, 6.0),
+        );
+        let inv = m.inverse().unwrap();
+
+        assert_mat4(m * inv, Mat4::IDENTITY);
+        assert_mat4(inv * m, Mat4::IDENTITY);
+        assert!((m.determinant() - 3.0).abs() < EPS);
+    }
+
+    #[test]
+    fn singular_matrix_has_no_inverse() {
+        assert!(
+            Mat4::from_scale(Vec3::new(1.0, 0.0, 1.0))
+                .inverse()
+                .is_none()
+        );
+        assert_eq!(Mat4::from_scale(Vec3::splat(2.0)).determinant(), 8.0);
+    }
+
+    #[test]
+    fn transpose_swaps_rows_and_columns() {
+        let m = Mat4(std::array::from_fn(|i| i as f32));
+
+        assert_eq!(m.transpose().col(0), m.row(0));
+        assert_eq!(m.transpose().transpose(), m);
+    }
+
+    #[test]
+    fn look_at_maps_eye_to_origin() {
+        let eye = Vec3::new(0.0, 0.0, 5.0);
+        let view = Mat4::look_at(eye, Vec3::ZERO, Vec3::Y);
+
+        assert_vec3(view.transform_point3(eye), Vec3::ZERO);
+        // The target ends up straight ahead, down -Z.
+        assert_vec3(view.transform_point3(Vec3::ZERO), Vec3::new(0.0, 0.0, -5.0));
+        assert_vec3(view.transform_point3(Vec3::X), Vec3::new(1.0, 0.0, -5.0));
+    }
+
+    #[test]
+    fn perspective_maps_near_and_far_planes() {
+        let proj = Mat4::perspective(FRAC_PI_2, 2.0, 1.0, 10.0);
+
+        assert_eq!(proj.0[0], 0.5);
+        assert!((proj.0[5] - 1.0).abs() < EPS);
+        assert_vec3(proj.project_point3(Vec3::new(0.0, 0.0, -1.0)), -Vec3::Z);
+        assert_vec3(proj.project_point3(Vec3::new(0.0, 0.0, -10.0)), Vec3::Z);
+        assert_vec3(
+            proj.project_point3(Vec3::new(2.0, 1.0, -1.0)),
+            Vec3::new(1.0, 1.0, -1.0),
+        );
+    }
+
+    #[test]
+    fn orthographic_maps_box_to_clip_cube() {
+        let proj = Mat4::orthographic(-2.0, 2.0, -1.0, 1.0, 0.5, 10.0);
+
+        assert_vec3(
+            proj.transform_point3(Vec3::new(-2.0, -1.0, -0.5)),
+            -Vec3::ONE,
+        );
+        assert_vec3(proj.transform_point3(Vec3::new(2.0, 1.0, -10.0)), Vec3::ONE);
+    }
+}
@@ -0,0 +1,542 @@
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+
+use wasm_bindgen::prelude::*;
+
+use crate::bounds::{Aabb, Bounds};
+use crate::components::Mesh;
+use crate::math::Vec3;
+
+/// Floats per vertex: position, normal, then UV.
+pub const VERTEX_STRIDE: usize = 8;
+
+/// Indexed triangle mesh with interleaved vertices (see `VERTEX_STRIDE`).
+/// Triangles wind counter-clockwise seen from the side their normals
+/// point to.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct MeshData {
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() / VERTEX_STRIDE
+    }
+    pub fn position(&self, vertex: u32) -> Vec3 {
+        let v = &self.vertices[vertex as usize * VERTEX_STRIDE..];
+        Vec3::new(v[0], v[1], v[2])
+    }
+    pub fn normal(&self, vertex: u32) -> Vec3 {
+        let v = &self.vertices[vertex as usize * VERTEX_STRIDE..];
+        Vec3::new(v[3], v[4], v[5])
+    }
+    /// Box around all vertices; `None` for an empty mesh.
+    pub fn aabb(&self) -> Option<Aabb> {
+        let mut positions = (0..self.vertex_count() as u32).map(|i| self.position(i));
+        let first = positions.next()?;
+
+        Some(positions.fold(Aabb::new(first, first), |aabb, p| {
+            Aabb::new(aabb.min.min(p), aabb.max.max(p))
+        }))
+    }
+
+    /// Appends a vertex and returns its index.
+    pub fn push_vertex(&mut self, position: Vec3, normal: Vec3, uv: [f32; 2]) -> u32 {
+        let index = self.vertex_count() as u32;
+
+        self.vertices.extend_from_slice(&position.to_array());
+        self.vertices.extend_from_slice(&normal.to_array());
+        self.vertices.extend_from_slice(&uv);
+
+        index
+    }
+    /// Replaces every normal with the area-weighted average of the
+    /// triangles around the vertex, for sources that come without them.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vec3::ZERO; self.vertex_count()];
+
+        for tri in self.indices.chunks_exact(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]];
+            let (pa, pb, pc) = (self.position(a), self.position(b), self.position(c));
+            // Length is twice the area, so larger faces weigh more.
+            let face = (pb - pa).cross(pc - pa);
+
+            for i in [a, b, c] {
+                normals[i as usize] += face;
+            }
+        }
+
+        for (vertex, normal) in self.vertices.chunks_exact_mut(VERTEX_STRIDE).zip(normals) {
+            let normal = if normal.length_squared() > 0.0 {
+                normal.normalize()
+            } else {
+                Vec3::Y
+            };
+            vertex[3..6].copy_from_slice(&normal.to_array());
+        }
+    }
+
+    /// Adds a triangle, dropping it if it has no area (as at the poles of
+    /// a UV sphere or the tip of a cone).
+    fn triangle(&mut self, a: u32, b: u32, c: u32) {
+        let (pa, pb, pc) = (self.position(a), self.position(b), self.position(c));
+
+        if (pb - pa).cross(pc - pa).length_squared() > 1e-12 {
+            self.indices.extend_from_slice(&[a, b, c]);
+        }
+    }
+    /// Adds a `cols` x `rows` grid of quads, sampling `surface(u, v)` for
+    /// a position and normal at each grid point. Triangles face the way
+    /// of dP/du x dP/dv.
+    fn grid(&mut self, cols: u32, rows: u32, surface: impl Fn(f32, f32) -> (Vec3, Vec3)) {
+        let base = self.vertex_count() as u32;
+
+        for j in 0..=rows {
+            for i in 0..=cols {
+                let (u, v) = (i as f32 / cols as f32, j as f32 / rows as f32);
+                let (position, normal) = surface(u, v);
+
+                self.push_vertex(position, normal, [u, 1.0 - v]);
+            }
+        }
+
+        let at = |i: u32, j: u32| base + j * (cols + 1) + i;
+
+        for j in 0..rows {
+            for i in 0..cols {
+                let (a, b, c, d) = (at(i, j), at(i + 1, j), at(i + 1, j + 1), at(i, j + 1));
+
+                self.triangle(a, b, c);
+                self.triangle(a, c, d);
+            }
+        }
+    }
+    /// Flat disc of radius 0.5 at height `y`, facing +Y or -Y.
+    fn cap(&mut self, segments: u32, y: f32, up: bool) {
+        let normal = if up { Vec3::Y } else { -Vec3::Y };
+        let center = self.push_vertex(Vec3::new(0.0, y, 0.0), normal, [0.5, 0.5]);
+
+        let ring: Vec<u32> = (0..=segments)
+            .map(|i| {
+                let (s, c) = (i as f32 / segments as f32 * TAU).sin_cos();
+                self.push_vertex(
+                    Vec3::new(0.5 * s, y, 0.5 * c),
+                    normal,
+                    [0.5 + 0.5 * s, 0.5 + 0.5 * c],
+                )
+            })
+            .collect();
+
+        for pair in ring.windows(2) {
+            if up {
+                self.triangle(center, pair[0], pair[1]);
+            } else {
+                self.triangle(center, pair[1], pair[0]);
+            }
+        }
+    }
+}
+
+// The six cube faces as (normal, u, v) with u x v = normal.
+const FACES: [(Vec3, Vec3, Vec3); 6] = [
+    (Vec3::X, Vec3::new(0.0, 0.0, -1.0), Vec3::Y),
+    (Vec3::new(-1.0, 0.0, 0.0), Vec3::Z, Vec3::Y),
+    (Vec3::Y, Vec3::X, Vec3::new(0.0, 0.0, -1.0)),
+    (Vec3::new(0.0, -1.0, 0.0), Vec3::X, Vec3::Z),
+    (Vec3::Z, Vec3::X, Vec3::Y),
+    (
+        Vec3::new(0.0, 0.0, -1.0),
+        Vec3::new(-1.0, 0.0, 0.0),
+        Vec3::Y,
+    ),
+];
+
+/// Unit cube, ±0.5 on each axis, with flat normals.
+pub fn cube() -> MeshData {
+    let mut mesh = MeshData::default();
+
+    for (n, u, v) in FACES {
+        mesh.grid(1, 1, |s, t| (n * 0.5 + u * (s - 0.5) + v * (t - 0.5), n));
+    }
+
+    mesh
+}
+
+/// Sphere of radius 0.5 from `segments` meridians and `rings` parallels.
+/// UVs are equirectangular.
+pub fn uv_sphere(segments: u32, rings: u32) -> MeshData {
+    let mut mesh = MeshData::default();
+
+    mesh.grid(segments.max(3), rings.max(2), |u, v| {
+        let (sin_phi, cos_phi) = (u * TAU).sin_cos();
+        let (sin_theta, cos_theta) = (v * PI).sin_cos();
+        let normal = Vec3::new(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi);
+
+        (normal * 0.5, normal)
+    });
+
+    mesh
+}
+
+/// Sphere of radius 0.5 from an icosahedron split `subdivisions` times,
+/// giving `20 * 4^n` near-equal triangles. UVs are equirectangular and
+/// not split at the seam.
+pub fn ico_sphere(subdivisions: u32) -> MeshData {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+
+    let mut points: Vec<Vec3> = [
+        (-1.0, t, 0.0),
+        (1.0, t, 0.0),
+        (-1.0, -t, 0.0),
+        (1.0, -t, 0.0),
+        (0.0, -1.0, t),
+        (0.0, 1.0, t),
+        (0.0, -1.0, -t),
+        (0.0, 1.0, -t),
+        (t, 0.0, -1.0),
+        (t, 0.0, 1.0),
+        (-t, 0.0, -1.0),
+        (-t, 0.0, 1.0),
+    ]
+    .map(|(x, y, z)| Vec3::new(x, y, z).normalize())
+    .to_vec();
+
+    let mut faces: Vec<[u32; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions.min(6) {
+        // Edges are shared, so each midpoint is made once.
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let p = (points[a as usize] + points[b as usize]).normalize();
+                points.push(p);
+                points.len() as u32 - 1
+            })
+        };
+
+        faces = faces
+            .into_iter()
+            .flat_map(|[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut mesh = MeshData::default();
+
+    for p in points {
+        let u = 0.5 + p.z.atan2(p.x) / TAU;
+        let v = p.y.clamp(-1.0, 1.0).acos() / PI;
+
+        mesh.push_vertex(p * 0.5, p, [u, v]);
+    }
+    for [a, b, c] in faces {
+        mesh.triangle(a, b, c);
+    }
+
+    mesh
+}
+
+/// Capped cylinder along Y: radius 0.5, height 1, centered.
+pub fn cylinder(segments: u32) -> MeshData {
+    let segments = segments.max(3);
+    let mut mesh = MeshData::default();
+
+    mesh.grid(segments, 1, |u, v| {
+        let (s, c) = (u * TAU).sin_cos();
+        (Vec3::new(0.5 * s, v - 0.5, 0.5 * c), Vec3::new(s, 0.0, c))
+    });
+    mesh.cap(segments, 0.5, true);
+    mesh.cap(segments, -0.5, false);
+
+    mesh
+}
+
+/// Cone along Y: base radius 0.5 at y = -0.5, apex at y = 0.5.
+pub fn cone(segments: u32) -> MeshData {
+    let segments = segments.max(3);
+    let mut mesh = MeshData::default();
+
+    mesh.grid(segments, 1, |u, v| {
+        let (s, c) = (u * TAU).sin_cos();
+        let radius = 0.5 * (1.0 - v);
+
+        // Slope normal for height 1 and base radius 0.5.
+        (
+            Vec3::new(radius * s, v - 0.5, radius * c),
+            Vec3::new(s, 0.5, c).normalize(),
+        )
+    });
+    mesh.cap(segments, -0.5, false);
+
+    mesh
+}
+
+/// Torus around Y: ring radius 0.35, tube radius 0.15, so it fits the
+/// unit cube. `segments` go around the ring, `sides` around the tube.
+pub fn torus(segments: u32, sides: u32) -> MeshData {
+    const RING: f32 = 0.35;
+    const TUBE: f32 = 0.15;
+
+    let mut mesh = MeshData::default();
+
+    mesh.grid(segments.max(3), sides.max(3), |u, v| {
+        let (sin_phi, cos_phi) = (u * TAU).sin_cos();
+        let (sin_theta, cos_theta) = (v * TAU).sin_cos();
+        let normal = Vec3::new(cos_theta * sin_phi, sin_theta, cos_theta * cos_phi);
+        let center = Vec3::new(RING * sin_phi, 0.0, RING * cos_phi);
+
+        (center + normal * TUBE, normal)
+    });
+
+    mesh
+}
+
+/// Unit square in the XZ plane facing +Y, split into
+/// `subdivisions` x `subdivisions` quads.
+pub fn plane(subdivisions: u32) -> MeshData {
+    let n = subdivisions.max(1);
+    let (normal, u, v) = FACES[2];
+    let mut mesh = MeshData::default();
+
+    mesh.grid(n, n, |s, t| (u * (s - 0.5) + v * (t - 0.5), normal));
+
+    mesh
+}
+
+/// Unit cube with edges and corners rounded to `radius`, each rounded
+/// band split into `segments` steps.
+pub fn rounded_box(radius: f32, segments: u32) -> MeshData {
+    let radius = radius.clamp(1e-3, 0.5);
+    let segments = segments.max(1);
+
+    // Face coordinates: `segments` steps across each rounded band plus one
+    // across the flat middle (which has no width at radius 0.5).
+    let band = |k: u32| -0.5 + radius * k as f32 / segments as f32;
+    let mut steps: Vec<f32> = (0..=segments).map(band).collect();
+    steps.extend((0..=segments).rev().map(|k| -band(k)));
+    steps.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+
+    let cells = steps.len() as u32 - 1;
+    let inner = Vec3::splat(0.5 - radius);
+    let mut mesh = MeshData::default();
+
+    for (n, u, v) in FACES {
+        let at = |x: f32| steps[(x * cells as f32).round() as usize];
+
+        mesh.grid(cells, cells, |s, t| {
+            // Point on the cube, pulled onto the rounded surface around
+            // the nearest point of the inner box.
+            let p = n * 0.5 + u * at(s) + v * at(t);
+            let core = p.max(-inner).min(inner);
+            let normal = (p - core).normalize();
+
+            (core + normal * radius, normal)
+        });
+    }
+
+    mesh
+}
+
+/// The generated meshes, by the id `MeshShape` gives them. Registered in
+/// this order at startup, so `Mesh(shape as u32)` draws that shape.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MeshShape {
+    Cube = 0,
+    UvSphere = 1,
+    IcoSphere = 2,
+    Cylinder = 3,
+    Cone = 4,
+    Torus = 5,
+    Plane = 6,
+    RoundedBox = 7,
+}
+
+impl MeshShape {
+    pub const ALL: [MeshShape; 8] = [
+        MeshShape::Cube,
+        MeshShape::UvSphere,
+        MeshShape::IcoSphere,
+        MeshShape::Cylinder,
+        MeshShape::Cone,
+        MeshShape::Torus,
+        MeshShape::Plane,
+        MeshShape::RoundedBox,
+    ];
+
+    /// The shape at its default level of detail.
+    pub fn generate(self) -> MeshData {
+        match self {
+            MeshShape::Cube => cube(),
+            MeshShape::UvSphere => uv_sphere(32, 16),
+            MeshShape::IcoSphere => ico_sphere(2),
+            MeshShape::Cylinder => cylinder(32),
+            MeshShape::Cone => cone(32),
+            MeshShape::Torus => torus(32, 16),
+            MeshShape::Plane => plane(1),
+            MeshShape::RoundedBox => rounded_box(0.1, 4),
+        }
+    }
+}
+
+/// Mesh assets, indexed by `Mesh` id.
+pub struct Meshes {
+    meshes: Vec<MeshData>,
+}
+
+impl Default for Meshes {
+    fn default() -> Self {
+        Meshes {
+            meshes: MeshShape::ALL.map(MeshShape::generate).to_vec(),
+        }
+    }
+}
+
+impl Meshes {
+    pub fn add(&mut self, mesh: MeshData) -> Mesh {
+        self.meshes.push(mesh);
+        Mesh(self.meshes.len() as u32 - 1)
+    }
+    pub fn get(&self, mesh: Mesh) -> Option<&MeshData> {
+        self.meshes.get(mesh.0 as usize)
+    }
+    /// `Bounds` fitted to the mesh, for entities drawn with it.
+    pub fn bounds(&self, mesh: Mesh) -> Option<Bounds> {
+        let aabb = self.get(mesh)?.aabb().unwrap_or(Aabb::UNIT_CUBE);
+        Some(Bounds::from_aabb(aabb))
+    }
+    pub fn len(&self) -> usize {
+        self.meshes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.meshes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(name: &str, mesh: &MeshData) {
+        assert!(!mesh.indices.is_empty(), "{name}: no triangles");
+        assert_eq!(mesh.vertices.len() % VERTEX_STRIDE, 0, "{name}");
+        assert_eq!(mesh.indices.len() % 3, 0, "{name}");
+
+        let count = mesh.vertex_count() as u32;
+        assert!(
+            mesh.indices.iter().all(|&i| i < count),
+            "{name}: index out of range"
+        );
+
+        for i in 0..count {
+            let p = mesh.position(i);
+            let n = mesh.normal(i);
+            let uv = &mesh.vertices[i as usize * VERTEX_STRIDE + 6..][..2];
+
+            assert!((n.length() - 1.0).abs() < 1e-4, "{name}: normal {n:?}");
+            assert!(
+                (0..3).all(|k| p[k].abs() <= 0.5 + 1e-5),
+                "{name}: {p:?} outside unit cube"
+            );
+            assert!(
+                uv.iter().all(|t| (0.0..=1.0).contains(t)),
+                "{name}: uv {uv:?}"
+            );
+        }
+
+        for tri in mesh.indices.chunks(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]];
+            let face =
+                (mesh.position(b) - mesh.position(a)).cross(mesh.position(c) - mesh.position(a));
+            let normal = mesh.normal(a) + mesh.normal(b) + mesh.normal(c);
+
+            assert!(
+                face.dot(normal) > 0.0,
+                "{name}: triangle {tri:?} winds against its normals"
+            );
+        }
+    }
+
+    #[test]
+    fn builtin_shapes_are_well_formed() {
+        for shape in MeshShape::ALL {
+            check(&format!("{shape:?}"), &shape.generate());
+        }
+        check("rounded box r=0.5", &rounded_box(0.5, 3));
+        check("plane 8", &plane(8));
+    }
+
+    #[test]
+    fn cube_has_flat_faces() {
+        let mesh = cube();
+
+        assert_eq!(mesh.vertex_count(), 24);
+        assert_eq!(mesh.indices.len(), 36);
+        assert_eq!(mesh.aabb(), Some(Aabb::UNIT_CUBE));
+    }
+
+    #[test]
+    fn plane_is_subdivided() {
+        let mesh = plane(4);
+
+        assert_eq!(mesh.vertex_count(), 25);
+        assert_eq!(mesh.indices.len(), 4 * 4 * 6);
+        assert!((0..25).all(|i| mesh.position(i).y == 0.0 && mesh.normal(i) == Vec3::Y));
+    }
+
+    #[test]
+    fn spheres_lie_on_radius() {
+        for mesh in [uv_sphere(16, 8), ico_sphere(3)] {
+            let count = mesh.vertex_count() as u32;
+            assert!((0..count).all(|i| (mesh.position(i).length() - 0.5).abs() < 1e-5));
+        }
+    }
+
+    #[test]
+    fn ico_sphere_subdivides_faces() {
+        assert_eq!(ico_sphere(0).indices.len(), 20 * 3);
+        assert_eq!(ico_sphere(2).indices.len(), 20 * 16 * 3);
+        // Shared midpoints: V = 10 * 4^n + 2.
+        assert_eq!(ico_sphere(2).vertex_count(), 162);
+    }
+
+    #[test]
+    fn poles_and_tips_drop_degenerate_triangles() {
+        // 16 segments: the pole rows give 16 triangles each instead of 32.
+        assert_eq!(uv_sphere(16, 8).indices.len() / 3, 16 * 8 * 2 - 2 * 16);
+        // Side: one triangle per segment at the tip; cap: one per segment.
+        assert_eq!(cone(16).indices.len() / 3, 16 + 16);
+    }
+
+    #[test]
+    fn rounded_box_fills_unit_cube() {
+        let aabb = rounded_box(0.2, 4).aabb().unwrap();
+
+        assert!((aabb.min - Aabb::UNIT_CUBE.min).length() < 1e-5);
+        assert!((aabb.max - Aabb::UNIT_CUBE.max).length() < 1e-5);
+    }
+}
@@ -0,0 +1,178 @@
+//! Minimal PNG encoder for 8-bit RGBA images.
+//!
+//! The zlib stream uses stored (uncompressed) deflate blocks: files are
+//! larger than with a real compressor, but the output is byte-for-byte
+//! deterministic and there is nothing to get wrong.
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+// Largest payload of one stored deflate block.
+const MAX_STORED: usize = 0xffff;
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut n = 0;
+
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+}
+
+/// CRC-32 as used by PNG chunks (ISO 3309).
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |c, &b| {
+        CRC_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8)
+    })
+}
+
+/// Adler-32 checksum closing a zlib stream.
+pub fn adler32(bytes: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+
+    // 5552 bytes is the most that can be summed before `b` overflows.
+    for chunk in bytes.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+
+    (b << 16) | a
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_STORED).max(1);
+    let mut out = Vec::with_capacity(data.len() + blocks * 5 + 6);
+
+    // Deflate, 32K window, no preset dictionary, fastest level.
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut chunks = data.chunks(MAX_STORED).peekable();
+
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = block.len() as u16;
+
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// Encodes `width * height` RGBA pixels, row by row from the top, as a
+/// PNG file.
+///
+/// Panics if `pixels` is not exactly `width * height * 4` bytes.
+pub fn encode_rgba(width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+    let row = width as usize * 4;
+    assert_eq!(pixels.len(), row * height as usize, "pixel buffer size");
+
+    // Each scanline starts with its filter type; 0 is none.
+    let mut raw = Vec::with_capacity((row + 1) * height as usize);
+    for line in pixels.chunks(row.max(1)).take(height as usize) {
+        raw.push(0);
+        raw.extend_from_slice(line);
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, adaptive filtering, no interlace.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut out = SIGNATURE.to_vec();
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_reference_values() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(adler32(&[0xff; 100_000]), {
+            let (mut a, mut b) = (1u64, 0u64);
+            for _ in 0..100_000 {
+                a = (a + 0xff) % 65521;
+                b = (b + a) % 65521;
+            }
+            ((b << 16) | a) as u32
+        });
+    }
+
+    #[test]
+    fn encodes_header_and_chunks() {
+        let png = encode_rgba(2, 1, &[255, 0, 0, 255, 0, 0, 255, 128]);
+
+        assert_eq!(png[..8], SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(png[16..20], 2u32.to_be_bytes());
+        assert_eq!(png[20..24], 1u32.to_be_bytes());
+        assert_eq!(png[24..29], [8, 6, 0, 0, 0]);
+        assert_eq!(
+            png[png.len() - 12..],
+            [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+
+        // IDAT: zlib header, one final stored block holding the filtered
+        // scanline, then the Adler-32.
+        let idat = &png[33..png.len() - 12];
+        assert_eq!(idat[..4], 20u32.to_be_bytes());
+        assert_eq!(&idat[4..8], b"IDAT");
+        assert_eq!(idat[8..15], [0x78, 0x01, 1, 9, 0, 0xf6, 0xff]);
+        assert_eq!(idat[15..24], [0, 255, 0, 0, 255, 0, 0, 255, 128]);
+    }
+
+    #[test]
+    fn splits_large_images_into_stored_blocks() {
+        let pixels = vec![7; 200 * 200 * 4];
+        let zlib = zlib_stored(&pixels);
+
+        // Three blocks of at most 65535 bytes, only the last one final.
+        assert_eq!(zlib.len(), 2 + 3 * 5 + pixels.len() + 4);
+        assert_eq!(zlib[2], 0);
+        assert_eq!(zlib[2 + 5 + MAX_STORED], 0);
+        assert_eq!(zlib[2 + 2 * (5 + MAX_STORED)], 1);
+    }
+}
@@ -0,0 +1,588 @@
+use std::cell::{Ref, RefMut};
+use std::marker::PhantomData;
+use std::ops::ControlFlow;
+
+use crate::storage::Storage;
+use crate::world::{Component, Entity, World};
+
+/// A set of components fetched together for each matching entity.
+///
+/// Implemented for `&T`, `&mut T`, `Option<&T>`, `Option<&mut T>` and
+/// tuples of those. Borrows follow `RefCell` rules: asking for the same
+/// component mutably twice (or mutably and in a filter) panics.
+pub trait QueryData {
+    type Fetch<'w>;
+    type Item<'f>;
+
+    fn fetch(world: &World) -> Self::Fetch<'_>;
+    /// Dense entity list of the smallest storage this query requires.
+    /// `None` when no term is required (every term is optional).
+    fn driver<'a>(fetch: &'a Self::Fetch<'_>) -> Option<&'a [u32]>;
+    /// Whether every required term is present, checked before `get` so a
+    /// mutable term is not marked changed on an entity that fails a later
+    /// term.
+    fn contains(fetch: &Self::Fetch<'_>, index: u32) -> bool;
+    fn get<'f>(fetch: &'f mut Self::Fetch<'_>, index: u32) -> Option<Self::Item<'f>>;
+}
+
+/// Extra conditions on which entities a query visits.
+///
+/// `since` is the first tick that counts as new for change filters.
+pub trait QueryFilter {
+    type State<'w>;
+
+    fn state(world: &World) -> Self::State<'_>;
+    /// Dense entity list of the smallest storage this filter requires,
+    /// so it can drive the join when it is smaller than the query's.
+    fn driver<'a>(_state: &'a Self::State<'_>) -> Option<&'a [u32]> {
+        None
+    }
+    fn matches(state: &Self::State<'_>, index: u32, since: u32) -> bool;
+}
+
+/// Only entities that also have `T`.
+pub struct With<T>(PhantomData<T>);
+
+/// Only entities that do not have `T`.
+pub struct Without<T>(PhantomData<T>);
+
+/// Only entities whose `T` was inserted since the query's `since` tick.
+pub struct Added<T>(PhantomData<T>);
+
+/// Only entities whose `T` was inserted or mutably accessed since the
+/// query's `since` tick.
+pub struct Changed<T>(PhantomData<T>);
+
+fn try_storage<T: Component>(world: &World) -> Option<Ref<'_, Storage<T>>> {
+    world.is_registered::<T>().then(|| world.storage::<T>())
+}
+
+fn try_storage_mut<T: Component>(world: &World) -> Option<RefMut<'_, Storage<T>>> {
+    world.is_registered::<T>().then(|| world.storage_mut::<T>())
+}
+
+impl<T: Component> QueryData for &T {
+    type Fetch<'w> = Option<Ref<'w, Storage<T>>>;
+    type Item<'f> = &'f T;
+
+    fn fetch(world: &World) -> Self::Fetch<'_> {
+        try_storage::<T>(world)
+    }
+    fn driver<'a>(fetch: &'a Self::Fetch<'_>) -> Option<&'a [u32]> {
+        Some(fetch.as_ref().map_or(&[], |storage| storage.entities()))
+    }
+    fn contains(fetch: &Self::Fetch<'_>, index: u32) -> bool {
+        fetch
+            .as_ref()
+            .is_some_and(|storage| storage.contains_index(index))
+    }
+    fn get<'f>(fetch: &'f mut Self::Fetch<'_>, index: u32) -> Option<Self::Item<'f>> {
+        fetch.as_ref()?.get_index(index)
+    }
+}
+
+impl<T: Component> QueryData for &mut T {
+    type Fetch<'w> = Option<RefMut<'w, Storage<T>>>;
+    type Item<'f> = &'f mut T;
+
+    fn fetch(world: &World) -> Self::Fetch<'_> {
+        try_storage_mut::<T>(world)
+    }
+    fn driver<'a>(fetch: &'a Self::Fetch<'_>) -> Option<&'a [u32]> {
+        Some(fetch.as_ref().map_or(&[], |storage| storage.entities()))
+    }
+    fn contains(fetch: &Self::Fetch<'_>, index: u32) -> bool {
+        fetch
+            .as_ref()
+            .is_some_and(|storage| storage.contains_index(index))
+    }
+    fn get<'f>(fetch: &'f mut Self::Fetch<'_>, index: u32) -> Option<Self::Item<'f>> {
+        fetch.as_mut()?.get_index_mut(index)
+    }
+}
+
+impl<T: Component> QueryData for Option<&T> {
+    type Fetch<'w> = Option<Ref<'w, Storage<T>>>;
+    type Item<'f> = Option<&'f T>;
+
+    fn fetch(world: &World) -> Self::Fetch<'_> {
+        try_storage::<T>(world)
+    }
+    fn driver<'a>(_fetch: &'a Self::Fetch<'_>) -> Option<&'a [u32]> {
+        None
+    }
+    fn contains(_fetch: &Self::Fetch<'_>, _index: u32) -> bool {
+        true
+    }
+    fn get<'f>(fetch: &'f mut Self::Fetch<'_>, index: u32) -> Option<Self::Item<'f>> {
+        Some(fetch.as_ref().and_then(|storage| storage.get_index(index)))
+    }
+}
+
+impl<T: Component> QueryData for Option<&mut T> {
+    type Fetch<'w> = Option<RefMut<'w, Storage<T>>>;
+    type Item<'f> = Option<&'f mut T>;
+
+    fn fetch(world: &World) -> Self::Fetch<'_> {
+        try_storage_mut::<T>(world)
+    }
+    fn driver<'a>(_fetch: &'a Self::Fetch<'_>) -> Option<&'a [u32]> {
+        None
+    }
+    fn contains(_fetch: &Self::Fetch<'_>, _index: u32) -> bool {
+        true
+    }
+    fn get<'f>(fetch: &'f mut Self::Fetch<'_>, index: u32) -> Option<Self::Item<'f>> {
+        Some(
+            fetch
+                .as_mut()
+                .and_then(|storage| storage.get_index_mut(index)),
+        )
+    }
+}
+
+fn smaller<'a>(a: Option<&'a [u32]>, b: Option<&'a [u32]>) -> Option<&'a [u32]> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b.len() < a.len() { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+macro_rules! impl_query_data_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: QueryData),+> QueryData for ($($name,)+) {
+            type Fetch<'w> = ($($name::Fetch<'w>,)+);
+            type Item<'f> = ($($name::Item<'f>,)+);
+
+            fn fetch(world: &World) -> Self::Fetch<'_> {
+                ($($name::fetch(world),)+)
+            }
+            #[allow(non_snake_case)]
+            fn driver<'a>(fetch: &'a Self::Fetch<'_>) -> Option<&'a [u32]> {
+                let ($($name,)+) = fetch;
+                let driver = None;
+                $(let driver = smaller(driver, $name::driver($name));)+
+                driver
+            }
+            #[allow(non_snake_case)]
+            fn contains(fetch: &Self::Fetch<'_>, index: u32) -> bool {
+                let ($($name,)+) = fetch;
+                $($name::contains($name, index))&&+
+            }
+            #[allow(non_snake_case)]
+            fn get<'f>(fetch: &'f mut Self::Fetch<'_>, index: u32) -> Option<Self::Item<'f>> {
+                if !Self::contains(fetch, index) {
+                    return None;
+                }
+
+                let ($($name,)+) = fetch;
+                Some(($($name::get($name, index)?,)+))
+            }
+        }
+    };
+}
+
+impl_query_data_tuple!(A);
+impl_query_data_tuple!(A, B);
+impl_query_data_tuple!(A, B, C);
+impl_query_data_tuple!(A, B, C, D);
+impl_query_data_tuple!(A, B, C, D, E);
+impl_query_data_tuple!(A, B, C, D, E, F);
+
+impl QueryFilter for () {
+    type State<'w> = ();
+
+    fn state(_world: &World) -> Self::State<'_> {}
+    fn matches(_state: &Self::State<'_>, _index: u32, _since: u32) -> bool {
+        true
+    }
+}
+
+impl<T: Component> QueryFilter for With<T> {
+    type State<'w> = Option<Ref<'w, Storage<T>>>;
+
+    fn state(world: &World) -> Self::State<'_> {
+        try_storage::<T>(world)
+    }
+    fn driver<'a>(state: &'a Self::State<'_>) -> Option<&'a [u32]> {
+        Some(state.as_ref().map_or(&[], |storage| storage.entities()))
+    }
+    fn matches(state: &Self::State<'_>, index: u32, _since: u32) -> bool {
+        state
+            .as_ref()
+            .is_some_and(|storage| storage.contains_index(index))
+    }
+}
+
+impl<T: Component> QueryFilter for Without<T> {
+    type State<'w> = Option<Ref<'w, Storage<T>>>;
+
+    fn state(world: &World) -> Self::State<'_> {
+        try_storage::<T>(world)
+    }
+    fn matches(state: &Self::State<'_>, index: u32, _since: u32) -> bool {
+        !state
+            .as_ref()
+            .is_some_and(|storage| storage.contains_index(index))
+    }
+}
+
+impl<T: Component> QueryFilter for Added<T> {
+    type State<'w> = Option<Ref<'w, Storage<T>>>;
+
+    fn state(world: &World) -> Self::State<'_> {
+        try_storage::<T>(world)
+    }
+    fn driver<'a>(state: &'a Self::State<'_>) -> Option<&'a [u32]> {
+        Some(state.as_ref().map_or(&[], |storage| storage.entities()))
+    }
+    fn matches(state: &Self::State<'_>, index: u32, since: u32) -> bool {
+        state
+            .as_ref()
+            .is_some_and(|storage| storage.is_added_since(index, since))
+    }
+}
+
+impl<T: Component> QueryFilter for Changed<T> {
+    type State<'w> = Option<Ref<'w, Storage<T>>>;
+
+    fn state(world: &World) -> Self::State<'_> {
+        try_storage::<T>(world)
+    }
+    fn driver<'a>(state: &'a Self::State<'_>) -> Option<&'a [u32]> {
+        Some(state.as_ref().map_or(&[], |storage| storage.entities()))
+    }
+    fn matches(state: &Self::State<'_>, index: u32, since: u32) -> bool {
+        state
+            .as_ref()
+            .is_some_and(|storage| storage.is_changed_since(index, since))
+    }
+}
+
+macro_rules! impl_query_filter_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: QueryFilter),+> QueryFilter for ($($name,)+) {
+            type State<'w> = ($($name::State<'w>,)+);
+
+            fn state(world: &World) -> Self::State<'_> {
+                ($($name::state(world),)+)
+            }
+            #[allow(non_snake_case)]
+            fn driver<'a>(state: &'a Self::State<'_>) -> Option<&'a [u32]> {
+                let ($($name,)+) = state;
+                let driver = None;
+                $(let driver = smaller(driver, $name::driver($name));)+
+                driver
+            }
+            #[allow(non_snake_case)]
+            fn matches(state: &Self::State<'_>, index: u32, since: u32) -> bool {
+                let ($($name,)+) = state;
+                $($name::matches($name, index, since))&&+
+            }
+        }
+    };
+}
+
+impl_query_filter_tuple!(A);
+impl_query_filter_tuple!(A, B);
+impl_query_filter_tuple!(A, B, C);
+impl_query_filter_tuple!(A, B, C, D);
+
+/// Sparse-set join over the storages named by `Q`, narrowed by `F`.
+///
+/// Iteration is driven by the smallest storage required by either `Q`
+/// or `F`; every other term is probed by entity index.
+pub struct Query<'w, Q: QueryData, F: QueryFilter = ()> {
+    world: &'w World,
+    fetch: Q::Fetch<'w>,
+    filter: F::State<'w>,
+    since: u32,
+}
+
+impl<'w, Q: QueryData, F: QueryFilter> Query<'w, Q, F> {
+    pub(crate) fn new(world: &'w World) -> Self {
+        Self {
+            world,
+            fetch: Q::fetch(world),
+            filter: F::state(world),
+            since: world.change_tick(),
+        }
+    }
+
+    /// Makes `Added`/`Changed` filters match everything from `tick` on,
+    /// instead of only the current change window.
+    pub fn since(mut self, tick: u32) -> Self {
+        self.since = tick;
+        self
+    }
+    pub fn for_each(&mut self, mut f: impl FnMut(Entity, Q::Item<'_>)) {
+        self.scan(|entity, item| {
+            f(entity, item);
+            ControlFlow::Continue(())
+        });
+    }
+    pub fn get(&mut self, entity: Entity) -> Option<Q::Item<'_>> {
+        if !self.world.is_alive(entity) || !F::matches(&self.filter, entity.index, self.since) {
+            return None;
+        }
+
+        Q::get(&mut self.fetch, entity.index)
+    }
+    pub fn count(&mut self) -> usize {
+        let mut count = 0;
+        self.for_each(|_, _| count += 1);
+        count
+    }
+    /// Whether no entity matches. Stops at the first match.
+    pub fn is_empty(&mut self) -> bool {
+        let mut empty = true;
+
+        self.scan(|_, _| {
+            empty = false;
+            ControlFlow::Break(())
+        });
+
+        empty
+    }
+
+    /// Visits every match until `f` breaks, walking the driver's dense
+    /// entity list in place.
+    fn scan(&mut self, mut f: impl FnMut(Entity, Q::Item<'_>) -> ControlFlow<()>) {
+        let Self {
+            world,
+            fetch,
+            filter,
+            since,
+        } = self;
+        let (world, since) = (*world, *since);
+
+        let mut visit = |fetch: &mut Q::Fetch<'w>, index: u32| {
+            if !F::matches(filter, index, since) {
+                return ControlFlow::Continue(());
+            }
+            match Q::get(fetch, index) {
+                Some(item) => f(world.entity_at(index), item),
+                None => ControlFlow::Continue(()),
+            }
+        };
+
+        let fetch_len = Q::driver(fetch).map(<[u32]>::len);
+
+        if let Some(entities) = F::driver(filter)
+            && fetch_len.is_none_or(|len| entities.len() < len)
+        {
+            for &index in entities {
+                if visit(fetch, index).is_break() {
+                    return;
+                }
+            }
+        } else if fetch_len.is_some() {
+            // `get` needs the fetch mutably, so the driver slice is
+            // re-borrowed for each step rather than held across it.
+            let mut next = 0;
+
+            while let Some(&index) = Q::driver(fetch).and_then(|entities| entities.get(next)) {
+                next += 1;
+                if visit(fetch, index).is_break() {
+                    return;
+                }
+            }
+        } else {
+            for entity in world.iter() {
+                if visit(fetch, entity.index).is_break() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct A(u32);
+    #[derive(Debug, PartialEq)]
+    struct B(u32);
+    struct Marker;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register::<A>();
+        world.register::<B>();
+        world.register::<Marker>();
+        world
+    }
+
+    fn spawn(world: &mut World, a: Option<u32>, b: Option<u32>, marker: bool) -> Entity {
+        let entity = world.spawn().unwrap();
+        if let Some(a) = a {
+            world.insert(entity, A(a));
+        }
+        if let Some(b) = b {
+            world.insert(entity, B(b));
+        }
+        if marker {
+            world.insert(entity, Marker);
+        }
+        entity
+    }
+
+    fn collect<Q: QueryData, F: QueryFilter>(world: &World) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        world
+            .query_filtered::<Q, F>()
+            .for_each(|entity, _| entities.push(entity));
+        entities.sort_by_key(|entity| entity.index());
+        entities
+    }
+
+    #[test]
+    fn joins_visit_only_entities_with_every_term() {
+        let mut world = world();
+        let a = spawn(&mut world, Some(1), None, false);
+        let ab = spawn(&mut world, Some(2), Some(20), false);
+        let b = spawn(&mut world, None, Some(30), false);
+
+        let mut pairs = Vec::new();
+        world
+            .query::<(&A, &B)>()
+            .for_each(|entity, (a, b)| pairs.push((entity, a.0, b.0)));
+
+        assert_eq!(pairs, [(ab, 2, 20)]);
+        assert_eq!(world.query::<&A>().count(), 2);
+        assert_eq!(world.query::<(&A, &B)>().get(a).map(|(a, _)| a.0), None);
+        assert_eq!(world.query::<(&A, &B)>().get(b).map(|(a, _)| a.0), None);
+        assert_eq!(world.query::<(&A, &B)>().get(ab).map(|(a, _)| a.0), Some(2));
+    }
+
+    #[test]
+    fn optional_terms_do_not_narrow_the_join() {
+        let mut world = world();
+        let a = spawn(&mut world, Some(1), None, false);
+        let ab = spawn(&mut world, Some(2), Some(20), false);
+        spawn(&mut world, None, Some(30), false);
+
+        let mut seen = Vec::new();
+        world
+            .query::<(&A, Option<&B>)>()
+            .for_each(|entity, (_, b)| seen.push((entity, b.map(|b| b.0))));
+        seen.sort_by_key(|(entity, _)| entity.index());
+
+        assert_eq!(seen, [(a, None), (ab, Some(20))]);
+
+        // With nothing required every live entity is visited.
+        assert_eq!(world.query::<Option<&A>>().count(), 3);
+    }
+
+    #[test]
+    fn with_and_without_filters() {
+        let mut world = world();
+        let plain = spawn(&mut world, Some(1), None, false);
+        let marked = spawn(&mut world, Some(2), None, true);
+        let only_marker = spawn(&mut world, None, None, true);
+
+        assert_eq!(collect::<&A, With<Marker>>(&world), [marked]);
+        assert_eq!(collect::<&A, Without<Marker>>(&world), [plain]);
+        assert_eq!(
+            collect::<Option<&A>, With<Marker>>(&world),
+            [marked, only_marker]
+        );
+        assert!(
+            world
+                .query_filtered::<&A, (With<Marker>, With<B>)>()
+                .is_empty()
+        );
+        assert!(!world.query_filtered::<&A, With<Marker>>().is_empty());
+    }
+
+    #[test]
+    fn small_filter_drives_a_large_query() {
+        let mut world = world();
+        for i in 0..50 {
+            spawn(&mut world, Some(i), None, false);
+        }
+        let marked = spawn(&mut world, Some(99), None, true);
+        let dead = spawn(&mut world, Some(7), None, true);
+        world.despawn(dead);
+
+        let mut values = Vec::new();
+        world
+            .query_filtered::<&mut A, With<Marker>>()
+            .for_each(|entity, a| {
+                a.0 += 1;
+                values.push((entity, a.0));
+            });
+
+        assert_eq!(values, [(marked, 100)]);
+        assert_eq!(world.get::<A>(marked).map(|a| a.0), Some(100));
+    }
+
+    #[test]
+    fn failed_joins_do_not_mark_mutable_terms_changed() {
+        let mut world = world();
+        let lonely = spawn(&mut world, Some(1), None, false);
+        let paired = spawn(&mut world, Some(2), Some(20), false);
+        // Enough `B`s that `A` drives the join.
+        for i in 0..3 {
+            spawn(&mut world, None, Some(i), false);
+        }
+        world.advance_tick();
+
+        world.query::<(&mut A, &B)>().for_each(|_, _| {});
+        assert!(world.query::<(&mut A, &B)>().get(lonely).is_none());
+
+        assert_eq!(collect::<&A, Changed<A>>(&world), [paired]);
+    }
+
+    #[test]
+    #[should_panic(expected = "borrowed")]
+    fn mutable_aliasing_panics() {
+        let world = world();
+        world.query::<(&mut A, &A)>().count();
+    }
+
+    #[test]
+    fn advance_tick_moves_the_change_window() {
+        let mut world = world();
+        let old = spawn(&mut world, Some(1), None, false);
+        world.advance_tick();
+        let frame = world.change_tick();
+
+        let new = spawn(&mut world, Some(2), None, false);
+        *world.get_mut::<A>(old).unwrap() = A(10);
+
+        assert_eq!(collect::<&A, Added<A>>(&world), [new]);
+        assert_eq!(collect::<&A, Changed<A>>(&world), [old, new]);
+
+        // Last frame's changes are not reported again.
+        world.advance_tick();
+        assert!(world.query_filtered::<&A, Changed<A>>().is_empty());
+        assert!(world.query_filtered::<&A, Added<A>>().is_empty());
+
+        // Reading is not a change; a mutable query is, for everything it
+        // visits.
+        world.query::<&A>().for_each(|_, _| {});
+        assert!(world.query_filtered::<&A, Changed<A>>().is_empty());
+
+        world.query::<&mut A>().for_each(|_, _| {});
+        assert_eq!(collect::<&A, Changed<A>>(&world), [old, new]);
+        assert!(world.query_filtered::<&A, Added<A>>().is_empty());
+
+        // `since` widens the window back to an earlier tick.
+        let mut added = Vec::new();
+        world
+            .query_filtered::<&A, Added<A>>()
+            .since(frame)
+            .for_each(|entity, _| added.push(entity));
+        assert_eq!(added, [new]);
+    }
+
+    #[test]
+    #[should_panic(expected = "borrowed")]
+    fn mutable_term_and_filter_on_the_same_component_panics() {
+        let world = world();
+        world.query_filtered::<&mut A, With<A>>().count();
+    }
+}
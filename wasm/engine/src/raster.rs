@@ -0,0 +1,665 @@
+//! CPU rasterizer for screenshots and golden-image tests.
+//!
+//! Draws what the GPU path draws, from the same inputs: the instance
+//! streams and batches of `RenderInstances`, the `Meshes`, `ViewProj`,
+//! the `LightBuffer` and the `Sky`. Shading is simpler (Lambert diffuse
+//! plus ambient and emission, no specular or fog), so images match the
+//! browser in layout and coverage rather than exactly in color.
+
+use std::ops::Range;
+
+use crate::light::{LIGHT_HEADER, LIGHT_STRIDE, LightBuffer, LightKind};
+use crate::math::{Mat4, Vec3, Vec4};
+use crate::mesh::{MeshData, Meshes, VERTEX_STRIDE};
+use crate::png;
+use crate::render::{
+    INSTANCE_FLAG_UNLIT, INSTANCE_STRIDE, InstanceStream, RenderInstances, StreamKind,
+};
+use crate::sky::Sky;
+use crate::world::World;
+use crate::{Engine, ViewProj};
+
+/// An 8-bit RGBA image, rows from the top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        self.pixels[i..i + 4].try_into().unwrap()
+    }
+    pub fn to_png(&self) -> Vec<u8> {
+        png::encode_rgba(self.width, self.height, &self.pixels)
+    }
+}
+
+impl Engine {
+    /// Renders the scene as extracted by the last `update`. The camera
+    /// aspect should match `width / height`.
+    pub fn render_image(&self, width: u32, height: u32) -> Image {
+        render(&self.world, width, height)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Vertex {
+    clip: Vec4,
+    world: Vec3,
+    normal: Vec3,
+}
+
+impl Vertex {
+    fn lerp(self, other: Vertex, t: f32) -> Vertex {
+        Vertex {
+            clip: self.clip + (other.clip - self.clip) * t,
+            world: self.world.lerp(other.world, t),
+            normal: self.normal.lerp(other.normal, t),
+        }
+    }
+}
+
+/// What one instance slot is drawn with.
+struct Surface {
+    base: [f32; 4],
+    emission: Vec3,
+    unlit: bool,
+}
+
+struct Target {
+    width: u32,
+    height: u32,
+    color: Vec<Vec3>,
+    depth: Vec<f32>,
+}
+
+struct Lights<'a> {
+    records: &'a [f32],
+    ambient: f32,
+}
+
+impl Lights<'_> {
+    fn shade(&self, surface: &Surface, world: Vec3, normal: Vec3) -> Vec3 {
+        let base = Vec3::new(surface.base[0], surface.base[1], surface.base[2]);
+
+        if surface.unlit {
+            return base + surface.emission;
+        }
+
+        let mut diffuse = Vec3::splat(self.ambient);
+
+        for light in self.records.chunks_exact(LIGHT_STRIDE) {
+            let position = Vec3::new(light[0], light[1], light[2]);
+            let direction = Vec3::new(light[4], light[5], light[6]);
+            let range = light[7];
+            let color = Vec3::new(light[8], light[9], light[10]);
+
+            // Same falloff as the WGSL shader.
+            let (to_light, attenuation) = if light[3].to_bits() == LightKind::Directional as u32 {
+                (-direction, 1.0)
+            } else {
+                let offset = position - world;
+                let d = offset.length();
+                let cutoff = (1.0 - (d / range.max(1e-4)).powi(4)).clamp(0.0, 1.0);
+                let mut attenuation = cutoff * cutoff / (1.0 + d * d);
+                let to_light = offset / d.max(1e-4);
+
+                if light[3].to_bits() == LightKind::Spot as u32 {
+                    attenuation *= smoothstep(light[13], light[12], (-to_light).dot(direction));
+                }
+                (to_light, attenuation)
+            };
+
+            diffuse += color * (light[11] * attenuation * normal.dot(to_light).max(0.0));
+        }
+
+        Vec3::new(base.x * diffuse.x, base.y * diffuse.y, base.z * diffuse.z) + surface.emission
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn surface(instance: &[f32]) -> Surface {
+    let flags = instance[26].to_bits();
+
+    Surface {
+        base: instance[16..20].try_into().unwrap(),
+        emission: Vec3::new(instance[20], instance[21], instance[22]) * instance[23],
+        unlit: flags & INSTANCE_FLAG_UNLIT != 0,
+    }
+}
+
+/// Clips a triangle against the near plane (`z >= -w`), leaving a
+/// convex polygon of up to four vertices.
+fn clip_near(triangle: [Vertex; 3]) -> Vec<Vertex> {
+    let distance = |v: &Vertex| v.clip.z + v.clip.w;
+    let mut out = Vec::with_capacity(4);
+
+    for i in 0..3 {
+        let a = triangle[i];
+        let b = triangle[(i + 1) % 3];
+        let (da, db) = (distance(&a), distance(&b));
+
+        if da >= 0.0 {
+            out.push(a);
+        }
+        if (da >= 0.0) != (db >= 0.0) {
+            out.push(a.lerp(b, da / (da - db)));
+        }
+    }
+    out
+}
+
+impl Target {
+    fn new(width: u32, height: u32, clear: Vec3) -> Self {
+        let len = width as usize * height as usize;
+
+        Target {
+            width,
+            height,
+            color: vec![clear; len],
+            depth: vec![1.0; len],
+        }
+    }
+
+    /// Rasterizes one clipped triangle: depth tested against `less`,
+    /// back faces culled, attributes interpolated perspective-correctly.
+    /// Transparent surfaces blend over the target without writing depth.
+    fn triangle(&mut self, vertices: [&Vertex; 3], surface: &Surface, lights: &Lights) {
+        let (w, h) = (self.width as f32, self.height as f32);
+
+        // Screen x, y in pixels (y down), depth in 0..1 and 1/w.
+        let screen = vertices.map(|v| {
+            let inv_w = 1.0 / v.clip.w;
+            Vec4::new(
+                (v.clip.x * inv_w * 0.5 + 0.5) * w,
+                (0.5 - v.clip.y * inv_w * 0.5) * h,
+                v.clip.z * inv_w * 0.5 + 0.5,
+                inv_w,
+            )
+        });
+        let edge =
+            |a: Vec4, b: Vec4, x: f32, y: f32| (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x);
+
+        // Counter-clockwise in clip space is clockwise with y down.
+        let area = edge(screen[0], screen[1], screen[2].x, screen[2].y);
+        if area >= 0.0 {
+            return;
+        }
+
+        let span = |axis: fn(&Vec4) -> f32, size: u32| -> Range<u32> {
+            let lo = screen.iter().map(axis).fold(f32::INFINITY, f32::min);
+            let hi = screen.iter().map(axis).fold(f32::NEG_INFINITY, f32::max);
+            (lo.floor().max(0.0) as u32)..(hi.ceil().min(size as f32).max(0.0) as u32)
+        };
+        let transparent = surface.base[3] < 1.0;
+
+        for y in span(|v| v.y, self.height) {
+            for x in span(|v| v.x, self.width) {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+
+                let b0 = edge(screen[1], screen[2], px, py) / area;
+                let b1 = edge(screen[2], screen[0], px, py) / area;
+                let b2 = edge(screen[0], screen[1], px, py) / area;
+
+                if b0 < 0.0 || b1 < 0.0 || b2 < 0.0 {
+                    continue;
+                }
+
+                let depth = b0 * screen[0].z + b1 * screen[1].z + b2 * screen[2].z;
+                let i = y as usize * self.width as usize + x as usize;
+
+                if !(0.0..1.0).contains(&depth) || depth >= self.depth[i] {
+                    continue;
+                }
+
+                // Barycentrics weighted by 1/w, for attributes.
+                let (p0, p1, p2) = (b0 * screen[0].w, b1 * screen[1].w, b2 * screen[2].w);
+                let sum = p0 + p1 + p2;
+                let (p0, p1, p2) = (p0 / sum, p1 / sum, p2 / sum);
+
+                let world =
+                    vertices[0].world * p0 + vertices[1].world * p1 + vertices[2].world * p2;
+                let normal =
+                    (vertices[0].normal * p0 + vertices[1].normal * p1 + vertices[2].normal * p2)
+                        .normalize();
+                let color = lights.shade(surface, world, normal);
+
+                if transparent {
+                    let alpha = surface.base[3];
+                    self.color[i] = self.color[i].lerp(color, alpha);
+                } else {
+                    self.color[i] = color;
+                    self.depth[i] = depth;
+                }
+            }
+        }
+    }
+
+    fn mesh(
+        &mut self,
+        mesh: &MeshData,
+        model: &Mat4,
+        view_proj: &Mat4,
+        surface: &Surface,
+        lights: &Lights,
+    ) {
+        let vertices: Vec<Vertex> = mesh
+            .vertices
+            .chunks_exact(VERTEX_STRIDE)
+            .map(|v| {
+                let world = model.transform_point3(Vec3::new(v[0], v[1], v[2]));
+                Vertex {
+                    clip: *view_proj * world.extend(1.0),
+                    world,
+                    normal: model
+                        .transform_vector3(Vec3::new(v[3], v[4], v[5]))
+                        .normalize(),
+                }
+            })
+            .collect();
+
+        for triangle in mesh.indices.chunks_exact(3) {
+            let triangle = [0, 1, 2].map(|k| vertices[triangle[k] as usize]);
+            let polygon = clip_near(triangle);
+
+            for k in 1..polygon.len().saturating_sub(1) {
+                self.triangle([&polygon[0], &polygon[k], &polygon[k + 1]], surface, lights);
+            }
+        }
+    }
+
+    /// Draws the batches of `stream` starting inside `range` of its
+    /// visible list.
+    fn stream(
+        &mut self,
+        stream: &InstanceStream,
+        range: Range<u32>,
+        meshes: &Meshes,
+        view_proj: &Mat4,
+        lights: &Lights,
+    ) {
+        for batch in stream
+            .batches()
+            .iter()
+            .filter(|b| range.contains(&b.offset))
+        {
+            let Some(mesh) = meshes.get(batch.mesh) else {
+                continue;
+            };
+            let visible =
+                &stream.visible()[batch.offset as usize..(batch.offset + batch.count) as usize];
+
+            for &slot in visible {
+                let instance = &stream.data()[slot as usize * INSTANCE_STRIDE..][..INSTANCE_STRIDE];
+                let model = Mat4(instance[..16].try_into().unwrap());
+
+                self.mesh(mesh, &model, view_proj, &surface(instance), lights);
+            }
+        }
+    }
+}
+
+/// Renders the extracted scene into a `width` x `height` image: opaque
+/// instances of both streams, then transparent ones in their sorted
+/// order, over the sky's horizon color.
+pub fn render(world: &World, width: u32, height: u32) -> Image {
+    let sky = *world.resource::<Sky>();
+    let view_proj = world.resource::<ViewProj>().matrix;
+    let render = world.resource::<RenderInstances>();
+    let meshes = world.resource::<Meshes>();
+    let light_buffer = world.resource::<LightBuffer>();
+
+    let lights = Lights {
+        records: &light_buffer.data()[LIGHT_HEADER..],
+        ambient: sky.ambient,
+    };
+    let mut target = Target::new(width, height, Vec3::from(sky.horizon));
+
+    for kind in StreamKind::ALL {
+        let stream = render.stream(kind);
+        target.stream(stream, stream.opaque_range(), &meshes, &view_proj, &lights);
+    }
+    for kind in StreamKind::ALL {
+        let stream = render.stream(kind);
+        target.stream(
+            stream,
+            stream.transparent_range(),
+            &meshes,
+            &view_proj,
+            &lights,
+        );
+    }
+
+    let pixels = target
+        .color
+        .iter()
+        .flat_map(|c| {
+            let [r, g, b] = c
+                .to_array()
+                .map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+            [r, g, b, 255]
+        })
+        .collect();
+
+    Image {
+        width,
+        height,
+        pixels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::mesh::MeshShape;
+
+    const WIDTH: u32 = 120;
+    const HEIGHT: u32 = 80;
+
+    /// Engine with a fixed camera and a frozen mid-morning sun.
+    fn scene() -> Engine {
+        let mut engine = Engine::new();
+        engine.set_camera(0.0, 2.5, 4.5, 1.0, WIDTH as f32 / HEIGHT as f32, 0.1, 100.0);
+        engine.set_time_of_day(10.0);
+        engine.set_day_speed(0.0);
+
+        let sun = engine.create_entity().unwrap();
+        engine.add_transform(sun, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        engine.set_sun(sun);
+        // Lights only; entities without a mesh would draw as cubes.
+        engine.set_hidden(sun, true);
+
+        let ground = engine.create_entity().unwrap();
+        engine.add_transform(ground, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0);
+        engine.set_scale(ground, 20.0, 0.1, 20.0);
+        engine.add_color(ground, 0.35, 0.37, 0.40);
+        engine.set_static(ground);
+
+        engine
+    }
+
+    /// Largest per-channel difference from a golden that still passes,
+    /// so rounding differences between platforms do not fail the tests.
+    const GOLDEN_TOLERANCE: u8 = 2;
+
+    /// Reads back a PNG written by [`png::encode_rgba`]: RGBA8, unfiltered
+    /// scanlines, stored deflate blocks. Panics on anything else.
+    fn decode_png(bytes: &[u8]) -> Image {
+        assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n", "not a PNG");
+
+        let (mut width, mut height) = (0, 0);
+        let mut zlib = Vec::new();
+        let mut rest = &bytes[8..];
+
+        while rest.len() >= 12 {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let (kind, data) = (&rest[4..8], &rest[8..8 + len]);
+
+            match kind {
+                b"IHDR" => {
+                    width = u32::from_be_bytes(data[..4].try_into().unwrap());
+                    height = u32::from_be_bytes(data[4..8].try_into().unwrap());
+                    assert_eq!(data[8..], [8, 6, 0, 0, 0], "unsupported PNG format");
+                }
+                b"IDAT" => zlib.extend_from_slice(data),
+                _ => {}
+            }
+            rest = &rest[12 + len..];
+        }
+
+        // Skip the zlib header, then copy each stored block out.
+        let mut raw = Vec::new();
+        let mut at = 2;
+        loop {
+            let last = zlib[at] & 1 != 0;
+            assert_eq!(zlib[at] & 6, 0, "compressed deflate block");
+            let len = u16::from_le_bytes([zlib[at + 1], zlib[at + 2]]) as usize;
+            raw.extend_from_slice(&zlib[at + 5..at + 5 + len]);
+            at += 5 + len;
+            if last {
+                break;
+            }
+        }
+
+        let row = width as usize * 4;
+        let pixels = raw
+            .chunks(row + 1)
+            .take(height as usize)
+            .flat_map(|line| {
+                assert_eq!(line[0], 0, "filtered scanline");
+                line[1..].iter().copied()
+            })
+            .collect();
+
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Where `image` and `golden` differ by more than
+    /// [`GOLDEN_TOLERANCE`] in any channel, starting with the first
+    /// differing pixel, or `None` if they match.
+    fn golden_mismatch(image: &Image, golden: &Image) -> Option<String> {
+        if (golden.width, golden.height) != (image.width, image.height) {
+            return Some(format!(
+                "is {}x{}, golden is {}x{}",
+                image.width, image.height, golden.width, golden.height
+            ));
+        }
+
+        let differs = |&i: &usize| {
+            let range = i * 4..i * 4 + 4;
+            image.pixels[range.clone()]
+                .iter()
+                .zip(&golden.pixels[range])
+                .any(|(a, b)| a.abs_diff(*b) > GOLDEN_TOLERANCE)
+        };
+        let mut bad = (0..image.pixels.len() / 4).filter(differs);
+        let first = bad.next()? as u32;
+        let (x, y) = (first % image.width, first / image.width);
+
+        Some(format!(
+            "pixel ({x}, {y}) is {:?}, golden is {:?}; {} pixels differ",
+            image.pixel(x, y),
+            golden.pixel(x, y),
+            bad.count() + 1
+        ))
+    }
+
+    /// Compares `image` with `tests/golden/<name>.png`, allowing each
+    /// channel to be off by [`GOLDEN_TOLERANCE`]. Set `UPDATE_GOLDEN=1`
+    /// to write the current output instead; on a mismatch it is saved
+    /// next to the golden as `<name>.actual.png`.
+    fn assert_golden(name: &str, image: &Image) {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+        let path = dir.join(format!("{name}.png"));
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(&path, image.to_png()).unwrap();
+            return;
+        }
+
+        let golden = std::fs::read(&path)
+            .unwrap_or_else(|_| panic!("missing {}; run with UPDATE_GOLDEN=1", path.display()));
+
+        if let Some(mismatch) = golden_mismatch(image, &decode_png(&golden)) {
+            let actual = dir.join(format!("{name}.actual.png"));
+            std::fs::write(&actual, image.to_png()).unwrap();
+            panic!(
+                "{name} differs from its golden: {mismatch}; see {}",
+                actual.display()
+            );
+        }
+    }
+
+    #[test]
+    fn goldens_are_compared_within_the_tolerance() {
+        let golden = Image {
+            width: 3,
+            height: 2,
+            pixels: (0..24).map(|i| i * 10).collect(),
+        };
+        let decoded = decode_png(&golden.to_png());
+        assert_eq!((decoded.width, decoded.height), (3, 2));
+        assert_eq!(decoded.pixels, golden.pixels);
+
+        let mut image = Image {
+            pixels: golden.pixels.clone(),
+            ..golden
+        };
+        image.pixels[5] += GOLDEN_TOLERANCE;
+        image.pixels[17] -= GOLDEN_TOLERANCE;
+        assert_eq!(golden_mismatch(&image, &golden), None);
+
+        image.pixels[22] += GOLDEN_TOLERANCE + 1;
+        image.pixels[9] -= GOLDEN_TOLERANCE + 1;
+        assert_eq!(
+            golden_mismatch(&image, &golden).unwrap(),
+            "pixel (2, 0) is [80, 87, 100, 110], golden is [80, 90, 100, 110]; 2 pixels differ"
+        );
+
+        let small = Image {
+            width: 1,
+            height: 1,
+            pixels: vec![0; 4],
+        };
+        assert_eq!(
+            golden_mismatch(&small, &golden).unwrap(),
+            "is 1x1, golden is 3x2"
+        );
+    }
+
+    #[test]
+    fn empty_scene_clears_to_the_horizon() {
+        let mut engine = Engine::new();
+        engine.update(0.0);
+
+        let image = engine.render_image(4, 2);
+        let horizon = Sky::default().horizon.map(|c| (c * 255.0).round() as u8);
+
+        assert!(
+            image
+                .pixels
+                .chunks(4)
+                .all(|p| p[..3] == horizon && p[3] == 255)
+        );
+    }
+
+    #[test]
+    fn cube_is_drawn_with_a_shade_per_visible_face() {
+        let mut engine = scene();
+        let cube = engine.create_entity().unwrap();
+        engine.add_transform(cube, 0.0, 0.0, 0.0, 0.0, 0.6, 0.0);
+        engine.set_scale(cube, 2.0, 2.0, 2.0);
+        engine.add_color(cube, 1.0, 1.0, 1.0);
+        engine.update(0.0);
+        let with_cube = engine.render_image(WIDTH, HEIGHT);
+
+        engine.set_hidden(cube, true);
+        engine.update(0.0);
+        let without = engine.render_image(WIDTH, HEIGHT);
+
+        assert_ne!(
+            with_cube.pixel(WIDTH / 2, HEIGHT / 2),
+            without.pixel(WIDTH / 2, HEIGHT / 2)
+        );
+
+        // Flat faces, each lit at its own angle: the top and two sides.
+        let mut shades: Vec<&[u8]> = with_cube
+            .pixels
+            .chunks(4)
+            .zip(without.pixels.chunks(4))
+            .filter(|(a, b)| a != b)
+            .map(|(a, _)| a)
+            .collect();
+        shades.sort();
+        shades.dedup();
+
+        assert_eq!(shades.len(), 3, "{shades:?}");
+    }
+
+    #[test]
+    fn golden_shapes() {
+        let mut engine = scene();
+
+        for (i, shape) in MeshShape::ALL.into_iter().enumerate() {
+            let (x, z) = ((i % 4) as f32 * 1.4 - 2.1, (i / 4) as f32 * 1.6 - 0.8);
+            let entity = engine.create_entity().unwrap();
+            engine.add_transform(entity, x, 0.0, z, 0.3, 0.5, 0.0);
+            engine.set_mesh(entity, shape as u32);
+            engine.add_color(entity, 0.3 + 0.1 * i as f32, 0.8, 0.4);
+        }
+        engine.update(0.0);
+
+        assert_golden("shapes", &engine.render_image(WIDTH, HEIGHT));
+    }
+
+    #[test]
+    fn golden_depth_and_transparency() {
+        let mut engine = scene();
+
+        // Interpenetrating cube and sphere, behind a transparent panel.
+        let cube = engine.create_entity().unwrap();
+        engine.add_transform(cube, -0.3, 0.0, 0.0, 0.0, 0.7, 0.0);
+        engine.set_scale(cube, 1.5, 1.5, 1.5);
+        engine.add_color(cube, 0.9, 0.3, 0.2);
+
+        let sphere = engine.create_entity().unwrap();
+        engine.add_transform(sphere, 0.5, 0.2, 0.3, 0.0, 0.0, 0.0);
+        engine.set_scale(sphere, 1.4, 1.4, 1.4);
+        engine.set_mesh(sphere, MeshShape::UvSphere as u32);
+        engine.add_color(sphere, 0.2, 0.5, 0.9);
+
+        for (x, alpha) in [(-1.0, 0.3), (1.0, 0.7)] {
+            let panel = engine.create_entity().unwrap();
+            engine.add_transform(panel, x, 0.2, 1.8, 0.0, 0.0, 0.0);
+            engine.set_scale(panel, 1.6, 1.2, 0.05);
+            engine.set_material(panel, 1.0, 1.0, 0.3, alpha, 0.0, 0.5);
+        }
+        engine.update(0.0);
+
+        assert_golden(
+            "depth_and_transparency",
+            &engine.render_image(WIDTH, HEIGHT),
+        );
+    }
+
+    #[test]
+    fn golden_point_and_spot_lights_at_night() {
+        let mut engine = scene();
+        engine.set_time_of_day(0.0);
+
+        let lamp = engine.create_entity().unwrap();
+        engine.add_transform(lamp, -1.5, 0.5, 0.0, 0.0, 0.0, 0.0);
+        engine.add_point_light(lamp, 1.0, 0.6, 0.2, 8.0, 6.0);
+
+        let spot = engine.create_entity().unwrap();
+        engine.add_transform(spot, 1.5, 2.5, 0.0, 0.0, 0.0, 0.0);
+        engine.add_spot_light(spot, 0.3, 0.6, 1.0, 20.0, 8.0, 0.3, 0.5);
+        engine.set_light_direction(spot, 0.0, -1.0, 0.0);
+
+        let target = engine.create_entity().unwrap();
+        engine.add_transform(target, 0.0, -0.5, 0.0, 0.0, 0.0, 0.0);
+        engine.set_mesh(target, MeshShape::Torus as u32);
+        engine.set_scale(target, 2.0, 2.0, 2.0);
+        engine.add_color(target, 0.9, 0.9, 0.9);
+
+        engine.set_hidden(lamp, true);
+        engine.set_hidden(spot, true);
+        engine.update(0.0);
+
+        assert_golden("night_lights", &engine.render_image(WIDTH, HEIGHT));
+    }
+}
@@ -0,0 +1,730 @@
+use std::ops::Range;
+
+use wasm_bindgen::prelude::*;
+
+use crate::ViewProj;
+use crate::bounds::WorldBounds;
+use crate::camera::{Camera, Frustum};
+use crate::components::{Color, Hidden, Material, Mesh, Selected, Static};
+use crate::hierarchy::GlobalTransform;
+use crate::math::Mat4;
+use crate::query::{Changed, Without};
+use crate::world::{Entity, World};
+
+/// Bumped whenever the instance layout below changes, so JS can refuse
+/// to draw a buffer its shader does not understand.
+pub const INSTANCE_LAYOUT_VERSION: u32 = 2;
+
+/// Floats per instance, laid out for a WGSL struct of 16-byte aligned
+/// fields:
+///
+/// | floats | field |
+/// |--------|-------|
+/// | 0..16  | column-major model matrix |
+/// | 16..20 | base color RGBA |
+/// | 20..24 | emissive RGB, emissive strength |
+/// | 24     | metallic |
+/// | 25     | roughness |
+/// | 26     | `INSTANCE_FLAG_*` bits, stored as a `u32` |
+/// | 27     | padding |
+pub const INSTANCE_STRIDE: usize = 28;
+
+/// Draw without lighting (`Material::unlit`).
+pub const INSTANCE_FLAG_UNLIT: u32 = 1;
+
+/// The instance streams JS uploads separately. Entities marked `Static`
+/// go to `Static`, so their slots are written once and then left alone.
+/// Transparent entities always go to `Dynamic`, so a single sorted range
+/// holds all of them.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StreamKind {
+    Static = 0,
+    Dynamic = 1,
+}
+
+impl StreamKind {
+    pub const ALL: [StreamKind; 2] = [StreamKind::Static, StreamKind::Dynamic];
+}
+
+/// A run of `visible` drawn with one mesh: one instanced draw call.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Batch {
+    pub mesh: Mesh,
+    /// First entry of `visible`, i.e. the draw's first instance.
+    pub offset: u32,
+    pub count: u32,
+}
+
+/// Instance data with a persistent slot per entity.
+///
+/// An entity keeps its slot for as long as it is extracted into this
+/// stream, so unchanged instances never move and never need uploading
+/// again. Freed slots are reused; the buffer never shrinks. Which slots
+/// to draw is given separately by `visible`.
+#[derive(Default)]
+pub struct InstanceStream {
+    data: Vec<f32>,
+    // Entity in each slot, `None` for free slots.
+    slot_entities: Vec<Option<Entity>>,
+    // Slot of each entity, by entity index.
+    slots: Vec<Option<u32>>,
+    free: Vec<u32>,
+    // Slots written since `begin`; the rest are released by `end`.
+    seen: Vec<bool>,
+    // Float ranges written since the last `take_dirty_ranges`.
+    dirty: Vec<Range<usize>>,
+    visible: Vec<u32>,
+    visible_dirty: bool,
+    // Index into `visible` where the transparent instances start.
+    transparent_start: u32,
+    batches: Vec<Batch>,
+}
+
+impl InstanceStream {
+    pub fn data(&self) -> &[f32] {
+        &self.data
+    }
+    /// Indices of the slots to draw: the opaque ones grouped by mesh,
+    /// then the transparent ones back to front.
+    pub fn visible(&self) -> &[u32] {
+        &self.visible
+    }
+    /// Entries of `visible` holding opaque instances.
+    pub fn opaque_range(&self) -> Range<u32> {
+        0..self.transparent_start
+    }
+    /// Entries of `visible` holding transparent instances, farthest first.
+    pub fn transparent_range(&self) -> Range<u32> {
+        self.transparent_start..self.visible.len() as u32
+    }
+    /// The runs of `visible` sharing a mesh: opaque ones in mesh order,
+    /// then transparent ones in draw order. No batch spans both ranges.
+    pub fn batches(&self) -> &[Batch] {
+        &self.batches
+    }
+    pub fn slot(&self, entity: Entity) -> Option<u32> {
+        let slot = self.slots.get(entity.index() as usize).copied().flatten()?;
+        (self.slot_entities[slot as usize] == Some(entity)).then_some(slot)
+    }
+
+    /// Starts an extraction pass. Every entity still in the stream must be
+    /// `write`n again before `end`.
+    pub fn begin(&mut self) {
+        self.seen.fill(false);
+    }
+    /// Stores `instance` in the entity's slot, allocating one if needed,
+    /// and returns the slot. Only marks it dirty if the contents differ.
+    pub fn write(&mut self, entity: Entity, instance: &[f32; INSTANCE_STRIDE]) -> u32 {
+        let slot = match self.slot(entity) {
+            Some(slot) => slot,
+            None => self.allocate(entity),
+        };
+
+        let range = slot as usize * INSTANCE_STRIDE..(slot as usize + 1) * INSTANCE_STRIDE;
+
+        if self.data[range.clone()] != instance[..] {
+            self.data[range.clone()].copy_from_slice(instance);
+            self.dirty.push(range);
+        }
+
+        self.seen[slot as usize] = true;
+        slot
+    }
+    /// Releases the slots of entities not written since `begin`.
+    pub fn end(&mut self) {
+        for slot in 0..self.slot_entities.len() {
+            if !self.seen[slot]
+                && let Some(entity) = self.slot_entities[slot].take()
+            {
+                // A newer entity with the same index may own the entry now.
+                let entry = &mut self.slots[entity.index() as usize];
+                if *entry == Some(slot as u32) {
+                    *entry = None;
+                }
+                self.free.push(slot as u32);
+            }
+        }
+    }
+    fn allocate(&mut self, entity: Entity) -> u32 {
+        let slot = self.free.pop().unwrap_or_else(|| {
+            self.data.resize(self.data.len() + INSTANCE_STRIDE, 0.0);
+            self.slot_entities.push(None);
+            self.seen.push(false);
+            self.slot_entities.len() as u32 - 1
+        });
+
+        let index = entity.index() as usize;
+        if index >= self.slots.len() {
+            self.slots.resize(index + 1, None);
+        }
+
+        self.slots[index] = Some(slot);
+        self.slot_entities[slot as usize] = Some(entity);
+
+        // A reused slot may hold identical floats from its previous owner;
+        // upload it regardless.
+        self.dirty
+            .push(slot as usize * INSTANCE_STRIDE..(slot as usize + 1) * INSTANCE_STRIDE);
+
+        slot
+    }
+    /// Replaces the visible list with the opaque `(mesh, slot)` pairs,
+    /// grouped by mesh (keeping their order within a mesh), followed by
+    /// the transparent `(depth, mesh, slot)` triples sorted by decreasing
+    /// view depth, and rebuilds the batches over it.
+    pub fn set_visible(
+        &mut self,
+        mut opaque: Vec<(Mesh, u32)>,
+        mut transparent: Vec<(f32, Mesh, u32)>,
+    ) {
+        opaque.sort_by_key(|&(mesh, _)| mesh);
+        transparent.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        self.transparent_start = opaque.len() as u32;
+        self.batches.clear();
+
+        let visible: Vec<(Mesh, u32)> = opaque
+            .into_iter()
+            .chain(transparent.into_iter().map(|(_, mesh, slot)| (mesh, slot)))
+            .collect();
+
+        for (i, &(mesh, _)) in visible.iter().enumerate() {
+            match self.batches.last_mut() {
+                // Sorted transparent instances only merge with their own
+                // neighbours.
+                Some(batch) if batch.mesh == mesh && i as u32 != self.transparent_start => {
+                    batch.count += 1
+                }
+                _ => self.batches.push(Batch {
+                    mesh,
+                    offset: i as u32,
+                    count: 1,
+                }),
+            }
+        }
+
+        if !visible
+            .iter()
+            .map(|&(_, slot)| slot)
+            .eq(self.visible.iter().copied())
+        {
+            self.visible = visible.into_iter().map(|(_, slot)| slot).collect();
+            self.visible_dirty = true;
+        }
+    }
+
+    /// Byte ranges of `data` written since the last call, sorted and
+    /// merged, as `[offset, length]` pairs.
+    pub fn take_dirty_ranges(&mut self) -> Vec<u32> {
+        let mut ranges = std::mem::take(&mut self.dirty);
+        ranges.sort_by_key(|r| r.start);
+
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+
+        let bytes = size_of::<f32>();
+
+        merged
+            .into_iter()
+            .flat_map(|r| [(r.start * bytes) as u32, (r.len() * bytes) as u32])
+            .collect()
+    }
+    /// Whether `visible` changed since the last call.
+    pub fn take_visible_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.visible_dirty)
+    }
+}
+
+/// Output of render extraction: one `InstanceStream` per `StreamKind`.
+#[derive(Default)]
+pub struct RenderInstances {
+    pub streams: [InstanceStream; 2],
+    /// Instances drawn and skipped by the last frustum test.
+    pub visible: u32,
+    pub culled: u32,
+    // Matrix the last extraction was culled against.
+    view_proj: Option<Mat4>,
+}
+
+impl RenderInstances {
+    pub fn stream(&self, kind: StreamKind) -> &InstanceStream {
+        &self.streams[kind as usize]
+    }
+    pub fn stream_mut(&mut self, kind: StreamKind) -> &mut InstanceStream {
+        &mut self.streams[kind as usize]
+    }
+}
+
+/// Whether anything feeding the instance streams changed this frame.
+fn render_inputs_changed(world: &World) -> bool {
+    let since = world.change_tick();
+
+    !world
+        .query_filtered::<&GlobalTransform, Changed<GlobalTransform>>()
+        .is_empty()
+        || !world
+            .query_filtered::<&GlobalTransform, Changed<WorldBounds>>()
+            .is_empty()
+        || !world
+            .query_filtered::<&GlobalTransform, Changed<Color>>()
+            .is_empty()
+        || !world
+            .query_filtered::<&GlobalTransform, Changed<Material>>()
+            .is_empty()
+        || !world
+            .query_filtered::<&GlobalTransform, Changed<Selected>>()
+            .is_empty()
+        || !world
+            .query_filtered::<&GlobalTransform, Changed<Static>>()
+            .is_empty()
+        || !world
+            .query_filtered::<&GlobalTransform, Changed<Mesh>>()
+            .is_empty()
+        || !world
+            .query_filtered::<&GlobalTransform, Changed<Hidden>>()
+            .is_empty()
+        || world.storage::<GlobalTransform>().removed_since(since)
+        || world.storage::<Color>().removed_since(since)
+        || world.storage::<Material>().removed_since(since)
+        || world.storage::<Selected>().removed_since(since)
+        || world.storage::<Static>().removed_since(since)
+        || world.storage::<Mesh>().removed_since(since)
+        || world.storage::<Hidden>().removed_since(since)
+}
+
+/// The material an entity is drawn with: its `Material`, else one made
+/// from its `Color`, else the default blue.
+pub fn resolve_material(material: Option<&Material>, color: Option<&Color>) -> Material {
+    match (material, color) {
+        (Some(m), _) => *m,
+        (None, Some(c)) => Material::from_color(c.rgb),
+        (None, None) => Material::from_color([0.2, 0.7, 1.0]), // fallback default
+    }
+}
+
+fn instance(
+    global: &GlobalTransform,
+    material: Option<&Material>,
+    color: Option<&Color>,
+    selected: bool,
+) -> [f32; INSTANCE_STRIDE] {
+    let mut material = resolve_material(material, color);
+
+    if selected {
+        // brighten selected cubes slightly
+        for c in &mut material.base_color[..3] {
+            *c = (*c + 0.3).min(1.0);
+        }
+    }
+
+    let flags = if material.unlit {
+        INSTANCE_FLAG_UNLIT
+    } else {
+        0
+    };
+    let [er, eg, eb] = material.emissive;
+
+    let mut instance = [0.0; INSTANCE_STRIDE];
+    instance[..16].copy_from_slice(&global.matrix.0);
+    instance[16..20].copy_from_slice(&material.base_color);
+    instance[20..24].copy_from_slice(&[er, eg, eb, material.emissive_strength]);
+    instance[24] = material.metallic;
+    instance[25] = material.roughness;
+    instance[26] = f32::from_bits(flags);
+    instance
+}
+
+/// Writes every entity with a `GlobalTransform`, other than `Hidden`
+/// ones, into its stream's slot
+/// and rebuilds the visible lists and mesh batches from the camera
+/// frustum. Instances with a base alpha below 1 are transparent and
+/// sorted by their view depth. Skipped when
+/// neither the scene nor the camera changed.
+pub fn extract_instances(world: &mut World) {
+    // Nothing is culled until there is a camera to cull against.
+    let view_proj = world
+        .contains_resource::<Camera>()
+        .then(|| world.resource::<ViewProj>().matrix);
+
+    let mut render = world.resource_mut::<RenderInstances>();
+
+    if render.view_proj == view_proj && !render_inputs_changed(world) {
+        return;
+    }
+
+    let frustum = view_proj.as_ref().map(Frustum::from_view_proj);
+
+    render.view_proj = view_proj;
+    render.visible = 0;
+    render.culled = 0;
+
+    let RenderInstances {
+        streams,
+        visible: visible_count,
+        culled,
+        ..
+    } = &mut *render;
+
+    let materials = world.storage::<Material>();
+    let mut opaque = [Vec::new(), Vec::new()];
+    let mut transparent = [Vec::new(), Vec::new()];
+
+    for stream in streams.iter_mut() {
+        stream.begin();
+    }
+
+    world
+        .query_filtered::<(
+            &GlobalTransform,
+            Option<&WorldBounds>,
+            Option<&Color>,
+            Option<&Selected>,
+            Option<&Static>,
+            Option<&Mesh>,
+        ), Without<Hidden>>()
+        .for_each(
+            |entity, (global, bounds, color, selected, is_static, mesh)| {
+                let instance = instance(global, materials.get(entity), color, selected.is_some());
+                let is_transparent = instance[19] < 1.0;
+
+                let kind = match is_static {
+                    Some(_) if !is_transparent => StreamKind::Static,
+                    _ => StreamKind::Dynamic,
+                };
+
+                // Culled instances are still written, so their slot is current
+                // when they come back into view.
+                let slot = streams[kind as usize].write(entity, &instance);
+
+                if let Some(frustum) = &frustum
+                    && let Some(bounds) = bounds
+                    && !frustum.intersects_aabb(bounds.aabb.center(), bounds.aabb.half_extents())
+                {
+                    *culled += 1;
+                    return;
+                }
+
+                *visible_count += 1;
+
+                let mesh = mesh.copied().unwrap_or(Mesh::CUBE);
+
+                if is_transparent {
+                    let center = match bounds {
+                        Some(bounds) => bounds.aabb.center(),
+                        None => global.matrix.translation(),
+                    };
+                    // Clip w is the distance along the view direction.
+                    let depth = view_proj.map_or(0.0, |m| m.row(3).dot(center.extend(1.0)));
+
+                    transparent[kind as usize].push((depth, mesh, slot));
+                } else {
+                    opaque[kind as usize].push((mesh, slot));
+                }
+            },
+        );
+
+    for ((stream, opaque), transparent) in streams.iter_mut().zip(opaque).zip(transparent) {
+        stream.end();
+        stream.set_visible(opaque, transparent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bounds::Bounds;
+    use crate::math::Vec3;
+
+    fn instance_with(value: f32) -> [f32; INSTANCE_STRIDE] {
+        [value; INSTANCE_STRIDE]
+    }
+
+    fn slot_bytes(slot: u32) -> [u32; 2] {
+        let bytes = (INSTANCE_STRIDE * size_of::<f32>()) as u32;
+        [slot * bytes, bytes]
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register::<GlobalTransform>();
+        world.register::<WorldBounds>();
+        world.register::<Color>();
+        world.register::<Material>();
+        world.register::<Selected>();
+        world.register::<Static>();
+        world.register::<Mesh>();
+        world.register::<Hidden>();
+        world.insert_resource(RenderInstances::default());
+        world
+    }
+
+    /// Eye at +10 Z looking at the origin.
+    fn add_camera(world: &mut World) {
+        let camera = Camera {
+            target: Vec3::ZERO,
+            yaw: 0.0,
+            pitch: 0.0,
+            distance: 10.0,
+            fov: 1.0,
+            near: 0.1,
+            far: 100.0,
+            aspect: 1.0,
+        };
+
+        world.insert_resource(ViewProj {
+            matrix: camera.view_proj(),
+            eye: camera.eye(),
+        });
+        world.insert_resource(camera);
+    }
+
+    fn spawn_at(world: &mut World, position: Vec3, alpha: f32) -> Entity {
+        let matrix = Mat4::from_translation(position);
+        let mut material = Material::from_color([1.0, 0.5, 0.25]);
+        material.base_color[3] = alpha;
+
+        let entity = world.spawn().unwrap();
+        world.insert(entity, GlobalTransform { matrix });
+        world.insert(entity, WorldBounds::compute(&Bounds::default(), &matrix));
+        world.insert(entity, material);
+        entity
+    }
+
+    fn slots(world: &World, kind: StreamKind, entities: &[Entity]) -> Vec<Option<u32>> {
+        let render = world.resource::<RenderInstances>();
+        let stream = render.stream(kind);
+        entities.iter().map(|&e| stream.slot(e)).collect()
+    }
+
+    #[test]
+    fn released_slots_are_reused() {
+        let mut world = World::new();
+        let [a, b, c] = [(); 3].map(|_| world.spawn().unwrap());
+        let mut stream = InstanceStream::default();
+
+        stream.begin();
+        assert_eq!(stream.write(a, &instance_with(1.0)), 0);
+        assert_eq!(stream.write(b, &instance_with(2.0)), 1);
+        stream.end();
+
+        // `a` is not written this pass, so its slot is freed.
+        stream.begin();
+        stream.write(b, &instance_with(2.0));
+        stream.end();
+        assert_eq!(stream.slot(a), None);
+
+        // A new entity on the same index must not inherit the stale slot
+        // through the old handle.
+        world.despawn(a);
+        let reused = world.spawn().unwrap();
+        assert_eq!(reused.index(), a.index());
+
+        stream.begin();
+        stream.write(b, &instance_with(2.0));
+        assert_eq!(stream.write(c, &instance_with(3.0)), 0);
+        assert_eq!(stream.write(reused, &instance_with(4.0)), 2);
+        stream.end();
+
+        assert_eq!(stream.slot(a), None);
+        assert_eq!(stream.slot(c), Some(0));
+        assert_eq!(stream.data().len(), 3 * INSTANCE_STRIDE);
+    }
+
+    #[test]
+    fn only_touched_slots_are_dirty() {
+        let mut world = World::new();
+        let entities = [(); 4].map(|_| world.spawn().unwrap());
+        let mut stream = InstanceStream::default();
+
+        stream.begin();
+        for (i, &entity) in entities.iter().enumerate() {
+            stream.write(entity, &instance_with(i as f32));
+        }
+        stream.end();
+
+        // New slots are contiguous, so they merge into one range.
+        let [offset, len] = slot_bytes(0);
+        assert_eq!(stream.take_dirty_ranges(), [offset, len * 4]);
+
+        // Rewriting identical data uploads nothing.
+        stream.begin();
+        for (i, &entity) in entities.iter().enumerate() {
+            stream.write(entity, &instance_with(i as f32));
+        }
+        stream.end();
+        assert!(stream.take_dirty_ranges().is_empty());
+
+        stream.begin();
+        for (i, &entity) in entities.iter().enumerate() {
+            let value = if i == 2 { 9.0 } else { i as f32 };
+            stream.write(entity, &instance_with(value));
+        }
+        stream.end();
+        assert_eq!(stream.take_dirty_ranges(), slot_bytes(2));
+    }
+
+    #[test]
+    fn batches_group_instances_by_mesh() {
+        let mut stream = InstanceStream::default();
+        let (cube, sphere) = (Mesh::CUBE, Mesh(1));
+
+        stream.set_visible(
+            vec![(sphere, 0), (cube, 1), (sphere, 2), (cube, 3), (cube, 4)],
+            Vec::new(),
+        );
+
+        assert_eq!(stream.visible(), [1, 3, 4, 0, 2]);
+        assert_eq!(
+            stream.batches(),
+            [
+                Batch {
+                    mesh: cube,
+                    offset: 0,
+                    count: 3
+                },
+                Batch {
+                    mesh: sphere,
+                    offset: 3,
+                    count: 2
+                },
+            ]
+        );
+        assert!(stream.take_visible_dirty());
+
+        // Same draw order from differently ordered input: nothing to
+        // upload.
+        stream.set_visible(
+            vec![(cube, 1), (sphere, 0), (cube, 3), (sphere, 2), (cube, 4)],
+            Vec::new(),
+        );
+        assert!(!stream.take_visible_dirty());
+
+        stream.set_visible(vec![(cube, 1), (sphere, 0)], Vec::new());
+        assert!(stream.take_visible_dirty());
+        assert_eq!(stream.batches().len(), 2);
+    }
+
+    #[test]
+    fn transparent_batches_follow_depth_order() {
+        let mut stream = InstanceStream::default();
+        let (cube, sphere) = (Mesh::CUBE, Mesh(1));
+
+        stream.set_visible(
+            vec![(sphere, 0), (cube, 1)],
+            vec![(1.0, sphere, 2), (5.0, sphere, 3), (3.0, cube, 4)],
+        );
+
+        assert_eq!(stream.visible(), [1, 0, 3, 4, 2]);
+        assert_eq!(stream.opaque_range(), 0..2);
+        assert_eq!(stream.transparent_range(), 2..5);
+        assert_eq!(
+            stream.batches(),
+            [
+                Batch {
+                    mesh: cube,
+                    offset: 0,
+                    count: 1
+                },
+                Batch {
+                    mesh: sphere,
+                    offset: 1,
+                    count: 1
+                },
+                // Not merged with the opaque sphere before it, and kept
+                // apart from the nearer sphere by the cube between them.
+                Batch {
+                    mesh: sphere,
+                    offset: 2,
+                    count: 1
+                },
+                Batch {
+                    mesh: cube,
+                    offset: 3,
+                    count: 1
+                },
+                Batch {
+                    mesh: sphere,
+                    offset: 4,
+                    count: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn transparent_instances_move_to_the_dynamic_stream() {
+        let mut world = world();
+        let entity = spawn_at(&mut world, Vec3::ZERO, 1.0);
+        world.insert(entity, Static);
+
+        extract_instances(&mut world);
+        assert_eq!(slots(&world, StreamKind::Static, &[entity]), [Some(0)]);
+        assert_eq!(slots(&world, StreamKind::Dynamic, &[entity]), [None]);
+
+        world.advance_tick();
+        world.get_mut::<Material>(entity).unwrap().base_color[3] = 0.5;
+        extract_instances(&mut world);
+
+        let render = world.resource::<RenderInstances>();
+        let dynamic = render.stream(StreamKind::Dynamic);
+
+        assert_eq!(render.stream(StreamKind::Static).slot(entity), None);
+        assert!(render.stream(StreamKind::Static).visible().is_empty());
+        assert_eq!(dynamic.slot(entity), Some(0));
+        assert_eq!(dynamic.opaque_range(), 0..0);
+        assert_eq!(dynamic.transparent_range(), 0..1);
+    }
+
+    #[test]
+    fn transparent_instances_are_drawn_back_to_front() {
+        let mut world = world();
+        add_camera(&mut world);
+
+        let near = spawn_at(&mut world, Vec3::new(0.0, 0.0, 3.0), 0.5);
+        let far = spawn_at(&mut world, Vec3::new(0.0, 0.0, -5.0), 0.5);
+        let middle = spawn_at(&mut world, Vec3::new(1.0, 0.0, 0.0), 0.5);
+        let opaque = spawn_at(&mut world, Vec3::new(-1.0, 0.0, 0.0), 1.0);
+
+        extract_instances(&mut world);
+
+        let render = world.resource::<RenderInstances>();
+        let stream = render.stream(StreamKind::Dynamic);
+        let slot = |entity| stream.slot(entity).unwrap();
+
+        assert_eq!(
+            stream.visible(),
+            [slot(opaque), slot(far), slot(middle), slot(near)]
+        );
+        assert_eq!(stream.transparent_range(), 1..4);
+    }
+
+    #[test]
+    fn culled_instances_are_counted_but_keep_their_slot() {
+        let mut world = world();
+        add_camera(&mut world);
+
+        let inside = spawn_at(&mut world, Vec3::ZERO, 1.0);
+        let behind = spawn_at(&mut world, Vec3::new(0.0, 0.0, 20.0), 1.0);
+        let beside = spawn_at(&mut world, Vec3::new(50.0, 0.0, 0.0), 1.0);
+        let hidden = spawn_at(&mut world, Vec3::ZERO, 1.0);
+        world.insert(hidden, Hidden);
+
+        extract_instances(&mut world);
+
+        let render = world.resource::<RenderInstances>();
+        let stream = render.stream(StreamKind::Dynamic);
+
+        assert_eq!((render.visible, render.culled), (1, 2));
+        assert_eq!(stream.visible(), [stream.slot(inside).unwrap()]);
+        assert!(stream.slot(behind).is_some());
+        assert!(stream.slot(beside).is_some());
+        assert_eq!(stream.slot(hidden), None);
+    }
+}
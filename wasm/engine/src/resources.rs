@@ -0,0 +1,89 @@
+use std::any::{Any, TypeId, type_name};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+
+/// Singleton values keyed by type (time, camera, input, config...).
+///
+/// Like component storages, each resource sits in its own `RefCell` so a
+/// system can hold several at once through a shared `&World`.
+#[derive(Default)]
+pub struct Resources {
+    map: HashMap<TypeId, RefCell<Box<dyn Any>>>,
+}
+
+impl Resources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn insert<T: 'static>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), RefCell::new(Box::new(value)))
+            .and_then(|old| old.into_inner().downcast::<T>().ok())
+            .map(|old| *old)
+    }
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|old| old.into_inner().downcast::<T>().ok())
+            .map(|old| *old)
+    }
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+    pub fn get<T: 'static>(&self) -> Option<Ref<'_, T>> {
+        let cell = self.map.get(&TypeId::of::<T>())?;
+
+        Some(Ref::map(cell.borrow(), |value| {
+            value.downcast_ref::<T>().expect("resource type mismatch")
+        }))
+    }
+    pub fn get_mut<T: 'static>(&self) -> Option<RefMut<'_, T>> {
+        let cell = self.map.get(&TypeId::of::<T>())?;
+
+        Some(RefMut::map(cell.borrow_mut(), |value| {
+            value.downcast_mut::<T>().expect("resource type mismatch")
+        }))
+    }
+}
+
+/// Resources a system has declared it reads or writes. A write implies a
+/// read.
+#[derive(Default, Clone)]
+pub struct Access {
+    reads: Vec<(TypeId, &'static str)>,
+    writes: Vec<(TypeId, &'static str)>,
+}
+
+impl Access {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn add_read<T: 'static>(&mut self) {
+        self.reads.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+    pub fn add_write<T: 'static>(&mut self) {
+        self.writes.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+    pub fn can_read(&self, id: TypeId) -> bool {
+        self.reads.iter().chain(&self.writes).any(|&(t, _)| t == id)
+    }
+    pub fn can_write(&self, id: TypeId) -> bool {
+        self.writes.iter().any(|&(t, _)| t == id)
+    }
+    pub fn reads(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.reads.iter().map(|&(_, name)| name)
+    }
+    pub fn writes(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.writes.iter().map(|&(_, name)| name)
+    }
+    pub fn clear(&mut self) {
+        self.reads.clear();
+        self.writes.clear();
+    }
+    /// Replaces `self` with a copy of `other`, reusing allocations.
+    pub fn copy_from(&mut self, other: &Access) {
+        self.clear();
+        self.reads.extend_from_slice(&other.reads);
+        self.writes.extend_from_slice(&other.writes);
+    }
+}
@@ -0,0 +1,411 @@
+use crate::resources::Access;
+use crate::world::{Component, World};
+
+/// Fixed execution stages, run in declaration order every update.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Stage {
+    Input,
+    Simulation,
+    PostSimulation,
+    RenderExtract,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 4] = [
+        Stage::Input,
+        Stage::Simulation,
+        Stage::PostSimulation,
+        Stage::RenderExtract,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Input => "input",
+            Stage::Simulation => "simulation",
+            Stage::PostSimulation => "post-sim",
+            Stage::RenderExtract => "render-extract",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ScheduleError {
+    DuplicateName(String),
+    /// Ordering constraints within a stage form a cycle through these systems.
+    Cycle(Vec<String>),
+}
+
+type SystemFn = Box<dyn FnMut(&mut World)>;
+type ConditionFn = Box<dyn Fn(&World) -> bool>;
+
+struct SystemEntry {
+    name: String,
+    stage: Stage,
+    run: SystemFn,
+    condition: Option<ConditionFn>,
+    after: Vec<String>,
+    before: Vec<String>,
+    access: Access,
+    enabled: bool,
+}
+
+/// Ordered list of named systems over the world.
+///
+/// Within a stage, systems run in registration order unless `after` /
+/// `before` constraints say otherwise. Constraints naming systems in other
+/// stages (or that don't exist) are ignored.
+///
+/// Systems declare the resources they use with `reads`/`writes`; touching
+/// an undeclared resource from a system panics in debug builds.
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<SystemEntry>,
+    order: Vec<usize>,
+    dirty: bool,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_system(
+        &mut self,
+        name: &str,
+        stage: Stage,
+        run: impl FnMut(&mut World) + 'static,
+    ) -> SystemConfig<'_> {
+        self.systems.push(SystemEntry {
+            name: name.to_string(),
+            stage,
+            run: Box::new(run),
+            condition: None,
+            after: Vec::new(),
+            before: Vec::new(),
+            access: Access::new(),
+            enabled: true,
+        });
+        self.dirty = true;
+
+        SystemConfig {
+            entry: self.systems.last_mut().unwrap(),
+        }
+    }
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.systems.iter_mut().find(|s| s.name == name) {
+            Some(system) => {
+                system.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn is_enabled(&self, name: &str) -> Option<bool> {
+        self.systems
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| s.enabled)
+    }
+    /// System names in execution order, as `stage/name`.
+    pub fn describe(&mut self) -> Vec<String> {
+        self.ensure_order();
+
+        self.order
+            .iter()
+            .map(|&i| {
+                let system = &self.systems[i];
+                format!("{}/{}", system.stage.name(), system.name)
+            })
+            .collect()
+    }
+
+    /// Recomputes execution order from stages and constraints.
+    pub fn rebuild(&mut self) -> Result<(), ScheduleError> {
+        self.dirty = false;
+        self.order.clear();
+
+        for (i, system) in self.systems.iter().enumerate() {
+            if self.systems[..i].iter().any(|s| s.name == system.name) {
+                self.order = (0..self.systems.len()).collect();
+                return Err(ScheduleError::DuplicateName(system.name.clone()));
+            }
+        }
+
+        for stage in Stage::ALL {
+            let members: Vec<usize> = (0..self.systems.len())
+                .filter(|&i| self.systems[i].stage == stage)
+                .collect();
+
+            match self.sort_stage(&members) {
+                Ok(sorted) => self.order.extend(sorted),
+                Err(err) => {
+                    self.order = (0..self.systems.len()).collect();
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn run(&mut self, world: &mut World) {
+        self.ensure_order();
+
+        for &i in &self.order {
+            let system = &mut self.systems[i];
+
+            if !system.enabled {
+                continue;
+            }
+            if let Some(condition) = &system.condition
+                && !condition(world)
+            {
+                continue;
+            }
+
+            world.enter_system(&system.name, &system.access);
+            (system.run)(world);
+            world.exit_system();
+        }
+    }
+
+    /// On an invalid schedule this falls back to registration order, so
+    /// a bad constraint added at runtime never stops the frame.
+    fn ensure_order(&mut self) {
+        if self.dirty {
+            let _ = self.rebuild();
+        }
+    }
+
+    /// Stable topological sort (Kahn's algorithm, lowest index first).
+    fn sort_stage(&self, members: &[usize]) -> Result<Vec<usize>, ScheduleError> {
+        let position = |name: &str| members.iter().position(|&i| self.systems[i].name == name);
+
+        let mut edges = vec![Vec::new(); members.len()];
+        let mut incoming = vec![0usize; members.len()];
+
+        for (m, &i) in members.iter().enumerate() {
+            let system = &self.systems[i];
+
+            for dep in system.after.iter().filter_map(|n| position(n)) {
+                edges[dep].push(m);
+                incoming[m] += 1;
+            }
+            for next in system.before.iter().filter_map(|n| position(n)) {
+                edges[m].push(next);
+                incoming[next] += 1;
+            }
+        }
+
+        let mut sorted = Vec::with_capacity(members.len());
+        let mut done = vec![false; members.len()];
+
+        while sorted.len() < members.len() {
+            let Some(m) = (0..members.len()).find(|&m| !done[m] && incoming[m] == 0) else {
+                let stuck = (0..members.len())
+                    .filter(|&m| !done[m])
+                    .map(|m| self.systems[members[m]].name.clone())
+                    .collect();
+
+                return Err(ScheduleError::Cycle(stuck));
+            };
+
+            done[m] = true;
+            sorted.push(members[m]);
+
+            for &next in &edges[m] {
+                incoming[next] -= 1;
+            }
+        }
+
+        Ok(sorted)
+    }
+}
+
+/// Returned by [`Schedule::add_system`] to attach constraints.
+pub struct SystemConfig<'a> {
+    entry: &'a mut SystemEntry,
+}
+
+impl SystemConfig<'_> {
+    pub fn after(self, name: &str) -> Self {
+        self.entry.after.push(name.to_string());
+        self
+    }
+    pub fn before(self, name: &str) -> Self {
+        self.entry.before.push(name.to_string());
+        self
+    }
+    /// Run conditions see the world outside any system, so they may read
+    /// any resource.
+    pub fn run_if(self, condition: impl Fn(&World) -> bool + 'static) -> Self {
+        self.entry.condition = Some(Box::new(condition));
+        self
+    }
+    pub fn reads<T: Component>(self) -> Self {
+        self.entry.access.add_read::<T>();
+        self
+    }
+    pub fn writes<T: Component>(self) -> Self {
+        self.entry.access.add_write::<T>();
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+
+    struct Flag(bool);
+
+    fn logger(name: &'static str) -> impl FnMut(&mut World) {
+        move |world| world.resource_mut::<Log>().0.push(name)
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert_resource(Log::default());
+        world.insert_resource(Flag(false));
+        world
+    }
+
+    fn run(schedule: &mut Schedule, world: &mut World) -> Vec<&'static str> {
+        schedule.run(world);
+        std::mem::take(&mut world.resource_mut::<Log>().0)
+    }
+
+    #[test]
+    fn stages_then_registration_order() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system("extract", Stage::RenderExtract, logger("extract"))
+            .writes::<Log>();
+        schedule
+            .add_system("a", Stage::Simulation, logger("a"))
+            .writes::<Log>();
+        schedule
+            .add_system("input", Stage::Input, logger("input"))
+            .writes::<Log>();
+        schedule
+            .add_system("b", Stage::Simulation, logger("b"))
+            .writes::<Log>();
+
+        assert_eq!(
+            run(&mut schedule, &mut world()),
+            ["input", "a", "b", "extract"]
+        );
+    }
+
+    #[test]
+    fn before_and_after_reorder_within_a_stage() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system("c", Stage::Simulation, logger("c"))
+            .after("b")
+            .writes::<Log>();
+        schedule
+            .add_system("a", Stage::Simulation, logger("a"))
+            .writes::<Log>();
+        schedule
+            .add_system("b", Stage::Simulation, logger("b"))
+            .before("a")
+            .writes::<Log>();
+        // Constraints across stages or on unknown names are ignored.
+        schedule
+            .add_system("input", Stage::Input, logger("input"))
+            .after("c")
+            .before("missing")
+            .writes::<Log>();
+
+        assert_eq!(schedule.rebuild(), Ok(()));
+        assert_eq!(
+            schedule.describe(),
+            [
+                "input/input",
+                "simulation/b",
+                "simulation/c",
+                "simulation/a"
+            ]
+        );
+        assert_eq!(run(&mut schedule, &mut world()), ["input", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn cycles_are_reported_and_fall_back_to_registration_order() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system("free", Stage::Simulation, logger("free"))
+            .writes::<Log>();
+        schedule
+            .add_system("a", Stage::Simulation, logger("a"))
+            .after("b")
+            .writes::<Log>();
+        schedule
+            .add_system("b", Stage::Simulation, logger("b"))
+            .after("a")
+            .writes::<Log>();
+
+        assert_eq!(
+            schedule.rebuild(),
+            Err(ScheduleError::Cycle(vec!["a".into(), "b".into()]))
+        );
+
+        // The frame still runs every system.
+        assert_eq!(run(&mut schedule, &mut world()), ["free", "a", "b"]);
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        let mut schedule = Schedule::new();
+        schedule.add_system("tick", Stage::Input, |_| {});
+        schedule.add_system("tick", Stage::Simulation, |_| {});
+
+        assert_eq!(
+            schedule.rebuild(),
+            Err(ScheduleError::DuplicateName("tick".into()))
+        );
+    }
+
+    #[test]
+    fn run_if_and_enabled_skip_systems() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system("gated", Stage::Simulation, logger("gated"))
+            .run_if(|world| world.resource::<Flag>().0)
+            .writes::<Log>();
+        schedule
+            .add_system("toggled", Stage::Simulation, logger("toggled"))
+            .writes::<Log>();
+        let mut world = world();
+
+        assert_eq!(run(&mut schedule, &mut world), ["toggled"]);
+
+        world.resource_mut::<Flag>().0 = true;
+        assert!(schedule.set_enabled("toggled", false));
+        assert_eq!(schedule.is_enabled("toggled"), Some(false));
+        assert_eq!(run(&mut schedule, &mut world), ["gated"]);
+
+        assert!(schedule.set_enabled("toggled", true));
+        assert_eq!(run(&mut schedule, &mut world), ["gated", "toggled"]);
+
+        assert!(!schedule.set_enabled("missing", false));
+        assert_eq!(schedule.is_enabled("missing"), None);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Flag")]
+    fn undeclared_resource_access_panics() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system("sneaky", Stage::Input, |world| {
+                world.resource_mut::<Flag>().0 = true;
+            })
+            .reads::<Log>();
+
+        schedule.run(&mut world());
+    }
+}
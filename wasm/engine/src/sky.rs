@@ -0,0 +1,254 @@
+use std::f32::consts::TAU;
+
+use crate::Time;
+use crate::components::Transform;
+use crate::light::DirectionalLight;
+use crate::math::{Quat, Vec3};
+use crate::world::{Entity, World};
+
+/// Tilt of the sun's arc away from straight overhead, toward +Z, so
+/// the noon sun lights the side facing the default camera.
+const ARC_TILT: f32 = 0.5;
+
+/// Directional light intensity with the sun high in the sky.
+const NOON_INTENSITY: f32 = 0.8;
+
+const DAY_AMBIENT: f32 = 0.35;
+const NIGHT_AMBIENT: f32 = 0.08;
+
+const DAY_ZENITH: Vec3 = Vec3::new(0.32, 0.55, 0.85);
+const DAY_HORIZON: Vec3 = Vec3::new(0.70, 0.82, 0.92);
+const NIGHT_ZENITH: Vec3 = Vec3::new(0.02, 0.03, 0.08);
+const NIGHT_HORIZON: Vec3 = Vec3::new(0.05, 0.07, 0.14);
+const DUSK_HORIZON: Vec3 = Vec3::new(0.95, 0.60, 0.40);
+
+const LOW_SUN: Vec3 = Vec3::new(1.0, 0.55, 0.30);
+const HIGH_SUN: Vec3 = Vec3::new(1.0, 0.96, 0.90);
+
+/// Floats in the packed sky block, laid out for a WGSL struct of
+/// 16-byte aligned fields:
+///
+/// | floats | field |
+/// |--------|-------|
+/// | 0..3   | direction sunlight travels in, unit length |
+/// | 3      | ambient level |
+/// | 4..7   | sunlight color RGB |
+/// | 7      | sunlight intensity |
+/// | 8..11  | zenith color RGB |
+/// | 11     | padding |
+/// | 12..15 | horizon color RGB |
+/// | 15     | padding |
+pub const SKY_LEN: usize = 16;
+
+/// The clock behind the day/night cycle.
+#[derive(Clone, Copy, Debug)]
+pub struct TimeOfDay {
+    /// Hour of the day in `0..24`; the sun rises at 6 and sets at 18.
+    pub hour: f32,
+    /// Hours advanced per second of `Time::delta`.
+    pub speed: f32,
+    /// Entity moved along the sun's arc, if any.
+    pub sun: Option<Entity>,
+    /// Distance of the sun entity from the origin.
+    pub sun_distance: f32,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        TimeOfDay {
+            hour: 10.0,
+            // A full day every four minutes.
+            speed: 0.1,
+            sun: None,
+            sun_distance: 12.0,
+        }
+    }
+}
+
+/// Lighting and sky colors for the current hour, written by
+/// [`update_sun`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sky {
+    /// Direction sunlight travels in, unit length.
+    pub light_direction: Vec3,
+    pub light_color: [f32; 3],
+    pub light_intensity: f32,
+    pub ambient: f32,
+    pub zenith: [f32; 3],
+    pub horizon: [f32; 3],
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Sky::at(TimeOfDay::default().hour)
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Unit vector from the origin toward the sun. The sun rises in +X at
+/// 6, peaks at noon and sets in -X at 18.
+pub fn sun_direction(hour: f32) -> Vec3 {
+    let angle = (hour - 6.0) / 24.0 * TAU;
+    let (sin, cos) = angle.sin_cos();
+
+    Vec3::new(cos, sin * ARC_TILT.cos(), sin * ARC_TILT.sin())
+}
+
+impl Sky {
+    pub fn at(hour: f32) -> Sky {
+        let to_sun = sun_direction(hour);
+        let elevation = to_sun.y;
+
+        // 0 at night, 1 once the sun is well clear of the horizon.
+        let day = smoothstep(-0.1, 0.25, elevation);
+        // Strongest with the sun on the horizon.
+        let twilight = 1.0 - (elevation.abs() / 0.3).min(1.0);
+
+        let light_color = LOW_SUN.lerp(HIGH_SUN, smoothstep(0.0, 0.4, elevation));
+        let horizon = NIGHT_HORIZON
+            .lerp(DAY_HORIZON, day)
+            .lerp(DUSK_HORIZON, twilight * 0.6);
+
+        Sky {
+            light_direction: -to_sun,
+            light_color: light_color.to_array(),
+            light_intensity: NOON_INTENSITY * smoothstep(-0.05, 0.15, elevation),
+            ambient: NIGHT_AMBIENT + (DAY_AMBIENT - NIGHT_AMBIENT) * day,
+            zenith: NIGHT_ZENITH.lerp(DAY_ZENITH, day).to_array(),
+            horizon: horizon.to_array(),
+        }
+    }
+
+    /// The sky packed as described by [`SKY_LEN`].
+    pub fn to_array(&self) -> [f32; SKY_LEN] {
+        let d = self.light_direction;
+        let [lr, lg, lb] = self.light_color;
+        let [zr, zg, zb] = self.zenith;
+        let [hr, hg, hb] = self.horizon;
+
+        [
+            d.x,
+            d.y,
+            d.z,
+            self.ambient,
+            lr,
+            lg,
+            lb,
+            self.light_intensity,
+            zr,
+            zg,
+            zb,
+            0.0,
+            hr,
+            hg,
+            hb,
+            0.0,
+        ]
+    }
+}
+
+/// Advances the clock by `Time::delta`, wrapping at midnight.
+pub fn advance_time_of_day(world: &mut World) {
+    let delta = world.resource::<Time>().delta;
+    let mut time_of_day = world.resource_mut::<TimeOfDay>();
+
+    time_of_day.hour = (time_of_day.hour + delta * time_of_day.speed).rem_euclid(24.0);
+}
+
+/// Recomputes the `Sky` for the current hour and moves the sun entity
+/// onto its arc, aimed at the origin, with its `DirectionalLight`
+/// following the sunlight color and intensity.
+pub fn update_sun(world: &mut World) {
+    let time_of_day = *world.resource::<TimeOfDay>();
+    let sky = Sky::at(time_of_day.hour);
+
+    if let Some(sun) = time_of_day.sun {
+        if let Some(mut transform) = world.get_mut::<Transform>(sun) {
+            transform.position = -sky.light_direction * time_of_day.sun_distance;
+            transform.rotation = Quat::look_to(sky.light_direction, Vec3::Y);
+        }
+        if let Some(mut light) = world.get_mut::<DirectionalLight>(sun) {
+            light.color = sky.light_color;
+            light.intensity = sky.light_intensity;
+        }
+    }
+
+    *world.resource_mut::<Sky>() = sky;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPS: f32 = 1e-5;
+
+    fn assert_color(a: [f32; 3], b: Vec3) {
+        assert!((Vec3::from(a) - b).length() < EPS, "{a:?} != {b:?}");
+    }
+
+    fn advance(hour: f32, speed: f32, delta: f32) -> f32 {
+        let mut world = World::new();
+        world.insert_resource(Time {
+            elapsed: 0.0,
+            delta,
+        });
+        world.insert_resource(TimeOfDay {
+            hour,
+            speed,
+            ..TimeOfDay::default()
+        });
+
+        advance_time_of_day(&mut world);
+        world.resource::<TimeOfDay>().hour
+    }
+
+    #[test]
+    fn the_clock_wraps_at_midnight() {
+        assert!((advance(23.5, 1.0, 1.0) - 0.5).abs() < EPS);
+        assert!((advance(0.5, -1.0, 1.0) - 23.5).abs() < EPS);
+        assert!((advance(10.0, 1.0, 48.0) - 10.0).abs() < EPS);
+        assert!((advance(12.0, 0.1, 10.0) - 13.0).abs() < EPS);
+    }
+
+    #[test]
+    fn noon_and_midnight_are_full_day_and_night() {
+        let noon = Sky::at(12.0);
+        assert!(noon.light_direction.y < -0.8);
+        assert!((noon.light_intensity - NOON_INTENSITY).abs() < EPS);
+        assert!((noon.ambient - DAY_AMBIENT).abs() < EPS);
+        assert_color(noon.zenith, DAY_ZENITH);
+        assert_color(noon.horizon, DAY_HORIZON);
+        assert_color(noon.light_color, HIGH_SUN);
+
+        let midnight = Sky::at(0.0);
+        assert!(midnight.light_direction.y > 0.8);
+        assert_eq!(midnight.light_intensity, 0.0);
+        assert!((midnight.ambient - NIGHT_AMBIENT).abs() < EPS);
+        assert_color(midnight.zenith, NIGHT_ZENITH);
+        assert_color(midnight.horizon, NIGHT_HORIZON);
+
+        // The cycle joins up across midnight.
+        let end = Sky::at(24.0);
+        assert!((end.light_direction - midnight.light_direction).length() < EPS);
+        assert_eq!(end.ambient, midnight.ambient);
+    }
+
+    #[test]
+    fn the_sun_rises_in_the_east_and_sets_in_the_west() {
+        let sunrise = Sky::at(6.0);
+        let sunset = Sky::at(18.0);
+
+        assert!((sunrise.light_direction - -Vec3::X).length() < EPS);
+        assert!((sunset.light_direction - Vec3::X).length() < EPS);
+
+        for sky in [sunrise, sunset] {
+            assert!(sky.light_intensity > 0.0 && sky.light_intensity < NOON_INTENSITY);
+            assert!(sky.ambient > NIGHT_AMBIENT && sky.ambient < DAY_AMBIENT);
+            assert_color(sky.light_color, LOW_SUN);
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use std::any::Any;
+
+use crate::world::Entity;
+
+/// Sparse-set storage for a single component type.
+///
+/// Every dense slot also records the tick it was added and last changed
+/// at. Any mutable access (`insert`, `get_mut`, `iter_mut`) counts as a
+/// change, whether or not the value was actually written.
+pub struct Storage<T> {
+    dense: Vec<T>,
+    dense_entities: Vec<u32>,
+    sparse: Vec<Option<usize>>,
+    added: Vec<u32>,
+    changed: Vec<u32>,
+    removed_tick: u32,
+    tick: u32,
+}
+
+impl<T> Default for Storage<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Storage<T> {
+    pub fn new() -> Self {
+        Self {
+            dense: Vec::new(),
+            dense_entities: Vec::new(),
+            sparse: Vec::new(),
+            added: Vec::new(),
+            changed: Vec::new(),
+            removed_tick: 0,
+            tick: 0,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+    pub fn entities(&self) -> &[u32] {
+        &self.dense_entities
+    }
+    pub fn clear(&mut self) {
+        if self.dense.is_empty() {
+            return;
+        }
+
+        for &entity_index in &self.dense_entities {
+            if let Some(slot) = self.sparse.get_mut(entity_index as usize) {
+                *slot = None;
+            }
+        }
+
+        self.dense.clear();
+        self.dense_entities.clear();
+        self.added.clear();
+        self.changed.clear();
+        self.removed_tick = self.tick;
+    }
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.contains_index(entity.index)
+    }
+    pub fn contains_index(&self, index: u32) -> bool {
+        self.dense_index(index).is_some()
+    }
+    pub fn insert(&mut self, entity: Entity, component: T) {
+        let index = entity.index as usize;
+
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, None);
+        }
+
+        if let Some(dense_index) = self.sparse[index] {
+            self.dense[dense_index] = component;
+            self.changed[dense_index] = self.tick;
+            return;
+        }
+
+        let dense_index = self.dense.len();
+
+        self.dense.push(component);
+        self.dense_entities.push(entity.index);
+        self.added.push(self.tick);
+        self.changed.push(self.tick);
+        self.sparse[index] = Some(dense_index);
+    }
+    pub fn remove_entity(&mut self, entity: Entity) -> Option<T> {
+        let index = entity.index as usize;
+        let dense_index = self.dense_index(entity.index)?;
+
+        let last_dense = self.dense.len() - 1;
+
+        self.dense.swap(dense_index, last_dense);
+        self.dense_entities.swap(dense_index, last_dense);
+        self.added.swap(dense_index, last_dense);
+        self.changed.swap(dense_index, last_dense);
+
+        let moved_entity = self.dense_entities[dense_index];
+        self.sparse[moved_entity as usize] = Some(dense_index);
+
+        self.dense_entities.pop();
+        self.added.pop();
+        self.changed.pop();
+        self.sparse[index] = None;
+        self.removed_tick = self.tick;
+        self.dense.pop()
+    }
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.get_index_mut(entity.index)
+    }
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.get_index(entity.index)
+    }
+    pub fn get_index(&self, index: u32) -> Option<&T> {
+        let dense_index = self.dense_index(index)?;
+        self.dense.get(dense_index)
+    }
+    pub fn get_index_mut(&mut self, index: u32) -> Option<&mut T> {
+        let dense_index = self.dense_index(index)?;
+        self.changed[dense_index] = self.tick;
+        self.dense.get_mut(dense_index)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &T)> {
+        self.dense_entities.iter().copied().zip(self.dense.iter())
+    }
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (u32, &mut T)> {
+        self.changed.fill(self.tick);
+
+        self.dense_entities
+            .iter()
+            .copied()
+            .zip(self.dense.iter_mut())
+    }
+
+    // ===== CHANGE DETECTION =====
+
+    /// Tick stamped on inserts and mutable accesses from now on.
+    pub fn set_tick(&mut self, tick: u32) {
+        self.tick = tick;
+    }
+    pub fn added_tick(&self, index: u32) -> Option<u32> {
+        self.dense_index(index).map(|d| self.added[d])
+    }
+    pub fn changed_tick(&self, index: u32) -> Option<u32> {
+        self.dense_index(index).map(|d| self.changed[d])
+    }
+    pub fn is_added_since(&self, index: u32, tick: u32) -> bool {
+        self.added_tick(index).is_some_and(|t| t >= tick)
+    }
+    pub fn is_changed_since(&self, index: u32, tick: u32) -> bool {
+        self.changed_tick(index).is_some_and(|t| t >= tick)
+    }
+    /// Whether any entity lost this component at or after `tick`.
+    pub fn removed_since(&self, tick: u32) -> bool {
+        self.removed_tick >= tick
+    }
+
+    fn dense_index(&self, index: u32) -> Option<usize> {
+        self.sparse.get(index as usize).copied().flatten()
+    }
+}
+
+/// Type-erased view of a `Storage<T>`, so the world can own storages for
+/// any number of component types and still clean them up on despawn.
+pub trait ErasedStorage: Any {
+    fn remove_entity(&mut self, entity: Entity);
+    fn clear(&mut self);
+    fn set_tick(&mut self, tick: u32);
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: 'static> ErasedStorage for Storage<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        Storage::remove_entity(self, entity);
+    }
+    fn clear(&mut self) {
+        Storage::clear(self);
+    }
+    fn set_tick(&mut self, tick: u32) {
+        Storage::set_tick(self, tick);
+    }
+    fn len(&self) -> usize {
+        Storage::len(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(index: u32) -> Entity {
+        Entity::from_bits(index)
+    }
+
+    #[test]
+    fn insert_sets_added_and_changed() {
+        let mut storage = Storage::new();
+        storage.set_tick(3);
+        storage.insert(entity(4), 'a');
+
+        assert_eq!(storage.added_tick(4), Some(3));
+        assert_eq!(storage.changed_tick(4), Some(3));
+
+        // Overwriting is a change, not a new addition.
+        storage.set_tick(5);
+        storage.insert(entity(4), 'b');
+
+        assert_eq!(storage.added_tick(4), Some(3));
+        assert_eq!(storage.changed_tick(4), Some(5));
+        assert!(storage.is_changed_since(4, 5));
+        assert!(!storage.is_added_since(4, 5));
+    }
+
+    #[test]
+    fn mutable_access_sets_changed_only() {
+        let mut storage = Storage::new();
+        storage.insert(entity(0), 0);
+        storage.insert(entity(1), 1);
+        storage.set_tick(1);
+
+        let _ = storage.get(entity(0));
+        let _ = storage.iter().count();
+        assert_eq!(storage.changed_tick(0), Some(0));
+        assert_eq!(storage.changed_tick(1), Some(0));
+
+        storage.get_mut(entity(0));
+        assert_eq!(storage.changed_tick(0), Some(1));
+        assert_eq!(storage.changed_tick(1), Some(0));
+
+        storage.set_tick(2);
+        storage.iter_mut().for_each(|_| {});
+        assert_eq!(storage.changed_tick(0), Some(2));
+        assert_eq!(storage.changed_tick(1), Some(2));
+        assert_eq!(storage.added_tick(0), Some(0));
+        assert_eq!(storage.added_tick(1), Some(0));
+    }
+
+    #[test]
+    fn ticks_follow_swap_remove() {
+        let mut storage = Storage::new();
+        storage.insert(entity(0), 0);
+        storage.set_tick(1);
+        storage.insert(entity(1), 1);
+        storage.set_tick(2);
+
+        assert_eq!(storage.remove_entity(entity(0)), Some(0));
+
+        assert_eq!(storage.added_tick(1), Some(1));
+        assert_eq!(storage.added_tick(0), None);
+        assert!(storage.removed_since(2));
+        assert!(!storage.removed_since(3));
+    }
+}
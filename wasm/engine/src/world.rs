@@ -0,0 +1,542 @@
+use std::any::{Any, TypeId};
+use std::cell::{Cell, Ref, RefCell, RefMut};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::query::{Query, QueryData, QueryFilter};
+use crate::resources::{Access, Resources};
+use crate::storage::{ErasedStorage, Storage};
+
+/// Bits of a packed handle used for the entity index; the rest hold the
+/// generation. A slot whose generation is used up is retired rather than
+/// wrapped, so an old handle can never match a later entity.
+const INDEX_BITS: u32 = 20;
+const INDEX_MASK: u32 = (1 << INDEX_BITS) - 1;
+const GENERATION_MASK: u32 = u32::MAX >> INDEX_BITS;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Entity {
+    pub(crate) index: u32,
+    pub(crate) generation: u32,
+}
+
+impl Entity {
+    pub fn index(self) -> u32 {
+        self.index
+    }
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+    /// Packs index and generation into the `u32` handle handed to JS.
+    pub fn to_bits(self) -> u32 {
+        (self.generation << INDEX_BITS) | self.index
+    }
+    pub fn from_bits(bits: u32) -> Entity {
+        Entity {
+            index: bits & INDEX_MASK,
+            generation: bits >> INDEX_BITS,
+        }
+    }
+}
+
+/// Every entity index is live or retired, so `World::spawn` has nothing
+/// left to hand out.
+#[derive(Debug, PartialEq, Eq)]
+pub struct EntityLimit;
+
+impl fmt::Display for EntityLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "entity limit of {} reached", INDEX_MASK as u64 + 1)
+    }
+}
+
+impl std::error::Error for EntityLimit {}
+
+/// Entity lifecycle changes recorded by the world until drained.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Lifecycle {
+    Spawned(Entity),
+    Despawned(Entity),
+}
+
+/// Anything `'static` can be stored as a component.
+pub trait Component: 'static {}
+impl<T: 'static> Component for T {}
+
+/// Entity allocator plus a registry of component storages keyed by type,
+/// and the typed resources systems share.
+///
+/// Storages sit behind a `RefCell` so several of them can be borrowed at
+/// once (one mutably, others shared) while the world itself is only
+/// borrowed immutably.
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free_indices: Vec<u32>,
+    // Live entities; retired slots are neither live nor free.
+    live: usize,
+    // Handed out by `reserve` but not spawned yet: the last
+    // `reserved_free` entries of `free_indices`, then `reserved_new`
+    // indices past the end of `generations`.
+    reserved_free: Cell<usize>,
+    reserved_new: Cell<usize>,
+    storages: HashMap<TypeId, RefCell<Box<dyn ErasedStorage>>>,
+    change_tick: u32,
+    lifecycle: Vec<Lifecycle>,
+    resources: Resources,
+    // Name and declared access of the system currently running, if any.
+    active_system: Option<String>,
+    access: Access,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            generations: Vec::new(),
+            alive: Vec::new(),
+            free_indices: Vec::new(),
+            live: 0,
+            reserved_free: Cell::new(0),
+            reserved_new: Cell::new(0),
+            storages: HashMap::new(),
+            // 0 is reserved for "never", so everything done before the
+            // first advance still reads as a change.
+            change_tick: 1,
+            lifecycle: Vec::new(),
+            resources: Resources::new(),
+            active_system: None,
+            access: Access::new(),
+        }
+    }
+
+    // ===== CHANGE TICKS =====
+
+    /// Tick currently stamped on inserts and mutable accesses.
+    pub fn change_tick(&self) -> u32 {
+        self.change_tick
+    }
+    /// Starts a new change window. `Added`/`Changed` filters only match
+    /// what happened since the most recent call.
+    pub fn advance_tick(&mut self) {
+        self.change_tick += 1;
+
+        for storage in self.storages.values_mut() {
+            storage.get_mut().set_tick(self.change_tick);
+        }
+    }
+
+    // ===== ENTITIES =====
+
+    pub fn spawn(&mut self) -> Result<Entity, EntityLimit> {
+        self.flush_reserved();
+        self.allocate()
+    }
+    /// Hands out the entity a later `spawn` would return, without needing
+    /// the world mutably. It is not alive until [`World::flush_reserved`],
+    /// which the next `spawn` or `despawn` also does.
+    pub fn reserve(&self) -> Result<Entity, EntityLimit> {
+        let free = self.reserved_free.get();
+
+        if free < self.free_indices.len() {
+            let index = self.free_indices[self.free_indices.len() - 1 - free];
+            self.reserved_free.set(free + 1);
+
+            return Ok(Entity {
+                index,
+                generation: self.generations[index as usize],
+            });
+        }
+
+        let index = self.generations.len() + self.reserved_new.get();
+        if index > INDEX_MASK as usize {
+            return Err(EntityLimit);
+        }
+        self.reserved_new.set(self.reserved_new.get() + 1);
+
+        Ok(Entity {
+            index: index as u32,
+            generation: 0,
+        })
+    }
+    /// Spawns every reserved entity, in the order they were reserved.
+    pub fn flush_reserved(&mut self) {
+        let count = self.reserved_free.take() + self.reserved_new.take();
+
+        // `allocate` pops the free list from the end and then grows, the
+        // same order `reserve` walked it in.
+        for _ in 0..count {
+            let _ = self.allocate();
+        }
+    }
+    fn allocate(&mut self) -> Result<Entity, EntityLimit> {
+        let entity = if let Some(index) = self.free_indices.pop() {
+            let idx = index as usize;
+            self.alive[idx] = true;
+
+            Entity {
+                index,
+                generation: self.generations[idx],
+            }
+        } else {
+            let index = self.generations.len() as u32;
+            if index > INDEX_MASK {
+                return Err(EntityLimit);
+            }
+
+            self.generations.push(0);
+            self.alive.push(true);
+
+            Entity {
+                index,
+                generation: 0,
+            }
+        };
+
+        self.live += 1;
+        self.lifecycle.push(Lifecycle::Spawned(entity));
+        Ok(entity)
+    }
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        // Freeing an index would shift the ones reserved from the free list.
+        self.flush_reserved();
+
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        let idx = entity.index as usize;
+
+        self.alive[idx] = false;
+        self.live -= 1;
+
+        // Last generation: retire the slot instead of wrapping back to
+        // handles that may still be held.
+        if self.generations[idx] < GENERATION_MASK {
+            self.generations[idx] += 1;
+            self.free_indices.push(entity.index);
+        }
+
+        for storage in self.storages.values_mut() {
+            storage.get_mut().remove_entity(entity);
+        }
+
+        self.lifecycle.push(Lifecycle::Despawned(entity));
+        true
+    }
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let idx = entity.index as usize;
+
+        idx < self.generations.len()
+            && self.alive[idx]
+            && self.generations[idx] == entity.generation
+    }
+    /// The live entity currently occupying `index`, if any.
+    pub fn entity(&self, index: u32) -> Option<Entity> {
+        let idx = index as usize;
+
+        if idx >= self.generations.len() || !self.alive[idx] {
+            return None;
+        }
+
+        Some(Entity {
+            index,
+            generation: self.generations[idx],
+        })
+    }
+    /// Rebuilds the handle for a dense index taken from a storage.
+    /// Storages only ever hold live entities, so this cannot go stale.
+    pub(crate) fn entity_at(&self, index: u32) -> Entity {
+        Entity {
+            index,
+            generation: self.generations[index as usize],
+        }
+    }
+    /// Spawns and despawns since the last drain, in order.
+    pub fn drain_lifecycle(&mut self) -> impl Iterator<Item = Lifecycle> + '_ {
+        self.lifecycle.drain(..)
+    }
+    /// Every live entity, in index order.
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        (0..self.generations.len() as u32).filter_map(|index| self.entity(index))
+    }
+    pub fn len(&self) -> usize {
+        self.live
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// How many more entities `spawn` can hand out.
+    pub fn spawn_capacity(&self) -> usize {
+        let reserved = self.reserved_free.get() + self.reserved_new.get();
+
+        self.free_indices.len() + (INDEX_MASK as usize + 1 - self.generations.len()) - reserved
+    }
+
+    // ===== COMPONENTS =====
+
+    pub fn register<T: Component>(&mut self) {
+        let tick = self.change_tick;
+
+        self.storages.entry(TypeId::of::<T>()).or_insert_with(|| {
+            let mut storage = Storage::<T>::new();
+            storage.set_tick(tick);
+            RefCell::new(Box::new(storage))
+        });
+    }
+    pub fn is_registered<T: Component>(&self) -> bool {
+        self.storages.contains_key(&TypeId::of::<T>())
+    }
+    /// Borrows the storage for `T`.
+    ///
+    /// Panics if `T` was never registered or is already mutably borrowed.
+    pub fn storage<T: Component>(&self) -> Ref<'_, Storage<T>> {
+        Ref::map(self.cell::<T>().borrow(), |storage| {
+            (&**storage as &dyn Any)
+                .downcast_ref::<Storage<T>>()
+                .expect("component storage type mismatch")
+        })
+    }
+    /// Mutably borrows the storage for `T`.
+    ///
+    /// Panics if `T` was never registered or is already borrowed.
+    pub fn storage_mut<T: Component>(&self) -> RefMut<'_, Storage<T>> {
+        RefMut::map(self.cell::<T>().borrow_mut(), |storage| {
+            (&mut **storage as &mut dyn Any)
+                .downcast_mut::<Storage<T>>()
+                .expect("component storage type mismatch")
+        })
+    }
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        self.register::<T>();
+        self.storage_mut::<T>().insert(entity, component);
+
+        true
+    }
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        if !self.is_alive(entity) || !self.is_registered::<T>() {
+            return None;
+        }
+
+        self.storage_mut::<T>().remove_entity(entity)
+    }
+    pub fn has<T: Component>(&self, entity: Entity) -> bool {
+        self.is_alive(entity) && self.is_registered::<T>() && self.storage::<T>().contains(entity)
+    }
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        if !self.is_alive(entity) || !self.is_registered::<T>() {
+            return None;
+        }
+
+        Ref::filter_map(self.storage::<T>(), |storage| storage.get(entity)).ok()
+    }
+    pub fn get_mut<T: Component>(&self, entity: Entity) -> Option<RefMut<'_, T>> {
+        if !self.is_alive(entity) || !self.is_registered::<T>() {
+            return None;
+        }
+
+        RefMut::filter_map(self.storage_mut::<T>(), |storage| storage.get_mut(entity)).ok()
+    }
+
+    // ===== RESOURCES =====
+
+    pub fn insert_resource<T: Component>(&mut self, value: T) -> Option<T> {
+        self.check_access::<T>(true);
+        self.resources.insert(value)
+    }
+    pub fn remove_resource<T: Component>(&mut self) -> Option<T> {
+        self.check_access::<T>(true);
+        self.resources.remove()
+    }
+    pub fn contains_resource<T: Component>(&self) -> bool {
+        self.resources.contains::<T>()
+    }
+    /// Panics if `T` was never inserted.
+    pub fn resource<T: Component>(&self) -> Ref<'_, T> {
+        self.get_resource::<T>()
+            .unwrap_or_else(|| panic!("resource {} does not exist", std::any::type_name::<T>()))
+    }
+    /// Panics if `T` was never inserted.
+    pub fn resource_mut<T: Component>(&self) -> RefMut<'_, T> {
+        self.get_resource_mut::<T>()
+            .unwrap_or_else(|| panic!("resource {} does not exist", std::any::type_name::<T>()))
+    }
+    pub fn get_resource<T: Component>(&self) -> Option<Ref<'_, T>> {
+        self.check_access::<T>(false);
+        self.resources.get()
+    }
+    pub fn get_resource_mut<T: Component>(&self) -> Option<RefMut<'_, T>> {
+        self.check_access::<T>(true);
+        self.resources.get_mut()
+    }
+
+    /// Marks `name` as running so resource access is checked against what
+    /// it declared. Called by the schedule around each system.
+    pub(crate) fn enter_system(&mut self, name: &str, access: &Access) {
+        self.active_system = Some(name.to_string());
+        self.access.copy_from(access);
+    }
+    pub(crate) fn exit_system(&mut self) {
+        self.active_system = None;
+        self.access.clear();
+    }
+    fn check_access<T: Component>(&self, write: bool) {
+        let Some(system) = &self.active_system else {
+            return;
+        };
+
+        let id = TypeId::of::<T>();
+        let allowed = if write {
+            self.access.can_write(id)
+        } else {
+            self.access.can_read(id)
+        };
+
+        debug_assert!(
+            allowed,
+            "system `{system}` {} resource {} without declaring it",
+            if write { "writes" } else { "reads" },
+            std::any::type_name::<T>()
+        );
+    }
+
+    // ===== QUERIES =====
+
+    pub fn query<Q: QueryData>(&self) -> Query<'_, Q> {
+        Query::new(self)
+    }
+    pub fn query_filtered<Q: QueryData, F: QueryFilter>(&self) -> Query<'_, Q, F> {
+        Query::new(self)
+    }
+
+    fn cell<T: Component>(&self) -> &RefCell<Box<dyn ErasedStorage>> {
+        self.storages
+            .get(&TypeId::of::<T>())
+            .unwrap_or_else(|| panic!("component {} is not registered", std::any::type_name::<T>()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn components_live_in_per_type_storages() {
+        let mut world = World::new();
+        let a = world.spawn().unwrap();
+        let b = world.spawn().unwrap();
+
+        assert!(world.insert(a, 1u32));
+        assert!(world.insert(a, "a"));
+        assert!(world.insert(b, 2u32));
+        assert!(world.is_registered::<u32>());
+        assert!(world.get::<f32>(a).is_none());
+
+        *world.get_mut::<u32>(b).unwrap() += 1;
+        assert_eq!(world.remove::<u32>(b), Some(3));
+        assert!(!world.has::<u32>(b));
+        assert!(world.has::<u32>(a));
+
+        // Despawning clears the entity out of every storage.
+        world.despawn(a);
+        assert!(world.storage::<u32>().is_empty());
+        assert!(world.storage::<&str>().is_empty());
+        assert!(!world.insert(a, 5u32));
+    }
+
+    #[test]
+    fn despawned_handle_is_rejected_after_reuse() {
+        let mut world = World::new();
+        let old = world.spawn().unwrap();
+        world.despawn(old);
+
+        let new = world.spawn().unwrap();
+
+        assert_eq!(new.index(), old.index());
+        assert!(!world.is_alive(old));
+        assert!(!world.is_alive(Entity::from_bits(old.to_bits())));
+        assert!(world.is_alive(Entity::from_bits(new.to_bits())));
+        assert!(!world.despawn(old));
+        assert!(world.is_alive(new));
+    }
+
+    #[test]
+    fn slot_is_retired_instead_of_wrapping() {
+        let mut world = World::new();
+        let first = world.spawn().unwrap();
+        let mut last = first;
+
+        for _ in 0..GENERATION_MASK {
+            world.despawn(last);
+            last = world.spawn().unwrap();
+
+            assert_eq!(last.index(), first.index());
+            assert!(!world.is_alive(first));
+        }
+        assert_eq!(last.generation(), GENERATION_MASK);
+
+        world.despawn(last);
+
+        // Every generation of index 0 has been handed out; no handle to
+        // it can come back to life.
+        let next = world.spawn().unwrap();
+        assert_eq!(next.index(), 1);
+        assert!(!world.is_alive(first));
+        assert!(!world.is_alive(last));
+        assert_eq!(world.entity(first.index()), None);
+        assert_eq!(world.len(), 1);
+
+        world.despawn(next);
+        assert_eq!(world.len(), 0);
+        assert!(world.is_empty());
+    }
+
+    #[test]
+    fn spawn_fails_once_every_index_is_used() {
+        let mut world = World::new();
+
+        for _ in 0..=INDEX_MASK {
+            world.spawn().unwrap();
+        }
+        assert_eq!(world.spawn(), Err(EntityLimit));
+
+        assert_eq!(world.spawn_capacity(), 0);
+
+        // Freeing a slot makes room again.
+        world.despawn(world.entity(7).unwrap());
+        assert_eq!(world.spawn_capacity(), 1);
+        assert_eq!(world.spawn().map(Entity::index), Ok(7));
+    }
+
+    #[test]
+    fn reserved_entities_spawn_in_order() {
+        let mut world = World::new();
+        let [a, b, _] = [(); 3].map(|_| world.spawn().unwrap());
+        world.despawn(a);
+        world.despawn(b);
+
+        let first = world.reserve().unwrap();
+        let second = world.reserve().unwrap();
+        let third = world.reserve().unwrap();
+
+        assert_eq!([first.index(), second.index(), third.index()], [1, 0, 3]);
+        assert!(!world.is_alive(first));
+        assert_eq!(world.spawn_capacity(), INDEX_MASK as usize + 1 - 4);
+
+        let next = world.spawn().unwrap();
+
+        assert!(world.is_alive(first) && world.is_alive(second) && world.is_alive(third));
+        assert_eq!(next.index(), 4);
+        assert_eq!(world.len(), 5);
+        assert!(!world.is_alive(a));
+    }
+}